@@ -0,0 +1,9 @@
+//! Same as `generative_nodes --headless`, but doesn't need the `gui` feature.
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(error) = generative_nodes::headless::run(&args) {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+}
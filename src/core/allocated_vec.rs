@@ -1,8 +1,36 @@
 use std::slice::{Iter, IterMut};
 
+/// Identifies a slot in an [AllocatedVec]. A slot's generation is incremented every time its value is removed, so a
+/// handle to a removed value can't be used to access whatever value later reuses the slot.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Handle {
+    index: usize,
+    generation: u32,
+}
+
+impl Handle {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
 pub struct AllocatedVec<T> {
     vec: Vec<Option<T>>,
     free: Vec<usize>,
+    /// Parallel to [vec]. Stores the current generation of each slot.
+    generations: Vec<u32>,
+    /// Parallel to [vec]. Stores the revision each slot was last changed at, as set by [AllocatedVec::set_revision].
+    revisions: Vec<u64>,
+}
+
+impl<T> Default for AllocatedVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T> AllocatedVec<T> {
@@ -10,59 +38,127 @@ impl<T> AllocatedVec<T> {
         AllocatedVec {
             vec: Vec::new(),
             free: Vec::new(),
+            generations: Vec::new(),
+            revisions: Vec::new(),
         }
     }
 
-    pub fn push(&mut self, value: T) -> usize {
+    pub fn push(&mut self, value: T) -> Handle {
         match self.free.pop() {
             Some(index) => {
-                self.set(index, Some(value));
-                index
+                self.vec[index] = Some(value);
+                Handle {
+                    index,
+                    generation: self.generations[index],
+                }
             },
             None => {
                 let index = self.vec.len();
                 self.vec.push(Some(value));
-                index
+                self.generations.push(0);
+                self.revisions.push(0);
+                Handle {
+                    index,
+                    generation: 0,
+                }
             },
         }
     }
 
-    pub fn set(&mut self, index: usize, value: Option<T>) {
-        if index < self.vec.len() {
-            self.vec[index] = value;
-        }
+    /// Returns true if [handle] refers to a value which hasn't been removed.
+    pub fn contains(&self, handle: Handle) -> bool {
+        matches!(self.vec.get(handle.index), Some(Some(_))) && self.generations[handle.index] == handle.generation
     }
 
-    pub fn get(&self, index: usize) -> Option<&T> {
+    /// Returns the handle of the value currently stored at [index], if any.
+    pub fn handle_at(&self, index: usize) -> Option<Handle> {
         match self.vec.get(index) {
-            Some(value) => value.as_ref(),
-            None => None
+            Some(Some(_)) => Some(Handle {
+                index,
+                generation: self.generations[index],
+            }),
+            _ => None,
         }
     }
 
-    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
-        match self.vec.get_mut(index) {
-            Some(value) => value.as_mut(),
-            None => None
+    pub fn set(&mut self, handle: Handle, value: T) {
+        if self.contains(handle) {
+            self.vec[handle.index] = Some(value);
         }
     }
 
-    pub fn remove(&mut self, index: usize) -> Option<T> {
-        let old_value = self.vec.remove(index);
-        self.vec.insert(index, None);
-        self.free.push(index);
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        if self.contains(handle) {
+            self.vec[handle.index].as_ref()
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        if self.contains(handle) {
+            self.vec[handle.index].as_mut()
+        } else {
+            None
+        }
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        if !self.contains(handle) {
+            return None;
+        }
+        let old_value = self.vec[handle.index].take();
+        self.generations[handle.index] = self.generations[handle.index].wrapping_add(1);
+        self.free.push(handle.index);
         self.free.sort_unstable_by(|a, b| b.cmp(a));
         old_value
     }
 
+    /// Puts a removed value back into its old slot, making [handle] valid again. Fails if the slot has been reused
+    /// since, in which case [value] is given back.
+    pub fn restore(&mut self, handle: Handle, value: T) -> Result<(), T> {
+        let is_free = matches!(self.vec.get(handle.index), Some(None))
+            && self.generations[handle.index] == handle.generation.wrapping_add(1);
+        if !is_free {
+            return Err(value);
+        }
+        self.free.retain(|index| *index != handle.index);
+        self.generations[handle.index] = handle.generation;
+        self.vec[handle.index] = Some(value);
+        Ok(())
+    }
+
+    pub fn revision(&self, handle: Handle) -> Option<u64> {
+        if self.contains(handle) {
+            Some(self.revisions[handle.index])
+        } else {
+            None
+        }
+    }
+
+    pub fn set_revision(&mut self, handle: Handle, revision: u64) {
+        if self.contains(handle) {
+            self.revisions[handle.index] = revision;
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.vec.len() - self.free.len()
     }
-    
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn raw_len(&self) -> usize {
         self.vec.len()
     }
 
+    /// Iterates over the handles of all values which haven't been removed.
+    pub fn handles(&self) -> impl Iterator<Item = Handle> + '_ {
+        (0..self.vec.len()).filter_map(move |index| self.handle_at(index))
+    }
+
     pub fn iter(&self) -> Iter<'_, Option<T>> {
         self.vec.iter()
     }
@@ -70,4 +166,66 @@ impl<T> AllocatedVec<T> {
     pub fn iter_mut(&mut self) -> IterMut<'_, Option<T>> {
         self.vec.iter_mut()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_handle_is_stale() {
+        let mut values = AllocatedVec::new();
+        let a = values.push("a");
+        values.remove(a);
+        let b = values.push("b");
+
+        // The slot is reused, but the old handle doesn't refer to the new value.
+        assert_eq!(a.index(), b.index());
+        assert_ne!(a.generation(), b.generation());
+        assert!(!values.contains(a));
+        assert_eq!(values.get(a), None);
+        assert_eq!(values.remove(a), None);
+        values.set(a, "c");
+        assert_eq!(values.get(b), Some(&"b"));
+    }
+
+    #[test]
+    fn lowest_free_slot_is_reused_first() {
+        let mut values = AllocatedVec::new();
+        let handles: Vec<Handle> = (0..4).map(|value| values.push(value)).collect();
+        values.remove(handles[3]);
+        values.remove(handles[1]);
+
+        assert_eq!(values.len(), 2);
+        assert_eq!(values.raw_len(), 4);
+        assert_eq!(values.push(10).index(), 1);
+        assert_eq!(values.push(11).index(), 3);
+        assert_eq!(values.handles().count(), 4);
+    }
+
+    #[test]
+    fn restore_only_into_unused_slot() {
+        let mut values = AllocatedVec::new();
+        let a = values.push("a");
+        values.remove(a);
+        assert_eq!(values.restore(a, "a"), Ok(()));
+        assert_eq!(values.get(a), Some(&"a"));
+        // Restoring a value which is still there fails.
+        assert_eq!(values.restore(a, "b"), Err("b"));
+
+        values.remove(a);
+        let b = values.push("b");
+        assert_eq!(values.restore(a, "a"), Err("a"));
+        assert_eq!(values.get(b), Some(&"b"));
+    }
+
+    #[test]
+    fn revision_belongs_to_handle() {
+        let mut values = AllocatedVec::new();
+        let a = values.push(1);
+        values.set_revision(a, 5);
+        assert_eq!(values.revision(a), Some(5));
+        values.remove(a);
+        assert_eq!(values.revision(a), None);
+    }
+}
@@ -1,8 +1,16 @@
-use std::collections::HashMap;
+use std::{
+    any::TypeId,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    error::Error,
+    fmt,
+};
 
-use crate::core::{AllocatedVec, Cache, Node};
+use crate::core::{AllocatedVec, Cache, CacheIndex, Clock, Conversion, Converter, Direction, Edit, FeedbackType, History, InvalidTimestep, Migration, Node, NodeId, Port, TypeName, ValueSerializer};
 
 /// Used to determine the compute order of nodes.
+/// [EdgeType::Post] edges are feedback edges. They are ignored when sorting, and the receiving node reads the value
+/// the sending node produced on the previous frame from a double-buffered slot in the [Cache].
 #[derive(Clone)]
 pub enum EdgeType {
     Normal,
@@ -11,11 +19,99 @@ pub enum EdgeType {
 
 #[derive(Clone)]
 pub struct Edge {
-    pub(crate) from_node: usize,
+    pub(crate) from_node: NodeId,
     pub(crate) from_port: usize,
-    pub(crate) to_node: usize,
+    pub(crate) to_node: NodeId,
     pub(crate) to_port: usize,
     pub(crate) edge_type: EdgeType,
+    /// The [Cache] slot the receiving port is actually connected to if this is an [EdgeType::Post] edge. If the ports
+    /// are different types, the port's [Conversion] reads from here instead.
+    pub(crate) feedback_buffer: Option<CacheIndex>,
+}
+
+/// Returned when an edge can't be added. The [fmt::Display] implementation is intended to be shown to the user.
+#[derive(Debug)]
+pub enum EdgeError {
+    NodeNotFound(NodeId),
+    PortNotFound(NodeId, usize),
+    WrongDirection {
+        port_name: String,
+        expected: Direction,
+    },
+    /// The type names are those returned by [App::get_type_name].
+    TypeMismatch {
+        from_port_name: String,
+        from_type_name: String,
+        to_port_name: String,
+        to_type_name: String,
+    },
+    /// The output's type hasn't been registered with [App::with_feedback_types], so it can't be read one frame late.
+    NoFeedback {
+        from_port_name: String,
+        from_type_name: String,
+    },
+    /// The [Converter] between the ports' types couldn't convert the output's value.
+    ConversionFailed {
+        from_port_name: String,
+        to_port_name: String,
+    },
+}
+
+impl fmt::Display for EdgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EdgeError::NodeNotFound(_) => write!(f, "Node no longer exists"),
+            EdgeError::PortNotFound(_, port) => write!(f, "Node has no port {}", port),
+            EdgeError::WrongDirection { port_name, expected } => match expected {
+                Direction::Input => write!(f, "'{}' is not an input", port_name),
+                Direction::Output => write!(f, "'{}' is not an output", port_name),
+            },
+            EdgeError::TypeMismatch {
+                from_port_name,
+                from_type_name,
+                to_port_name,
+                to_type_name,
+            } => write!(
+                f,
+                "Can't connect '{}' ({}) to '{}' ({})",
+                from_port_name, from_type_name, to_port_name, to_type_name
+            ),
+            EdgeError::NoFeedback {
+                from_port_name,
+                from_type_name,
+            } => write!(
+                f,
+                "'{}' ({}) can't be part of a feedback loop",
+                from_port_name, from_type_name
+            ),
+            EdgeError::ConversionFailed {
+                from_port_name,
+                to_port_name,
+            } => write!(f, "Couldn't convert '{}' for '{}'", from_port_name, to_port_name),
+        }
+    }
+}
+
+impl Error for EdgeError {}
+
+/// Strips module paths from a type name, used for types without a [TypeName]. E.g. "alloc::vec::Vec<core::option::Option<f64>>" becomes
+/// "Vec<Option<f64>>".
+fn short_type_name(type_name: &str) -> String {
+    let mut short_name = String::new();
+    let mut segment_start = 0;
+    for (index, character) in type_name.char_indices() {
+        match character {
+            ':' => segment_start = index + 1,
+            '<' | '>' | ',' | ' ' | '(' | ')' | '[' | ']' | ';' | '&' => {
+                short_name.push_str(&type_name[segment_start..index]);
+                short_name.push(character);
+                segment_start = index + 1;
+            }
+            _ => (),
+        }
+    }
+    short_name.push_str(&type_name[segment_start..]);
+    short_name
 }
 
 pub struct App {
@@ -23,6 +119,25 @@ pub struct App {
     nodes: AllocatedVec<Node>,
     edges: Vec<Edge>,
     factories: HashMap<&'static str, fn(&mut Cache) -> Node>,
+    converters: HashMap<(TypeId, TypeId), Converter>,
+    serializers: HashMap<TypeId, ValueSerializer>,
+    type_names: HashMap<TypeId, &'static str>,
+    migrations: Vec<Migration>,
+    /// Where each node is shown in the graph editor.
+    positions: HashMap<NodeId, (f64, f64)>,
+    history: History,
+    clock: Clock,
+    /// Passed to every node through the [FrameContext], see [App::set_seed].
+    seed: u64,
+    /// All nodes, sorted such that each node comes after every node it depends on.
+    /// Only rebuilt when nodes or edges are added/removed.
+    compute_order: Vec<NodeId>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl App {
@@ -32,6 +147,15 @@ impl App {
             nodes: AllocatedVec::new(),
             edges: Vec::new(),
             factories: HashMap::new(),
+            converters: HashMap::new(),
+            serializers: HashMap::new(),
+            type_names: HashMap::new(),
+            migrations: Vec::new(),
+            positions: HashMap::new(),
+            history: History::new(),
+            clock: Clock::new(1. / 60.),
+            seed: 0,
+            compute_order: Vec::new(),
         }
     }
 
@@ -40,79 +164,354 @@ impl App {
         self
     }
 
-    pub fn add_node(&mut self, node_factory_index: &'static str) -> usize {
-        self.nodes
-            .push((self.factories[node_factory_index])(&mut self.cache))
+    pub fn with_feedback_types(mut self, feedback_types: Vec<FeedbackType>) -> Self {
+        for feedback_type in feedback_types {
+            self.cache.register_feedback_type(feedback_type);
+        }
+        self
+    }
+
+    /// Sets the length of a simulation step in seconds. Defaults to 1/60.
+    pub fn with_timestep(mut self, timestep: f64) -> Result<Self, InvalidTimestep> {
+        self.clock.set_timestep(timestep)?;
+        Ok(self)
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// When an edge is added between two ports of different types, a [Converter] between those types is used if
+    /// one is available.
+    pub fn with_converters(mut self, converters: Vec<Converter>) -> Self {
+        for converter in converters {
+            self.converters.insert((*converter.from(), *converter.to()), converter);
+        }
+        self
+    }
+
+    /// Values of types without a [ValueSerializer] are left out when saving, and keep their defaults when loading.
+    pub fn with_serializers(mut self, serializers: Vec<ValueSerializer>) -> Self {
+        for serializer in serializers {
+            self.serializers.insert(*serializer.type_id(), serializer);
+        }
+        self
+    }
+
+    /// Types without a [TypeName] are shown by their name in Rust, without module paths.
+    pub fn with_type_names(mut self, type_names: Vec<TypeName>) -> Self {
+        for type_name in type_names {
+            self.type_names.insert(*type_name.type_id(), type_name.name());
+        }
+        self
+    }
+
+    /// [migrations] are run in order on documents saved with older versions of the file format. Adding a migration
+    /// increases the version of any documents saved afterwards. See [App::load].
+    pub fn with_migrations(mut self, migrations: Vec<Migration>) -> Self {
+        self.migrations = migrations;
+        self
+    }
+
+    pub fn add_node(&mut self, node_factory_index: &'static str) -> NodeId {
+        let mut node = (self.factories[node_factory_index])(&mut self.cache);
+        node.set_factory_name(node_factory_index);
+        // Above every other node's, so that it stays unique however nodes are added, removed and loaded.
+        node.set_salt(self.nodes.iter().flatten().map(|node| node.get_salt() + 1).max().unwrap_or(0));
+        node.init(&mut self.cache);
+        let node_id = self.nodes.push(node);
+        self.update_compute_order();
+        node_id
+    }
+
+    /// Creates a new node in the slot a removed node used to be in, so that [node_id] refers to it again. Falls back
+    /// to a new slot if that one has been reused since.
+    pub(crate) fn restore_node(&mut self, node_factory_index: &'static str, node_id: NodeId, salt: u64) -> NodeId {
+        let mut node = (self.factories[node_factory_index])(&mut self.cache);
+        node.set_factory_name(node_factory_index);
+        node.set_salt(salt);
+        node.init(&mut self.cache);
+        let node_id = match self.nodes.restore(node_id, node) {
+            Ok(()) => node_id,
+            Err(node) => self.nodes.push(node),
+        };
+        self.update_compute_order();
+        node_id
     }
 
-    pub fn remove_node(&mut self, node_index: usize) {
-        let mut removed_node = self.nodes.remove(node_index).unwrap();
+    pub fn remove_node(&mut self, node_id: NodeId) {
+        if !self.nodes.contains(node_id) {
+            return;
+        }
 
+        // Edges have to be removed first so that the node's inputs are reset to their defaults. Otherwise
+        // remove_all_cache would remove the outputs of any connected nodes instead.
         for index in (0..self.edges.len()).rev() {
             let edge = self.edges.get(index).unwrap();
-            if edge.from_node == node_index || edge.to_node == node_index {
+            if edge.from_node == node_id || edge.to_node == node_id {
                 self.remove_edge(index);
             }
         }
 
+        let mut removed_node = self.nodes.remove(node_id).unwrap();
         removed_node.remove_all_cache(&mut self.cache);
+        self.positions.remove(&node_id);
+        self.update_compute_order();
+    }
+
+    /// Removes every node and edge.
+    pub fn clear(&mut self) {
+        let node_ids: Vec<NodeId> = self.nodes.handles().collect();
+        for node_id in node_ids {
+            self.remove_node(node_id);
+        }
+    }
+
+    /// Returns the key of the factory with the given name, if there is one.
+    pub fn find_factory(&self, node_type: &str) -> Option<&'static str> {
+        self.factories.get_key_value(node_type).map(|(key, _)| *key)
+    }
+
+    /// Replaces the salt given to the node by [App::add_node], e.g. with the one it was saved with.
+    pub(crate) fn set_salt(&mut self, node_id: NodeId, salt: u64) {
+        if let Some(node) = self.nodes.get_mut(node_id) {
+            node.set_salt(salt);
+        }
+    }
+
+    pub fn get_position(&self, node_id: NodeId) -> Option<(f64, f64)> {
+        self.positions.get(&node_id).copied()
+    }
+
+    /// Doesn't record anything in the undo history, see [Edit::MoveNode] for that.
+    pub fn set_position(&mut self, node_id: NodeId, position: (f64, f64)) {
+        if self.nodes.contains(node_id) {
+            self.positions.insert(node_id, position);
+        }
+    }
+
+    /// Sets the value of a port, recording the change in the undo history if there is a [ValueSerializer] for [T].
+    /// Connected inputs can't be changed this way, as their value belongs to another node.
+    pub fn set_value<T: 'static>(&mut self, node_id: NodeId, port_index: usize, value: T) {
+        let port = match self.get_port(node_id, port_index) {
+            Ok(port) => port,
+            Err(_) => return,
+        };
+        if port.is_connected() && port.get_direction() == &Direction::Input {
+            return;
+        }
+        let cache_index = port.get_cache_index().clone();
+
+        let old_value = self
+            .serializers
+            .get(cache_index.type_id())
+            .and_then(|serializer| serializer.save(&self.cache, &cache_index));
+        self.cache.set(&cache_index, value);
+        let dropped_edges = self.update_ports(node_id);
+        if let Some(old_value) = old_value {
+            let mut undo_edits = vec![Edit::SetValue {
+                node_id,
+                port: port_index,
+                value: old_value,
+            }];
+            undo_edits.extend(dropped_edges.into_iter().map(Edit::Connect));
+            self.record(undo_edits);
+        }
+    }
+
+    /// Returns None if the node has been removed.
+    pub fn get_node(&self, node_id: NodeId) -> Option<&Node> {
+        self.nodes.get(node_id)
+    }
+
+    /// Connects an output to an input, replacing any edge already connected to the input.
+    /// If the connection would create a cycle it is made into a feedback edge (see [App::add_feedback_edge]).
+    pub fn add_edge(
+        &mut self,
+        from_node: NodeId,
+        from_port: usize,
+        to_node: NodeId,
+        to_port: usize,
+    ) -> Result<(), EdgeError> {
+        self.check_edge(from_node, from_port, to_node, to_port)?;
+
+        let edge_type = if self.depends_on(from_node, to_node) {
+            self.check_feedback(from_node, from_port)?;
+            EdgeType::Post
+        } else {
+            EdgeType::Normal
+        };
+
+        self.remove_edge_to(to_node, to_port);
+        self.connect(from_node, from_port, to_node, to_port, edge_type)
+    }
+
+    /// Connects an output to an input such that the input always receives the value the output had on the previous
+    /// frame, regardless of compute order. Used for trails, accumulators and other recursive patterns. Fails if the
+    /// output's type hasn't been registered with [App::with_feedback_types].
+    pub fn add_feedback_edge(
+        &mut self,
+        from_node: NodeId,
+        from_port: usize,
+        to_node: NodeId,
+        to_port: usize,
+    ) -> Result<(), EdgeError> {
+        self.check_edge(from_node, from_port, to_node, to_port)?;
+        self.check_feedback(from_node, from_port)?;
+        self.remove_edge_to(to_node, to_port);
+        self.connect(from_node, from_port, to_node, to_port, EdgeType::Post)
+    }
+
+    /// Makes sure both ports exist, are facing the right way and hold the same type of value, or that there is a
+    /// [Converter] between their types.
+    fn check_edge(
+        &self,
+        from_node: NodeId,
+        from_port: usize,
+        to_node: NodeId,
+        to_port: usize,
+    ) -> Result<(), EdgeError> {
+        let output = self.get_port(from_node, from_port)?;
+        let input = self.get_port(to_node, to_port)?;
+
+        if output.get_direction() != &Direction::Output {
+            return Err(EdgeError::WrongDirection {
+                port_name: output.get_name().to_string(),
+                expected: Direction::Output,
+            });
+        }
+        if input.get_direction() != &Direction::Input {
+            return Err(EdgeError::WrongDirection {
+                port_name: input.get_name().to_string(),
+                expected: Direction::Input,
+            });
+        }
+        if output.get_type_id() != input.get_type_id()
+            && !self
+                .converters
+                .contains_key(&(*output.get_type_id(), *input.get_type_id()))
+        {
+            return Err(EdgeError::TypeMismatch {
+                from_port_name: output.get_name().to_string(),
+                from_type_name: self.get_type_name(output),
+                to_port_name: input.get_name().to_string(),
+                to_type_name: self.get_type_name(input),
+            });
+        }
+        Ok(())
+    }
+
+    /// Makes sure the output can be double-buffered, as otherwise the value read through a feedback edge would depend
+    /// on whether or not the sending node has been computed yet this frame.
+    fn check_feedback(&self, from_node: NodeId, from_port: usize) -> Result<(), EdgeError> {
+        let output = self.get_port(from_node, from_port)?;
+        if self.cache.is_feedback_type(output.get_type_id()) {
+            Ok(())
+        } else {
+            Err(EdgeError::NoFeedback {
+                from_port_name: output.get_name().to_string(),
+                from_type_name: self.get_type_name(output),
+            })
+        }
     }
 
-    pub fn get_node(&self, node_index: usize) -> &Node {
-        &self.nodes.get(node_index).unwrap()
+    /// The name of the type of value [port] holds, as shown to the user. See [App::with_type_names].
+    pub fn get_type_name(&self, port: &Port) -> String {
+        match self.type_names.get(port.get_type_id()) {
+            Some(name) => name.to_string(),
+            None => short_type_name(port.get_type_name()),
+        }
     }
 
-    // TODO: Rewrite this and split it into separate and more specified private methods to make this code more readable.
-    pub fn add_edge(&mut self, from_node: usize, from_port: usize, to_node: usize, to_port: usize) {
-        let new_cache_index = self
+    fn get_port(&self, node_id: NodeId, port_index: usize) -> Result<&Port, EdgeError> {
+        self.nodes
+            .get(node_id)
+            .ok_or(EdgeError::NodeNotFound(node_id))?
+            .get_ports()
+            .get(port_index)
+            .ok_or(EdgeError::PortNotFound(node_id, port_index))
+    }
+
+    fn connect(
+        &mut self,
+        from_node: NodeId,
+        from_port: usize,
+        to_node: NodeId,
+        to_port: usize,
+        edge_type: EdgeType,
+    ) -> Result<(), EdgeError> {
+        let output_cache_index = self
             .nodes
             .get(from_node)
             .unwrap()
             .get_output(from_port)
             .unwrap()
             .clone();
-        let node = self.nodes.get_mut(to_node).unwrap();
 
-        if let Some(old_edge_index) = self
-            .edges
-            .iter()
-            .position(|edge| edge.to_node == to_node && edge.to_port == to_port)
-        {
-            let edge = self.edges.remove(old_edge_index);
-            node.disconnect_input(to_port, &mut self.cache);
-        }
-
-        node.connect_input(to_port, new_cache_index, &mut self.cache);
+        // Only ever None for types which can't be double-buffered, which [App::check_feedback] rules out.
+        let feedback_buffer = match edge_type {
+            EdgeType::Post => self.cache.insert_feedback(&output_cache_index),
+            EdgeType::Normal => None,
+        };
+        let source = feedback_buffer.clone().unwrap_or(output_cache_index);
 
-        let edge_type = if self
-            .edges
-            .iter()
-            .any(|edge| edge.from_node == to_node && edge.to_node == from_node)
-        {
-            EdgeType::Post
+        let input_type_id = *self.get_port(to_node, to_port).unwrap().get_type_id();
+        let conversion = if source.type_id() != &input_type_id {
+            let converter = (*source.type_id(), input_type_id);
+            match self.converters[&converter].create(&mut self.cache, &source) {
+                Some(converted) => Some(Conversion::new(converter, source.clone(), converted)),
+                None => {
+                    if let Some(feedback_buffer) = &feedback_buffer {
+                        self.cache.remove_feedback(feedback_buffer);
+                    }
+                    return Err(EdgeError::ConversionFailed {
+                        from_port_name: self.get_port(from_node, from_port)?.get_name().to_string(),
+                        to_port_name: self.get_port(to_node, to_port)?.get_name().to_string(),
+                    });
+                }
+            }
         } else {
-            EdgeType::Normal
+            None
+        };
+        let new_cache_index = match &conversion {
+            Some(conversion) => conversion.converted().clone(),
+            None => source,
         };
 
+        let node = self.nodes.get_mut(to_node).unwrap();
+        node.connect_input(to_port, new_cache_index, &mut self.cache);
+        if let Some(conversion) = conversion {
+            node.set_conversion(to_port, conversion);
+        }
+
         self.edges.push(Edge {
             from_node,
             from_port,
             to_node,
             to_port,
             edge_type,
+            feedback_buffer,
         });
+        self.update_compute_order();
+        Ok(())
     }
 
     fn remove_edge(&mut self, index: usize) {
         let edge = self.edges.remove(index);
-        self.nodes
-            .get_mut(edge.to_node)
-            .unwrap()
-            .disconnect_input(edge.to_port, &mut self.cache);
-        println!("new edge count: {}", self.edges.len());
+        let node = self.nodes.get_mut(edge.to_node).unwrap();
+        let conversion = node.take_conversion(edge.to_port);
+        node.disconnect_input(edge.to_port, &mut self.cache);
+        if let Some(conversion) = conversion {
+            self.cache.remove_any(conversion.converted());
+        }
+        if let Some(feedback_buffer) = edge.feedback_buffer {
+            self.cache.remove_feedback(&feedback_buffer);
+        }
+        self.update_compute_order();
     }
 
-    pub fn remove_edge_to(&mut self, to_node: usize, to_port: usize) {
+    pub fn remove_edge_to(&mut self, to_node: NodeId, to_port: usize) {
         let edge_index = self
             .edges
             .iter()
@@ -123,7 +522,7 @@ impl App {
         }
     }
 
-    pub fn remove_edges_from(&mut self, from_node: usize, from_port: usize) {
+    pub fn remove_edges_from(&mut self, from_node: NodeId, from_port: usize) {
         for index in (0..self.edges.len()).rev() {
             let edge = self.edges.get(index).unwrap();
             if edge.from_node == from_node && edge.from_port == from_port {
@@ -148,10 +547,389 @@ impl App {
         &mut self.cache
     }
 
+    /// Overwrites the value at [cache_index] using the [ValueSerializer] for its type. Returns false if there is no
+    /// such serializer or [value] couldn't be deserialised.
+    pub fn deserialize_value(&mut self, cache_index: &CacheIndex, value: serde_json::Value) -> bool {
+        match self.serializers.get(cache_index.type_id()) {
+            Some(serializer) => serializer.load(&mut self.cache, cache_index, value),
+            None => false,
+        }
+    }
+
+    /// See [NodeBehavior::refresh]. Returns false if the node doesn't exist.
+    pub(crate) fn refresh_node(&mut self, node_id: NodeId) -> bool {
+        match self.nodes.get_mut(node_id) {
+            Some(node) => node.refresh(&self.cache),
+            None => false,
+        }
+    }
+
+    /// Must only be called while none of the node's ports are connected, see [App::update_ports].
+    pub(crate) fn update_node_ports(&mut self, node_id: NodeId) {
+        if let Some(node) = self.nodes.get_mut(node_id) {
+            node.update_ports(&mut self.cache);
+        }
+    }
+
+    pub(crate) fn serializers(&self) -> &HashMap<TypeId, ValueSerializer> {
+        &self.serializers
+    }
+
+    pub(crate) fn history(&mut self) -> &mut History {
+        &mut self.history
+    }
+
+    pub(crate) fn migrations(&self) -> &Vec<Migration> {
+        &self.migrations
+    }
+
+    pub fn compute_order(&self) -> &Vec<NodeId> {
+        &self.compute_order
+    }
+
+    pub fn get_clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub fn get_clock_mut(&mut self) -> &mut Clock {
+        &mut self.clock
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Changes every random value in the graph. Saved along with the graph, so that it renders the same way when
+    /// loaded again.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        for node in self.nodes.iter_mut().flatten() {
+            node.invalidate();
+        }
+    }
+
+    pub fn play(&mut self) {
+        self.clock.set_playing(true);
+    }
+
+    pub fn pause(&mut self) {
+        self.clock.set_playing(false);
+    }
+
+    pub fn toggle_playback(&mut self) {
+        self.clock.set_playing(!self.clock.is_playing());
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.clock.is_playing()
+    }
+
+    /// Sets how fast simulated time passes compared to real time, e.g. 2 for double speed.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.clock.set_speed(speed);
+    }
+
+    /// Computes a single step, whether or not the simulation is playing.
+    pub fn step(&mut self) {
+        self.compute();
+    }
+
+    /// Rewinds the simulation to its initial state by re-running every node's init, which puts outputs and any
+    /// internal state back to how they started, and resetting the [Clock]. Feedback edges then read the initial value
+    /// of their source, rather than whatever it was before the reset. The graph itself and any values set by the user
+    /// are kept.
+    pub fn reset(&mut self) {
+        for node in self.nodes.iter_mut().flatten() {
+            node.init(&mut self.cache);
+        }
+        self.cache.update_feedback();
+        self.clock.reset();
+    }
+
+    /// Computes as many fixed-size steps as fit into [elapsed] seconds of real time (scaled by the playback speed),
+    /// carrying over the remainder. Returns the number of steps computed, which is always 0 while paused.
+    pub fn advance(&mut self, elapsed: f64) -> u32 {
+        let steps = self.clock.accumulate(elapsed);
+        for _ in 0..steps {
+            self.compute();
+        }
+        steps
+    }
+
+    /// Computes a single step of the simulation.
     pub fn compute(&mut self) {
-        for i in 0..self.nodes.len() {
-            let node = self.nodes.get(i).unwrap();
-            node.compute(&mut self.cache)
+        self.cache.update_feedback();
+        let context = self.clock.context(self.seed);
+
+        for node_id in &self.compute_order {
+            if let Some(node) = self.nodes.get_mut(*node_id) {
+                // Converted values are only updated right before they're needed, so that they always reflect any
+                // changes made to their source earlier in the frame.
+                for conversion in node.conversions_mut() {
+                    conversion.update(&self.converters[conversion.converter()], &mut self.cache);
+                }
+                if node.needs_compute(&self.cache) {
+                    node.compute(&mut self.cache, &context)
+                }
+            }
+        }
+
+        self.cache.next_frame();
+        self.clock.tick();
+    }
+
+    /// Returns true if [node] is downstream of [dependency], following only [EdgeType::Normal] edges.
+    /// A node is considered to depend on itself.
+    fn depends_on(&self, node: NodeId, dependency: NodeId) -> bool {
+        let mut visited = vec![false; self.nodes.raw_len()];
+        let mut stack = vec![node];
+
+        while let Some(current) = stack.pop() {
+            if current == dependency {
+                return true;
+            }
+            if visited[current.index()] {
+                continue;
+            }
+            visited[current.index()] = true;
+
+            for edge in &self.edges {
+                if edge.to_node == current && matches!(edge.edge_type, EdgeType::Normal) {
+                    stack.push(edge.from_node);
+                }
+            }
         }
+        false
+    }
+
+    /// Topologically sorts the nodes using Kahn's algorithm. [EdgeType::Post] edges are treated as back-edges and
+    /// ignored, so feedback loops don't prevent the sort from completing. Ties are broken by node index so the
+    /// order is deterministic.
+    fn update_compute_order(&mut self) {
+        let mut in_degrees = vec![0; self.nodes.raw_len()];
+        let mut dependents = vec![Vec::new(); self.nodes.raw_len()];
+
+        for edge in &self.edges {
+            if let EdgeType::Normal = edge.edge_type {
+                in_degrees[edge.to_node.index()] += 1;
+                dependents[edge.from_node.index()].push(edge.to_node.index());
+            }
+        }
+
+        let mut ready = BinaryHeap::new();
+        for node_id in self.nodes.handles() {
+            if in_degrees[node_id.index()] == 0 {
+                ready.push(Reverse(node_id.index()));
+            }
+        }
+
+        self.compute_order.clear();
+        while let Some(Reverse(index)) = ready.pop() {
+            self.compute_order.push(self.nodes.handle_at(index).unwrap());
+            for dependent in &dependents[index] {
+                in_degrees[*dependent] -= 1;
+                if in_degrees[*dependent] == 0 {
+                    ready.push(Reverse(*dependent));
+                }
+            }
+        }
+
+        // Should be unreachable as any edge which would form a cycle is marked as [EdgeType::Post], but make sure
+        // that no nodes get left out either way.
+        if self.compute_order.len() < self.nodes.len() {
+            for node_id in self.nodes.handles() {
+                if !self.compute_order.contains(&node_id) {
+                    self.compute_order.push(node_id);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+
+    use super::*;
+    use crate::nodes::{self, circle_node, common::shapes::Circle, math_node, vector_node};
+
+    // Ports of the "Add" node.
+    const A: usize = 0;
+    const B: usize = 1;
+    const RESULT: usize = 2;
+
+    fn result(app: &App, node_id: NodeId) -> f64 {
+        let port = &app.get_node(node_id).unwrap().get_ports()[RESULT];
+        *app.get_cache().get::<f64>(port.get_cache_index()).unwrap()
+    }
+
+    #[test]
+    fn compute_order_follows_edges() {
+        let mut app = nodes::new_app();
+        let c = app.add_node(math_node::add::NAME);
+        let b = app.add_node(math_node::add::NAME);
+        let a = app.add_node(math_node::add::NAME);
+        app.add_edge(a, RESULT, b, A).unwrap();
+        app.add_edge(b, RESULT, c, A).unwrap();
+        assert_eq!(app.compute_order(), &vec![a, b, c]);
+
+        app.set_value(a, A, 1.);
+        app.compute();
+        assert_eq!(result(&app, c), 1.);
+    }
+
+    #[test]
+    fn unchanged_nodes_are_skipped() {
+        let mut app = nodes::new_app();
+        let a = app.add_node(math_node::add::NAME);
+        let b = app.add_node(math_node::add::NAME);
+        app.add_edge(a, RESULT, b, A).unwrap();
+        app.set_value(a, A, 1.);
+        app.compute();
+
+        // Computing a node writes its outputs, so their revisions show whether it was computed.
+        let revisions = |app: &App| {
+            [a, b].map(|node_id| {
+                let port = &app.get_node(node_id).unwrap().get_ports()[RESULT];
+                app.get_cache().revision(port.get_cache_index()).unwrap()
+            })
+        };
+        let first = revisions(&app);
+        app.compute();
+        assert_eq!(revisions(&app), first);
+        assert!(!app.get_node(a).unwrap().needs_compute(app.get_cache()));
+        assert!(!app.get_node(b).unwrap().needs_compute(app.get_cache()));
+
+        app.set_value(a, B, 2.);
+        app.compute();
+        let second = revisions(&app);
+        assert!(second[0] > first[0] && second[1] > first[1]);
+        assert_eq!(result(&app, b), 3.);
+    }
+
+    #[test]
+    fn cycle_becomes_feedback_edge() {
+        let mut app = nodes::new_app();
+        let a = app.add_node(math_node::add::NAME);
+        let b = app.add_node(math_node::add::NAME);
+        let c = app.add_node(math_node::add::NAME);
+        app.add_edge(a, RESULT, b, A).unwrap();
+        app.add_edge(b, RESULT, c, A).unwrap();
+        app.add_edge(c, RESULT, a, A).unwrap();
+
+        assert!(matches!(app.edges()[0].edge_type, EdgeType::Normal));
+        assert!(matches!(app.edges()[1].edge_type, EdgeType::Normal));
+        assert!(matches!(app.edges()[2].edge_type, EdgeType::Post));
+        // The feedback edge doesn't take part in the order, so every node is still computed once.
+        assert_eq!(app.compute_order(), &vec![a, b, c]);
+    }
+
+    #[test]
+    fn removed_node_takes_its_edges() {
+        let mut app = nodes::new_app();
+        let a = app.add_node(math_node::add::NAME);
+        let b = app.add_node(math_node::add::NAME);
+        app.add_edge(a, RESULT, b, A).unwrap();
+        app.remove_node(a);
+
+        assert!(app.edges().is_empty());
+        assert_eq!(app.compute_order(), &vec![b]);
+        assert!(!app.get_node(b).unwrap().get_ports()[A].is_connected());
+        // A new node can reuse the slot, but not the handle.
+        let c = app.add_node(math_node::add::NAME);
+        assert_eq!(c.index(), a.index());
+        assert!(matches!(app.add_edge(a, RESULT, b, A), Err(EdgeError::NodeNotFound(_))));
+    }
+
+    #[test]
+    fn feedback_edge_reads_previous_frame() {
+        let mut app = nodes::new_app();
+        let node = app.add_node(math_node::add::NAME);
+        app.set_value(node, B, 1.);
+        // Connecting a node to itself closes a loop, so the edge becomes a feedback edge.
+        app.add_edge(node, RESULT, node, A).unwrap();
+        assert!(matches!(app.edges()[0].edge_type, EdgeType::Post));
+
+        for _ in 0..3 {
+            app.compute();
+        }
+        assert_eq!(result(&app, node), 3.);
+    }
+
+    #[test]
+    fn feedback_edge_needs_feedback_type() {
+        let mut app = App::new().with_factories(nodes::node_factories());
+        let a = app.add_node(math_node::add::NAME);
+        let b = app.add_node(math_node::add::NAME);
+        app.add_edge(a, RESULT, b, A).unwrap();
+
+        assert!(matches!(app.add_edge(b, RESULT, a, A), Err(EdgeError::NoFeedback { .. })));
+        assert!(matches!(
+            app.add_feedback_edge(a, RESULT, b, B),
+            Err(EdgeError::NoFeedback { .. })
+        ));
+        // The edge which was already there is kept.
+        assert_eq!(app.edges().len(), 1);
+    }
+
+    #[test]
+    fn reset_starts_feedback_loop_over() {
+        let mut app = nodes::new_app();
+        let node = app.add_node(math_node::add::NAME);
+        app.set_value(node, B, 1.);
+        app.add_edge(node, RESULT, node, A).unwrap();
+        for _ in 0..5 {
+            app.compute();
+        }
+        assert_eq!(result(&app, node), 5.);
+
+        app.reset();
+        assert_eq!(result(&app, node), 0.);
+        let feedback = app.get_node(node).unwrap().get_ports()[A].get_cache_index();
+        assert_eq!(app.get_cache().get::<f64>(feedback), Some(&0.));
+        assert_eq!(app.get_clock().get_frame(), 0);
+
+        app.compute();
+        assert_eq!(result(&app, node), 1.);
+    }
+
+    #[test]
+    fn type_mismatch_uses_type_names() {
+        let mut app = nodes::new_app();
+        let vector = app.add_node(vector_node::NAME);
+        let particle = app.add_node("Particle");
+        // The vector's output to the particle's "Bounds" input, which no converter exists for.
+        let error = app.add_edge(vector, 2, particle, 14).unwrap_err();
+        assert_eq!(error.to_string(), "Can't connect 'Vector2' (Vector) to 'Bounds' (Bounds)");
+    }
+
+    #[test]
+    fn short_type_name_strips_paths() {
+        assert_eq!(short_type_name("alloc::vec::Vec<core::option::Option<f64>>"), "Vec<Option<f64>>");
+        assert_eq!(short_type_name("(f64, alloc::string::String)"), "(f64, String)");
+    }
+
+    #[test]
+    fn conversion_follows_source() {
+        let mut app = nodes::new_app();
+        let add = app.add_node(math_node::add::NAME);
+        let circle = app.add_node(circle_node::NAME);
+        // A number into the circle's "Position" input is splatted into a vector.
+        app.add_edge(add, RESULT, circle, 0).unwrap();
+        let position = |app: &App| {
+            let port = &app.get_node(circle).unwrap().get_ports()[2];
+            *app.get_cache().get::<Circle>(port.get_cache_index()).unwrap().get_position()
+        };
+
+        app.set_value(add, A, 3.);
+        app.compute();
+        assert_eq!(position(&app), Vector2::new(3., 3.));
+        app.set_value(add, A, 4.);
+        app.compute();
+        assert_eq!(position(&app), Vector2::new(4., 4.));
+
+        app.remove_edge_to(circle, 0);
+        app.compute();
+        assert_eq!(position(&app), Vector2::new(0., 0.));
     }
 }
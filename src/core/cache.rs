@@ -1,25 +1,36 @@
-use std::any::TypeId;
+use std::{any::TypeId, collections::HashMap};
 
 use anymap::AnyMap;
 
-use crate::core::AllocatedVec;
+use crate::core::{AllocatedVec, Handle};
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct CacheIndex {
     type_id: TypeId,
-    index: usize
+    type_name: &'static str,
+    handle: Handle,
 }
 
 impl CacheIndex {
-    pub fn new<T: 'static>(index: usize) -> CacheIndex {
+    pub fn new<T: 'static>(handle: Handle) -> CacheIndex {
         CacheIndex {
             type_id: TypeId::of::<T>(),
-            index
+            type_name: std::any::type_name::<T>(),
+            handle,
         }
     }
 
-    pub fn index(&self) -> &usize {
-        &self.index
+    pub fn handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    pub fn type_id(&self) -> &TypeId {
+        &self.type_id
+    }
+
+    /// The full name of the type, including module paths. Only intended for debugging and error messages.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
     }
 
     pub fn is_type<T: 'static>(&self) -> bool {
@@ -27,19 +38,151 @@ impl CacheIndex {
     }
 }
 
+/// Describes how to double-buffer values of type [T] so that they can be read one frame late through feedback edges.
+/// Only types registered with [Cache::register_feedback_type] can be the source of a feedback edge.
+pub struct FeedbackType {
+    type_id: TypeId,
+    create: fn(cache: &mut Cache, source: &CacheIndex) -> Option<CacheIndex>,
+    copy: fn(cache: &mut Cache, source: &CacheIndex, buffer: &CacheIndex),
+    remove: fn(cache: &mut Cache, buffer: &CacheIndex),
+}
+
+impl FeedbackType {
+    pub fn new<T: Clone + 'static>() -> FeedbackType {
+        FeedbackType {
+            type_id: TypeId::of::<T>(),
+            create: create_feedback_buffer::<T>,
+            copy: copy_feedback_buffer::<T>,
+            remove: Cache::remove::<T>,
+        }
+    }
+}
+
+/// Monomorphised functions for a type registered with [Cache::register].
+struct TypeFunctions {
+    revision: fn(cache: &Cache, handle: Handle) -> Option<u64>,
+    remove: fn(cache: &mut Cache, cache_index: &CacheIndex),
+}
+
+fn lookup_revision<T: 'static>(cache: &Cache, handle: Handle) -> Option<u64> {
+    cache.data.get::<AllocatedVec<T>>()?.revision(handle)
+}
+
+fn create_feedback_buffer<T: Clone + 'static>(cache: &mut Cache, source: &CacheIndex) -> Option<CacheIndex> {
+    let value = cache.get::<T>(source)?.clone();
+    Some(cache.insert(value))
+}
+
+fn copy_feedback_buffer<T: Clone + 'static>(cache: &mut Cache, source: &CacheIndex, buffer: &CacheIndex) {
+    if let Some(value) = cache.get::<T>(source).cloned() {
+        cache.set(buffer, value);
+    }
+}
+
+/// A value from the [Cache] along with whether or not it has changed since a given frame.
+pub struct Tracked<T> {
+    pub value: T,
+    pub changed: bool,
+}
+
+/// A copy of [source] which is only updated at the start of each frame, and so always holds the value [source] had
+/// at the end of the previous frame.
+struct FeedbackBuffer {
+    source: CacheIndex,
+    buffer: CacheIndex,
+}
+
 pub struct Cache {
     data: AnyMap,
+    /// Incremented at the end of every frame. Any value which is changed is stamped with the current frame.
+    frame: u64,
+    /// Allows accessing values without knowing their type at compile time.
+    type_functions: HashMap<TypeId, TypeFunctions>,
+    feedback_types: HashMap<TypeId, FeedbackType>,
+    feedback_buffers: Vec<FeedbackBuffer>,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Cache {
     pub fn new() -> Cache {
         Cache {
             data: AnyMap::new(),
+            frame: 0,
+            type_functions: HashMap::new(),
+            feedback_types: HashMap::new(),
+            feedback_buffers: Vec::new(),
+        }
+    }
+
+    pub fn register_feedback_type(&mut self, feedback_type: FeedbackType) {
+        self.feedback_types.insert(feedback_type.type_id, feedback_type);
+    }
+
+    /// Returns true if values of the given type can be the source of a feedback edge.
+    pub fn is_feedback_type(&self, type_id: &TypeId) -> bool {
+        self.feedback_types.contains_key(type_id)
+    }
+
+    /// Creates a buffer holding a copy of the value at [source], which will be updated by [Cache::update_feedback].
+    /// Returns None if the type of [source] hasn't been registered with [Cache::register_feedback_type].
+    pub fn insert_feedback(&mut self, source: &CacheIndex) -> Option<CacheIndex> {
+        let create = self.feedback_types.get(source.type_id())?.create;
+        let buffer = (create)(self, source)?;
+        self.feedback_buffers.push(FeedbackBuffer {
+            source: source.clone(),
+            buffer: buffer.clone(),
+        });
+        Some(buffer)
+    }
+
+    pub fn remove_feedback(&mut self, buffer: &CacheIndex) {
+        if let Some(index) = self
+            .feedback_buffers
+            .iter()
+            .position(|feedback| &feedback.buffer == buffer)
+        {
+            self.feedback_buffers.remove(index);
+            if let Some(feedback_type) = self.feedback_types.get(buffer.type_id()) {
+                (feedback_type.remove)(self, buffer);
+            }
+        }
+    }
+
+    /// Copies the current value of every feedback source into its buffer. Should be called once at the start of each
+    /// frame, before any nodes are computed.
+    pub fn update_feedback(&mut self) {
+        for index in 0..self.feedback_buffers.len() {
+            let feedback = &self.feedback_buffers[index];
+            let (source, buffer) = (feedback.source.clone(), feedback.buffer.clone());
+            if let Some(feedback_type) = self.feedback_types.get(source.type_id()) {
+                (feedback_type.copy)(self, &source, &buffer);
+            }
         }
     }
 
     pub fn register<T: 'static>(&mut self) {
         self.data.insert(AllocatedVec::<T>::new());
+        self.type_functions.insert(
+            TypeId::of::<T>(),
+            TypeFunctions {
+                revision: lookup_revision::<T>,
+                remove: Cache::remove::<T>,
+            },
+        );
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Should be called once every node has been computed for the current frame.
+    pub fn next_frame(&mut self) {
+        self.frame += 1;
     }
 
     pub fn insert<T: 'static>(&mut self, value: T) -> CacheIndex {
@@ -47,42 +190,79 @@ impl Cache {
             self.register::<T>();
         }
         let vec = self.data.get_mut::<AllocatedVec<T>>().unwrap();
-        vec.push(value);
-        CacheIndex::new::<T>(vec.len() - 1)
+        let handle = vec.push(value);
+        vec.set_revision(handle, self.frame);
+        CacheIndex::new::<T>(handle)
     }
 
-    // TODO: Have this function and any other "get" functions return an struct which contains not only the cached [T],
-    // but also a bool declaring whether or not this cache has changed. 
-    // This would make optimising the compute() functions of nodes easier.
-    // [AllocVec] might be the right place for this info to be stored. Maybe in a parallel [Vector].
     pub fn get<T: 'static>(&self, cache_index: &CacheIndex) -> Option<&T> {
         if cache_index.is_type::<T>() {
             if let Some(vec) = self.data.get::<AllocatedVec<T>>() {
-                return vec.get(*cache_index.index());
+                return vec.get(*cache_index.handle());
             }
         }
         None
     }
 
+    /// Same as [Cache::get], but also returns whether or not the value was changed after [frame].
+    pub fn get_since<T: 'static>(&self, cache_index: &CacheIndex, frame: u64) -> Option<Tracked<&T>> {
+        let value = self.get::<T>(cache_index)?;
+        Some(Tracked {
+            value,
+            changed: self.has_changed_since(cache_index, frame),
+        })
+    }
+
+    /// Marks the value as changed on the current frame, regardless of whether or not it is actually modified.
     pub fn get_mut<T: 'static>(&mut self, cache_index: &CacheIndex) -> Option<&mut T> {
         if cache_index.is_type::<T>() {
             if let Some(vec) = self.data.get_mut::<AllocatedVec<T>>() {
-                return vec.get_mut(*cache_index.index());
+                vec.set_revision(*cache_index.handle(), self.frame);
+                return vec.get_mut(*cache_index.handle());
             }
         }
         None
     }
 
+    /// Returns the frame the value was last changed on, or None if there is no value at [cache_index].
+    pub fn revision(&self, cache_index: &CacheIndex) -> Option<u64> {
+        let lookup = self.type_functions.get(cache_index.type_id())?.revision;
+        (lookup)(self, *cache_index.handle())
+    }
+
+    /// Missing values are always considered to have changed.
+    pub fn has_changed_since(&self, cache_index: &CacheIndex, frame: u64) -> bool {
+        match self.revision(cache_index) {
+            Some(revision) => revision > frame,
+            None => true,
+        }
+    }
+
     pub fn get_all_of_type<T: 'static>(&self) -> Option<&AllocatedVec<T>> {
         self.data.get::<AllocatedVec<T>>()
     }
 
+    /// Returns true if [cache_index] is a buffer created by [Cache::insert_feedback].
+    pub fn is_feedback_buffer(&self, cache_index: &CacheIndex) -> bool {
+        self.feedback_buffers.iter().any(|feedback| &feedback.buffer == cache_index)
+    }
+
+    /// Every value of type [T], except for feedback buffers as they're only copies of other values. Used to draw
+    /// everything of a type without drawing anything fed back twice.
+    pub fn values_of_type<T: 'static>(&self) -> impl Iterator<Item = &T> + '_ {
+        self.get_all_of_type::<T>().into_iter().flat_map(move |values| {
+            values
+                .handles()
+                .filter(move |handle| !self.is_feedback_buffer(&CacheIndex::new::<T>(*handle)))
+                .filter_map(move |handle| values.get(handle))
+        })
+    }
+
     pub fn set<T: 'static>(&mut self, cache_index: &CacheIndex, new_value: T) {
         if cache_index.is_type::<T>() {
-            self.data
-                .get_mut::<AllocatedVec<T>>()
-                .unwrap()
-                .set(*cache_index.index(), Some(new_value))
+            let vec = self.data.get_mut::<AllocatedVec<T>>().unwrap();
+            vec.set(*cache_index.handle(), new_value);
+            vec.set_revision(*cache_index.handle(), self.frame);
         }
     }
 
@@ -91,7 +271,72 @@ impl Cache {
             self.data
                 .get_mut::<AllocatedVec<T>>()
                 .unwrap()
-                .remove(*cache_index.index());
+                .remove(*cache_index.handle());
+        }
+    }
+
+    /// Same as [Cache::remove], but for when the type of the value isn't known at compile time.
+    pub fn remove_any(&mut self, cache_index: &CacheIndex) {
+        if let Some(type_functions) = self.type_functions.get(cache_index.type_id()) {
+            (type_functions.remove)(self, cache_index);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feedback_buffer_lags_one_frame() {
+        let mut cache = Cache::new();
+        cache.register_feedback_type(FeedbackType::new::<f64>());
+        let source = cache.insert(1.);
+        let buffer = cache.insert_feedback(&source).unwrap();
+        assert!(cache.is_feedback_buffer(&buffer));
+        assert!(!cache.is_feedback_buffer(&source));
+
+        cache.set(&source, 2.);
+        assert_eq!(cache.get::<f64>(&buffer), Some(&1.));
+        cache.update_feedback();
+        assert_eq!(cache.get::<f64>(&buffer), Some(&2.));
+
+        cache.remove_feedback(&buffer);
+        assert_eq!(cache.get::<f64>(&buffer), None);
+        // Only registered types can be fed back.
+        let text = cache.insert(String::new());
+        assert!(cache.insert_feedback(&text).is_none());
+    }
+
+    #[test]
+    fn values_of_type_skips_feedback_buffers() {
+        let mut cache = Cache::new();
+        cache.register_feedback_type(FeedbackType::new::<f64>());
+        let removed = cache.insert(1.);
+        let source = cache.insert(2.);
+        cache.insert_feedback(&source).unwrap();
+        cache.remove::<f64>(&removed);
+
+        assert_eq!(cache.values_of_type::<f64>().collect::<Vec<_>>(), [&2.]);
+        assert_eq!(cache.values_of_type::<bool>().count(), 0);
+    }
+
+    #[test]
+    fn changes_are_tracked_by_frame() {
+        let mut cache = Cache::new();
+        let value = cache.insert(1.);
+        cache.next_frame();
+        assert!(!cache.has_changed_since(&value, 0));
+
+        cache.set(&value, 2.);
+        assert!(cache.has_changed_since(&value, 0));
+        assert!(!cache.has_changed_since(&value, 1));
+        // Wrong types are ignored.
+        cache.set(&value, String::from("text"));
+        assert_eq!(cache.get::<f64>(&value), Some(&2.));
+        assert_eq!(cache.get::<String>(&value), None);
+
+        cache.remove_any(&value);
+        assert!(cache.has_changed_since(&value, 1));
+    }
+}
@@ -0,0 +1,172 @@
+use std::{error::Error, fmt};
+
+/// Passed to every node's compute.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameContext {
+    /// The number of steps computed before this one.
+    pub frame: u64,
+    /// Simulated time in seconds at the start of this step.
+    pub time: f64,
+    /// Simulated time in seconds covered by this step. Always the [Clock]'s timestep.
+    pub dt: f64,
+    /// The graph's seed, see [App::set_seed]. Every random value should be derived from it, so that renders are
+    /// reproducible.
+    pub seed: u64,
+    /// Differs between the nodes of a graph and is kept when saving. Mixed into the seed of nodes which should be
+    /// independent of each other even when their seed inputs are the same, e.g. two unconnected Random nodes.
+    pub salt: u64,
+}
+
+/// Returned when a timestep isn't a positive, finite number of seconds.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct InvalidTimestep(pub f64);
+
+impl fmt::Display for InvalidTimestep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Timestep must be a positive number of seconds, not {}", self.0)
+    }
+}
+
+impl Error for InvalidTimestep {}
+
+/// Turns real elapsed time into a whole number of fixed-size simulation steps, so that the simulation runs at the
+/// same speed regardless of frame rate. Also keeps the playback state, see [App::play].
+pub struct Clock {
+    timestep: f64,
+    is_playing: bool,
+    /// Multiplies elapsed real time, e.g. 0.5 for half speed.
+    speed: f64,
+    /// Caps the number of steps per call to [Clock::accumulate], so a slow frame can't cause an ever growing backlog.
+    max_substeps: u32,
+    /// Real time which hasn't been simulated yet.
+    accumulator: f64,
+    frame: u64,
+    time: f64,
+}
+
+impl Clock {
+    pub fn new(timestep: f64) -> Clock {
+        Clock {
+            timestep,
+            is_playing: true,
+            speed: 1.,
+            max_substeps: 8,
+            accumulator: 0.,
+            frame: 0,
+            time: 0.,
+        }
+    }
+
+    pub fn get_timestep(&self) -> f64 {
+        self.timestep
+    }
+
+    /// Fails for timesteps which could never add up to any time, or would step past everything at once.
+    pub fn set_timestep(&mut self, timestep: f64) -> Result<(), InvalidTimestep> {
+        if !(timestep > 0. && timestep.is_finite()) {
+            return Err(InvalidTimestep(timestep));
+        }
+        self.timestep = timestep;
+        Ok(())
+    }
+
+    pub fn set_max_substeps(&mut self, max_substeps: u32) {
+        self.max_substeps = max_substeps;
+    }
+
+    pub fn get_frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn get_time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.is_playing
+    }
+
+    pub fn set_playing(&mut self, is_playing: bool) {
+        self.is_playing = is_playing;
+    }
+
+    pub fn get_speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.max(0.);
+    }
+
+    /// Adds [elapsed] seconds of real time, returning how many steps should be computed to catch up. Always 0 while
+    /// paused.
+    pub fn accumulate(&mut self, elapsed: f64) -> u32 {
+        if !self.is_playing {
+            return 0;
+        }
+        self.accumulator += elapsed * self.speed;
+        // Allows for rounding errors, so that e.g. adding exactly one timestep always results in a step.
+        let steps = (self.accumulator / self.timestep + 1e-9).floor();
+        if steps > self.max_substeps as f64 {
+            self.accumulator = 0.;
+            self.max_substeps
+        } else {
+            self.accumulator = (self.accumulator - steps * self.timestep).max(0.);
+            steps as u32
+        }
+    }
+
+    pub fn context(&self, seed: u64) -> FrameContext {
+        FrameContext {
+            frame: self.frame,
+            time: self.time,
+            dt: self.timestep,
+            seed,
+            salt: 0,
+        }
+    }
+
+    /// Moves on to the next step.
+    pub fn tick(&mut self) {
+        self.frame += 1;
+        self.time += self.timestep;
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0.;
+        self.frame = 0;
+        self.time = 0.;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_timestep_is_rejected() {
+        let mut clock = Clock::new(0.5);
+        for timestep in [0., -1., f64::NAN, f64::INFINITY] {
+            assert!(clock.set_timestep(timestep).is_err());
+        }
+        assert_eq!(clock.get_timestep(), 0.5);
+        assert_eq!(clock.set_timestep(0.25), Ok(()));
+        assert_eq!(clock.get_timestep(), 0.25);
+    }
+
+    #[test]
+    fn accumulate_counts_whole_steps() {
+        let mut clock = Clock::new(0.1);
+        assert_eq!(clock.accumulate(0.25), 2);
+        // The remaining 0.05 carries over.
+        assert_eq!(clock.accumulate(0.05), 1);
+        clock.set_speed(2.);
+        assert_eq!(clock.accumulate(0.1), 2);
+        // Too many steps at once are dropped rather than saved up.
+        assert_eq!(clock.accumulate(100.), 8);
+        assert_eq!(clock.accumulate(0.), 0);
+
+        clock.set_playing(false);
+        assert_eq!(clock.accumulate(1.), 0);
+    }
+}
@@ -0,0 +1,98 @@
+use std::any::TypeId;
+
+use crate::core::{Cache, CacheIndex};
+
+type CreateFn = Box<dyn Fn(&mut Cache, &CacheIndex) -> Option<CacheIndex>>;
+type UpdateFn = Box<dyn Fn(&mut Cache, &CacheIndex, &CacheIndex)>;
+
+/// Converts values of one type into another, allowing an output to be connected to an input of a different type
+/// without an extra node in between. See [App::with_converters].
+pub struct Converter {
+    from: TypeId,
+    to: TypeId,
+    /// Inserts the converted value of [source] into the [Cache].
+    create: CreateFn,
+    /// Overwrites the value at [converted] with the converted value of [source].
+    update: UpdateFn,
+}
+
+impl Converter {
+    pub fn new<A: 'static, B: 'static>(convert: fn(&A) -> B) -> Converter {
+        Converter {
+            from: TypeId::of::<A>(),
+            to: TypeId::of::<B>(),
+            create: Box::new(move |cache, source| {
+                let converted = convert(cache.get::<A>(source)?);
+                Some(cache.insert(converted))
+            }),
+            update: Box::new(move |cache, source, converted| {
+                if let Some(value) = cache.get::<A>(source) {
+                    let value = convert(value);
+                    cache.set(converted, value);
+                }
+            }),
+        }
+    }
+
+    pub fn from(&self) -> &TypeId {
+        &self.from
+    }
+
+    pub fn to(&self) -> &TypeId {
+        &self.to
+    }
+
+    pub fn create(&self, cache: &mut Cache, source: &CacheIndex) -> Option<CacheIndex> {
+        (self.create)(cache, source)
+    }
+
+    pub fn update(&self, cache: &mut Cache, source: &CacheIndex, converted: &CacheIndex) {
+        (self.update)(cache, source, converted)
+    }
+}
+
+/// A [Converter] in use by an edge, stored on the input it feeds so that it can be updated right before the receiving
+/// node is computed.
+#[derive(Clone)]
+pub(crate) struct Conversion {
+    /// The [TypeId]s of the output and input, used to look up the [Converter].
+    converter: (TypeId, TypeId),
+    /// Either the output's [CacheIndex] or the edge's feedback buffer.
+    source: CacheIndex,
+    /// The [Cache] slot the receiving port is actually connected to.
+    converted: CacheIndex,
+    /// The frame [converted] was last updated on. None until the first update, as the source may be computed on the
+    /// same frame the edge was added.
+    updated: Option<u64>,
+}
+
+impl Conversion {
+    pub(crate) fn new(converter: (TypeId, TypeId), source: CacheIndex, converted: CacheIndex) -> Conversion {
+        Conversion {
+            converter,
+            source,
+            converted,
+            updated: None,
+        }
+    }
+
+    pub(crate) fn converter(&self) -> &(TypeId, TypeId) {
+        &self.converter
+    }
+
+    pub(crate) fn converted(&self) -> &CacheIndex {
+        &self.converted
+    }
+
+    /// Converts the source's value again if it has changed since the last update.
+    pub(crate) fn update(&mut self, converter: &Converter, cache: &mut Cache) {
+        let is_outdated = match self.updated {
+            Some(updated) => cache.has_changed_since(&self.source, updated),
+            None => true,
+        };
+        if is_outdated {
+            converter.update(cache, &self.source, &self.converted);
+            self.updated = Some(cache.frame());
+        }
+    }
+}
@@ -0,0 +1,511 @@
+use serde_json::Value;
+
+use crate::core::{App, CacheIndex, Direction, Edge, EdgeError, EdgeType, NodeId};
+
+/// A single reversible change to an [App]. Applying an edit with [App::edit] records the edits needed to revert it
+/// in the undo history.
+pub enum Edit {
+    AddNode {
+        node_type: &'static str,
+        position: (f64, f64),
+    },
+    /// Recreates a removed node in its old slot, so that any other edits referring to it still apply.
+    RestoreNode(NodeRecord),
+    RemoveNode(NodeId),
+    /// Replaces any edge already connected to the input.
+    Connect(EdgeRecord),
+    Disconnect {
+        to_node: NodeId,
+        to_port: usize,
+    },
+    /// Sets the default value of a port. Only works for types with a [ValueSerializer].
+    SetValue {
+        node_id: NodeId,
+        port: usize,
+        value: Value,
+    },
+    MoveNode {
+        node_id: NodeId,
+        position: (f64, f64),
+    },
+}
+
+#[derive(Clone)]
+pub struct EdgeRecord {
+    pub from_node: NodeId,
+    pub from_port: usize,
+    pub to_node: NodeId,
+    pub to_port: usize,
+    pub feedback: bool,
+}
+
+/// Everything needed to recreate a removed node.
+pub struct NodeRecord {
+    node_id: NodeId,
+    node_type: &'static str,
+    salt: u64,
+    position: Option<(f64, f64)>,
+    values: Vec<(usize, Value)>,
+    edges: Vec<EdgeRecord>,
+}
+
+/// The undo and redo stacks of an [App]. Each step is a list of edits, applied in order.
+pub struct History {
+    undo_stack: Vec<Vec<Edit>>,
+    redo_stack: Vec<Vec<Edit>>,
+    /// Set between [App::begin_edit_group] and [App::end_edit_group]. Collects the undo edits of everything done in
+    /// between into a single step.
+    group: Option<Vec<Edit>>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            group: None,
+        }
+    }
+
+    fn record(&mut self, mut undo_edits: Vec<Edit>) {
+        self.redo_stack.clear();
+        match &mut self.group {
+            Some(group) => {
+                // Only the first value/position of a drag needs to be kept, so that undoing it goes back to where
+                // the drag started.
+                undo_edits.retain(|edit| !group.iter().any(|other| edit.replaces_same_state(other)));
+                group.splice(0..0, undo_edits);
+            }
+            None => {
+                if !undo_edits.is_empty() {
+                    self.undo_stack.push(undo_edits);
+                }
+            }
+        }
+    }
+}
+
+impl Edit {
+    fn replaces_same_state(&self, other: &Edit) -> bool {
+        match (self, other) {
+            (
+                Edit::SetValue { node_id, port, .. },
+                Edit::SetValue {
+                    node_id: other_node_id,
+                    port: other_port,
+                    ..
+                },
+            ) => node_id == other_node_id && port == other_port,
+            (Edit::MoveNode { node_id, .. }, Edit::MoveNode { node_id: other_node_id, .. }) => node_id == other_node_id,
+            _ => false,
+        }
+    }
+}
+
+impl App {
+    /// Applies [edit] and records how to revert it in the undo history.
+    pub fn edit(&mut self, edit: Edit) -> Result<(), EdgeError> {
+        let undo_edits = self.apply_edit(edit)?;
+        self.record(undo_edits);
+        Ok(())
+    }
+
+    pub(crate) fn record(&mut self, undo_edits: Vec<Edit>) {
+        self.history().record(undo_edits);
+    }
+
+    /// Everything edited until [App::end_edit_group] is undone in one step. Used to turn drags into a single step.
+    pub fn begin_edit_group(&mut self) {
+        let history = self.history();
+        if history.group.is_none() {
+            history.group = Some(Vec::new());
+        }
+    }
+
+    pub fn end_edit_group(&mut self) {
+        let history = self.history();
+        if let Some(group) = history.group.take() {
+            if !group.is_empty() {
+                history.undo_stack.push(group);
+            }
+        }
+    }
+
+    pub fn clear_history(&mut self) {
+        *self.history() = History::new();
+    }
+
+    /// Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.end_edit_group();
+        match self.history().undo_stack.pop() {
+            Some(step) => {
+                let redo_step = self.apply_step(step);
+                self.history().redo_stack.push(redo_step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns false if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        self.end_edit_group();
+        match self.history().redo_stack.pop() {
+            Some(step) => {
+                let undo_step = self.apply_step(step);
+                self.history().undo_stack.push(undo_step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies every edit in [step], returning the step which reverts it. Edits which no longer apply (e.g. because
+    /// a node couldn't be restored into its old slot) are skipped.
+    fn apply_step(&mut self, step: Vec<Edit>) -> Vec<Edit> {
+        let mut reverse_step = Vec::new();
+        for edit in step {
+            if let Ok(reverse_edits) = self.apply_edit(edit) {
+                reverse_step.splice(0..0, reverse_edits);
+            }
+        }
+        reverse_step
+    }
+
+    /// Applies [edit] without recording it, returning the edits which revert it.
+    fn apply_edit(&mut self, edit: Edit) -> Result<Vec<Edit>, EdgeError> {
+        match edit {
+            Edit::AddNode { node_type, position } => {
+                let node_id = self.add_node(node_type);
+                self.set_position(node_id, position);
+                Ok(vec![Edit::RemoveNode(node_id)])
+            }
+            Edit::RestoreNode(record) => {
+                let node_id = self.restore_node(record.node_type, record.node_id, record.salt);
+                if let Some(position) = record.position {
+                    self.set_position(node_id, position);
+                }
+                for (port, value) in record.values {
+                    if let Some(cache_index) = self.default_cache_index(node_id, port) {
+                        self.deserialize_value(&cache_index, value);
+                    }
+                }
+                self.update_ports(node_id);
+                for mut edge in record.edges {
+                    if edge.from_node == record.node_id {
+                        edge.from_node = node_id;
+                    }
+                    if edge.to_node == record.node_id {
+                        edge.to_node = node_id;
+                    }
+                    // Edges to other nodes which have been removed since are simply left out.
+                    let _ = self.connect_record(&edge);
+                }
+                Ok(vec![Edit::RemoveNode(node_id)])
+            }
+            Edit::RemoveNode(node_id) => {
+                let node = match self.get_node(node_id) {
+                    Some(node) => node,
+                    None => return Err(EdgeError::NodeNotFound(node_id)),
+                };
+                let values = node
+                    .get_ports()
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, port)| {
+                        let serializer = self.serializers().get(port.get_type_id())?;
+                        Some((index, serializer.save(self.get_cache(), port.get_default_cache_index())?))
+                    })
+                    .collect();
+                let record = NodeRecord {
+                    node_id,
+                    node_type: node.get_factory_name(),
+                    salt: node.get_salt(),
+                    position: self.get_position(node_id),
+                    values,
+                    edges: self
+                        .edges()
+                        .iter()
+                        .filter(|edge| edge.from_node == node_id || edge.to_node == node_id)
+                        .map(edge_record)
+                        .collect(),
+                };
+                self.remove_node(node_id);
+                Ok(vec![Edit::RestoreNode(record)])
+            }
+            Edit::Connect(edge) => {
+                let replaced = self.edge_record_to(edge.to_node, edge.to_port);
+                self.connect_record(&edge)?;
+                let mut undo_edits = vec![Edit::Disconnect {
+                    to_node: edge.to_node,
+                    to_port: edge.to_port,
+                }];
+                if let Some(replaced) = replaced {
+                    undo_edits.push(Edit::Connect(replaced));
+                }
+                Ok(undo_edits)
+            }
+            Edit::Disconnect { to_node, to_port } => {
+                let removed = self.edge_record_to(to_node, to_port);
+                self.remove_edge_to(to_node, to_port);
+                Ok(removed.map(Edit::Connect).into_iter().collect())
+            }
+            Edit::SetValue { node_id, port, value } => {
+                let cache_index = self
+                    .default_cache_index(node_id, port)
+                    .ok_or(EdgeError::PortNotFound(node_id, port))?;
+                let old_value = self
+                    .serializers()
+                    .get(cache_index.type_id())
+                    .and_then(|serializer| serializer.save(self.get_cache(), &cache_index));
+                self.deserialize_value(&cache_index, value);
+                let dropped_edges = self.update_ports(node_id);
+                Ok(old_value
+                    .map(|old_value| Edit::SetValue {
+                        node_id,
+                        port,
+                        value: old_value,
+                    })
+                    .into_iter()
+                    .chain(dropped_edges.into_iter().map(Edit::Connect))
+                    .collect())
+            }
+            Edit::MoveNode { node_id, position } => {
+                let old_position = self.get_position(node_id);
+                self.set_position(node_id, position);
+                Ok(old_position
+                    .map(|old_position| Edit::MoveNode {
+                        node_id,
+                        position: old_position,
+                    })
+                    .into_iter()
+                    .collect())
+            }
+        }
+    }
+
+    /// Rebuilds the ports of [node_id] if they no longer match its values (see [NodeBehavior::refresh]). Edges are
+    /// reconnected to the port with the same name and direction. Returns the edges which couldn't be, e.g. because
+    /// the port is gone or its type has changed, so that the caller can record how to restore them.
+    pub(crate) fn update_ports(&mut self, node_id: NodeId) -> Vec<EdgeRecord> {
+        if !self.refresh_node(node_id) {
+            return Vec::new();
+        }
+
+        // The names of the node's ports at either end of each edge, so they can be found again afterwards.
+        let port_name = |port: usize| self.get_node(node_id).unwrap().get_ports()[port].get_name().to_string();
+        let edges: Vec<(EdgeRecord, Option<String>, Option<String>)> = self
+            .edges()
+            .iter()
+            .filter(|edge| edge.from_node == node_id || edge.to_node == node_id)
+            .map(|edge| {
+                (
+                    edge_record(edge),
+                    Some(edge.from_port).filter(|_| edge.from_node == node_id).map(port_name),
+                    Some(edge.to_port).filter(|_| edge.to_node == node_id).map(port_name),
+                )
+            })
+            .collect();
+
+        for (edge, _, _) in &edges {
+            self.remove_edge_to(edge.to_node, edge.to_port);
+        }
+        self.update_node_ports(node_id);
+
+        let mut dropped_edges = Vec::new();
+        for (edge, from_name, to_name) in edges {
+            let from_port = match &from_name {
+                Some(name) => self.find_port(node_id, name, Direction::Output),
+                None => Some(edge.from_port),
+            };
+            let to_port = match &to_name {
+                Some(name) => self.find_port(node_id, name, Direction::Input),
+                None => Some(edge.to_port),
+            };
+            let reconnected = match (from_port, to_port) {
+                (Some(from_port), Some(to_port)) => self
+                    .connect_record(&EdgeRecord {
+                        from_port,
+                        to_port,
+                        ..edge.clone()
+                    })
+                    .is_ok(),
+                _ => false,
+            };
+            if !reconnected {
+                dropped_edges.push(edge);
+            }
+        }
+        dropped_edges
+    }
+
+    fn connect_record(&mut self, edge: &EdgeRecord) -> Result<(), EdgeError> {
+        if edge.feedback {
+            self.add_feedback_edge(edge.from_node, edge.from_port, edge.to_node, edge.to_port)
+        } else {
+            self.add_edge(edge.from_node, edge.from_port, edge.to_node, edge.to_port)
+        }
+    }
+
+    fn edge_record_to(&self, to_node: NodeId, to_port: usize) -> Option<EdgeRecord> {
+        self.edges()
+            .iter()
+            .find(|edge| edge.to_node == to_node && edge.to_port == to_port)
+            .map(edge_record)
+    }
+
+    fn default_cache_index(&self, node_id: NodeId, port: usize) -> Option<CacheIndex> {
+        Some(self.get_node(node_id)?.get_ports().get(port)?.get_default_cache_index().clone())
+    }
+}
+
+fn edge_record(edge: &Edge) -> EdgeRecord {
+    EdgeRecord {
+        from_node: edge.from_node,
+        from_port: edge.from_port,
+        to_node: edge.to_node,
+        to_port: edge.to_port,
+        feedback: matches!(edge.edge_type, EdgeType::Post),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::{self, expression_node, math_node};
+
+    // Ports of the "Add" node.
+    const A: usize = 0;
+    const B: usize = 1;
+    const RESULT: usize = 2;
+
+    fn value(app: &App, node_id: NodeId, port: usize) -> f64 {
+        let cache_index = app.default_cache_index(node_id, port).unwrap();
+        *app.get_cache().get::<f64>(&cache_index).unwrap()
+    }
+
+    fn port_names(app: &App, node_id: NodeId) -> Vec<&str> {
+        app.get_node(node_id).unwrap().get_ports().iter().map(|port| port.get_name()).collect()
+    }
+
+    fn connect(app: &mut App, from_node: NodeId, from_port: usize, to_node: NodeId, to_port: usize) {
+        app.edit(Edit::Connect(EdgeRecord {
+            from_node,
+            from_port,
+            to_node,
+            to_port,
+            feedback: false,
+        }))
+        .unwrap();
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut app = nodes::new_app();
+        let a = app.add_node(math_node::add::NAME);
+        let b = app.add_node(math_node::add::NAME);
+        connect(&mut app, a, RESULT, b, A);
+        app.set_value(b, B, 2.);
+
+        assert!(app.undo());
+        assert_eq!(value(&app, b, B), 0.);
+        assert!(app.undo());
+        assert!(app.edges().is_empty());
+        assert!(!app.undo());
+
+        assert!(app.redo());
+        assert_eq!(app.edges().len(), 1);
+        assert!(app.redo());
+        assert_eq!(value(&app, b, B), 2.);
+        assert!(!app.redo());
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut app = nodes::new_app();
+        let node = app.add_node(math_node::add::NAME);
+        app.set_value(node, A, 1.);
+        assert!(app.undo());
+        app.set_value(node, B, 1.);
+        assert!(!app.redo());
+        assert_eq!(value(&app, node, A), 0.);
+    }
+
+    #[test]
+    fn removed_node_is_restored_with_values_and_edges() {
+        let mut app = nodes::new_app();
+        let a = app.add_node(math_node::add::NAME);
+        let b = app.add_node(math_node::add::NAME);
+        app.set_value(a, A, 3.);
+        app.set_position(a, (5., 6.));
+        connect(&mut app, a, RESULT, b, A);
+
+        app.edit(Edit::RemoveNode(a)).unwrap();
+        assert!(app.get_node(a).is_none());
+        assert!(app.edges().is_empty());
+
+        assert!(app.undo());
+        // Restored into the same slot, so the handle still refers to it.
+        assert!(app.get_node(a).is_some());
+        assert_eq!(value(&app, a, A), 3.);
+        assert_eq!(app.get_position(a), Some((5., 6.)));
+        assert_eq!(app.edges().len(), 1);
+    }
+
+    #[test]
+    fn edit_group_is_undone_in_one_step() {
+        let mut app = nodes::new_app();
+        let node = app.add_node(math_node::add::NAME);
+        app.set_value(node, A, 1.);
+
+        // A drag, which sets the value many times.
+        app.begin_edit_group();
+        for step in 2..10 {
+            app.set_value(node, A, step as f64);
+            app.set_value(node, B, step as f64);
+        }
+        app.end_edit_group();
+
+        assert!(app.undo());
+        assert_eq!(value(&app, node, A), 1.);
+        assert_eq!(value(&app, node, B), 0.);
+        assert!(app.undo());
+        assert_eq!(value(&app, node, A), 0.);
+
+        assert!(app.redo());
+        assert!(app.redo());
+        assert_eq!(value(&app, node, A), 9.);
+        assert_eq!(value(&app, node, B), 9.);
+    }
+
+    #[test]
+    fn changed_ports_reconnect_by_name() {
+        let mut app = nodes::new_app();
+        let add = app.add_node(math_node::add::NAME);
+        let expression = app.add_node(expression_node::NAME);
+        app.set_value(expression, expression_node::FORMULA, String::from("a + b"));
+        assert_eq!(port_names(&app, expression), ["Formula", "Result", "a", "b"]);
+        connect(&mut app, add, RESULT, expression, expression_node::FIRST_VARIABLE + 1);
+
+        // "b" moves to the first variable, and its edge moves with it.
+        app.set_value(expression, expression_node::FORMULA, String::from("b - a"));
+        assert_eq!(port_names(&app, expression), ["Formula", "Result", "b", "a"]);
+        assert_eq!(app.edges().len(), 1);
+        assert_eq!(app.edges()[0].to_port, expression_node::FIRST_VARIABLE);
+
+        // Once "b" is gone the edge is dropped, and undoing brings both back.
+        app.set_value(expression, expression_node::FORMULA, String::from("a"));
+        assert!(app.edges().is_empty());
+        assert!(app.undo());
+        assert_eq!(port_names(&app, expression), ["Formula", "Result", "b", "a"]);
+        assert_eq!(app.edges().len(), 1);
+        assert_eq!(app.edges()[0].to_port, expression_node::FIRST_VARIABLE);
+    }
+}
@@ -1,9 +1,21 @@
 mod app;
 mod cache;
+mod clock;
+mod converter;
 mod node;
+mod node_behavior;
 mod allocated_vec;
+mod save;
+mod history;
+mod type_name;
 
 pub use app::*;
 pub use cache::*;
+pub use clock::*;
+pub use converter::*;
 pub use node::*;
+pub use node_behavior::*;
 pub use allocated_vec::*;
+pub use save::*;
+pub use history::*;
+pub use type_name::*;
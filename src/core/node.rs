@@ -1,24 +1,39 @@
-use crate::core::{Cache, CacheIndex};
+use std::{any::TypeId, borrow::Cow};
 
+use crate::core::{Cache, CacheIndex, Conversion, FrameContext, Handle, NodeBehavior};
+
+/// Identifies a [Node] within an [App]. Becomes invalid once the node is removed, even if its slot is reused.
+pub type NodeId = Handle;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Direction {
     Input,
     Output,
 }
 
 pub struct Port {
+    /// Displayed to the user, e.g. in error messages. Only owned for ports created at runtime, e.g. an expression's
+    /// variables.
+    name: Cow<'static, str>,
+    /// Also determines the type of value this port accepts/produces, as any connected [CacheIndex] must be the same
+    /// type.
     default_cache_index: CacheIndex,
     cache_index: CacheIndex,
     direction: Direction,
     is_connected: bool, // variable for the number of connected edges? Would consequently make is_connected redundant
+    /// Set while connected to an output of a different type.
+    conversion: Option<Conversion>,
 }
 
 impl Port {
-    pub fn new(default_cache_index: CacheIndex, direction: Direction) -> Port {
+    pub fn new(name: impl Into<Cow<'static, str>>, default_cache_index: CacheIndex, direction: Direction) -> Port {
         Port {
+            name: name.into(),
             cache_index: default_cache_index.clone(),
             default_cache_index,
             direction,
             is_connected: false,
+            conversion: None,
         }
     }
 
@@ -30,6 +45,19 @@ impl Port {
     pub fn disconnect(&mut self) {
         self.cache_index = self.default_cache_index.clone();
         self.is_connected = false;
+        self.conversion = None;
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_type_id(&self) -> &TypeId {
+        self.default_cache_index.type_id()
+    }
+
+    pub fn get_type_name(&self) -> &'static str {
+        self.default_cache_index.type_name()
     }
 
     pub fn get_cache_index(&self) -> &CacheIndex {
@@ -66,77 +94,115 @@ impl Port {
 pub struct Node {
     /// Stores [Port]s containing [CacheIndex]es to any input/output data.
     ports: Vec<Port>,
-    /** Modifies the [Cache].
-        Should generally get immutable references to inputs. The except being "internal" values (inputs which aren't exposed by the GUI).
-        Should alwawys get mutable references to outputs.
-        Called every frame.
-    */
-    // TODO: Investigate whether storing all these funcs in Options is more efficient than just having empty funcs which are used if no func is given.
-    // Granted, this efficiency doesn't really matter for disconnect/connect functions, but compute() is gonna be called every frame, soooo...
-    compute: Option<fn(ports: &Vec<Port>, cache: &mut Cache)>,
-    /// Called when the node is about to be removed. Primary purpose is to remove any input/output values from the [Cache].
-    remove_all_cache: fn(ports: &Vec<Port>, cache: &mut Cache),
-    /// Called when an input port is disconnected and so a new value must be created in the Cache.
-    disconnect: Option<fn(node: &Node, port_index: usize, cache: &mut Cache) -> Option<CacheIndex>>,
-    /// Called when an input port is connected and so the current ("internal") value must be removed from the [Cache].
-    connect: Option<fn(node: &Node, port_index: usize, cache: &mut Cache)>,
+    behavior: Box<dyn NodeBehavior>,
+    /// The key of the factory this node was created by, used when saving. Set by [App::add_node].
+    factory_name: &'static str,
+    /// Nodes with internal state (e.g. a simulated [Particle]) must be computed every frame, even if none of their
+    /// inputs have changed.
+    has_internal_state: bool,
+    /// See [FrameContext::salt]. Set by [App::add_node].
+    salt: u64,
+    /// The [Cache] frame this node was last computed on. None if it has never been computed, or if its connections
+    /// have changed since.
+    last_computed: Option<u64>,
 }
 
 impl Node {
-    pub fn new(
-        ports: Vec<Port>,
-        remove_all_cache: fn(
-            ports: &Vec<Port>,
-            cache: &mut Cache,
-        ),
-    ) -> Self {
+    pub fn new<B: NodeBehavior + 'static>(ports: Vec<Port>, behavior: B) -> Self {
         Node {
             ports,
-            compute: None,
-            remove_all_cache,
-            disconnect: None,
-            connect: None,
+            behavior: Box::new(behavior),
+            factory_name: "",
+            salt: 0,
+            has_internal_state: false,
+            last_computed: None,
         }
     }
 
-    pub fn with_compute(
-        mut self,
-        compute_func: fn(ports: &Vec<Port>, cache: &mut Cache),
-    ) -> Self {
-        self.compute = Some(compute_func);
+    pub fn with_internal_state(mut self) -> Self {
+        self.has_internal_state = true;
         self
     }
 
-    pub fn with_create_remove_input_cache(
-        mut self,
-        disconnect: fn(node: &Node, port_index: usize, cache: &mut Cache) -> Option<CacheIndex>,
-        connect: fn(node: &Node, port_index: usize, cache: &mut Cache),
-    ) -> Self {
-        self.disconnect = Some(disconnect);
-        self.connect = Some(connect);
-        self
+    pub fn get_factory_name(&self) -> &'static str {
+        self.factory_name
+    }
+
+    pub(crate) fn set_factory_name(&mut self, factory_name: &'static str) {
+        self.factory_name = factory_name;
+    }
+
+    pub fn get_salt(&self) -> u64 {
+        self.salt
+    }
+
+    pub(crate) fn set_salt(&mut self, salt: u64) {
+        self.salt = salt;
+        self.last_computed = None;
+    }
+
+    pub fn init(&mut self, cache: &mut Cache) {
+        self.behavior.init(&self.ports, cache);
+        self.last_computed = None;
     }
 
     pub fn connect_input(&mut self, port_index: usize, new_cache_index: CacheIndex, cache: &mut Cache) {
-        // TODO: This is what is causing the crash when changing an input. Needs to only remove cache if it is owned by the node and not from a connected node.
         if let Direction::Input = self.ports[port_index].get_direction() {
-            if let Some(func) = self.connect {
-                (func)(&self, port_index, cache);
+            // Makes sure on_connect only ever sees the port's default value, and so never removes the value of
+            // another node's output.
+            if self.ports[port_index].is_connected() {
+                self.disconnect_input(port_index, cache);
             }
+            self.behavior.on_connect(&mut self.ports, port_index, cache);
             let port = self.ports.get_mut(port_index).unwrap();
             port.connect(new_cache_index);
+            self.last_computed = None;
         }
     }
 
-    // TODO: I'd like to essentially make this method obselete by somehow storing a "default" cache index which is
-    // just switched out for any connection. And then if disconnected, this default is used once-more.
     // TODO: Would need to check somehow to see if the port is meant to be able to handle multiple inputs - if this is something I decide to implement.
+    /// Makes the input go through [conversion], which must already be the one its [CacheIndex] comes from.
+    pub(crate) fn set_conversion(&mut self, port_index: usize, conversion: Conversion) {
+        self.ports[port_index].conversion = Some(conversion);
+    }
+
+    pub(crate) fn take_conversion(&mut self, port_index: usize) -> Option<Conversion> {
+        self.ports.get_mut(port_index)?.conversion.take()
+    }
+
+    pub(crate) fn conversions_mut(&mut self) -> impl Iterator<Item = &mut Conversion> {
+        self.ports.iter_mut().filter_map(|port| port.conversion.as_mut())
+    }
+
     pub fn disconnect_input(&mut self, port_index: usize, cache: &mut Cache) {
         if let Direction::Input = self.ports[port_index].get_direction() {
+            self.behavior.on_disconnect(&mut self.ports, port_index, cache);
             self.ports.get_mut(port_index).unwrap().disconnect();
+            self.last_computed = None;
         }
     }
 
+    /// Makes sure the node is computed on the next frame even if none of its inputs have changed, e.g. because the
+    /// seed in the [FrameContext] has.
+    pub(crate) fn invalidate(&mut self) {
+        self.last_computed = None;
+    }
+
+    /// See [NodeBehavior::refresh].
+    pub(crate) fn refresh(&mut self, cache: &Cache) -> bool {
+        self.behavior.refresh(&self.ports, cache)
+    }
+
+    /// See [NodeBehavior::update_ports]. Must only be called while no port is connected.
+    pub(crate) fn update_ports(&mut self, cache: &mut Cache) {
+        self.behavior.update_ports(&mut self.ports, cache);
+        self.last_computed = None;
+    }
+
+    pub fn get_error(&self) -> Option<&str> {
+        self.behavior.get_error()
+    }
+
     pub fn get_output(&self, port_index: usize) -> Option<&CacheIndex> {
         match self.ports.get(port_index) {
             Some(port) => Some(port.get_cache_index()),
@@ -148,13 +214,32 @@ impl Node {
         &self.ports
     }
 
-    pub fn compute(&self, cache: &mut Cache) {
-        if let Some(compute_func) = self.compute {
-            (compute_func)(&self.ports, cache);
+    /// Returns false if computing this node would have no effect because none of its inputs have changed since it
+    /// was last computed.
+    pub fn needs_compute(&self, cache: &Cache) -> bool {
+        match self.last_computed {
+            Some(last_computed) => {
+                self.has_internal_state
+                    || self.behavior.has_internal_state(&self.ports, cache)
+                    || self.ports.iter().any(|port| {
+                        matches!(port.get_direction(), Direction::Input)
+                            && cache.has_changed_since(port.get_cache_index(), last_computed)
+                    })
+            }
+            None => true,
         }
     }
 
+    pub fn compute(&mut self, cache: &mut Cache, context: &FrameContext) {
+        let context = FrameContext {
+            salt: self.salt,
+            ..*context
+        };
+        self.behavior.compute(&self.ports, cache, &context);
+        self.last_computed = Some(cache.frame());
+    }
+
     pub fn remove_all_cache(&mut self, cache: &mut Cache) {
-        (self.remove_all_cache)(&self.ports, cache);
+        self.behavior.teardown(&self.ports, cache);
     }
 }
@@ -0,0 +1,135 @@
+use crate::core::{Cache, CacheIndex, FrameContext, Port};
+
+/// Implements what a [Node] actually does. Owned by the [Node], so can hold any per-instance state (random number
+/// generators, history buffers, file handles etc.) which doesn't belong in the [Cache].
+/// Every hook has a default implementation which does nothing.
+pub trait NodeBehavior {
+    /// Called once when the node is added to an [App], and again whenever the graph is reset.
+    /// Should (re)initialise any internal state, including any stateful values in the [Cache].
+    fn init(&mut self, _ports: &Vec<Port>, _cache: &mut Cache) {}
+
+    /** Modifies the [Cache].
+        Should generally get immutable references to inputs. The except being "internal" values (inputs which aren't exposed by the GUI).
+        Should alwawys get mutable references to outputs.
+        Called every frame that any of the inputs have changed, or every frame if the node has internal state.
+        Anything simulated over time should use [FrameContext::dt] rather than assuming a fixed frame length.
+    */
+    fn compute(&mut self, _ports: &Vec<Port>, _cache: &mut Cache, _context: &FrameContext) {}
+
+    /// Like [Node::with_internal_state](crate::core::Node::with_internal_state), but asked every frame, for nodes which
+    /// only depend on the frame some of the time, e.g. while a "Per Frame" input is set.
+    fn has_internal_state(&self, _ports: &Vec<Port>, _cache: &Cache) -> bool {
+        false
+    }
+
+    /// Called right before an input port is connected, while it is still using its default [CacheIndex].
+    fn on_connect(&mut self, _ports: &mut Vec<Port>, _port_index: usize, _cache: &mut Cache) {}
+
+    /// Called right before an input port is disconnected. The port will go back to using its default [CacheIndex]
+    /// afterwards, so this is the place to replace it with [Port::set_default_cache_index] if needed.
+    fn on_disconnect(&mut self, _ports: &mut Vec<Port>, _port_index: usize, _cache: &mut Cache) {}
+
+    /// Called after one of the node's values has been set from outside the graph, e.g. by the user, by undo or when
+    /// loading. Returns true if the node's ports no longer match its values (e.g. after an expression's formula has
+    /// changed), in which case [NodeBehavior::update_ports] is called next.
+    fn refresh(&mut self, _ports: &Vec<Port>, _cache: &Cache) -> bool {
+        false
+    }
+
+    /// Adds, removes or replaces ports, including their values in the [Cache]. Every port is disconnected first, and
+    /// edges are reconnected afterwards to whichever port has the same name and direction.
+    fn update_ports(&mut self, _ports: &mut Vec<Port>, _cache: &mut Cache) {}
+
+    /// Shown on the node, e.g. if its formula couldn't be parsed.
+    fn get_error(&self) -> Option<&str> {
+        None
+    }
+
+    /// Called when the node is about to be removed. Primary purpose is to remove any input/output values from the [Cache].
+    /// By default removes the default value of every port, which is enough for any node that never replaces them.
+    fn teardown(&mut self, ports: &Vec<Port>, cache: &mut Cache) {
+        for port in ports {
+            cache.remove_any(port.get_default_cache_index());
+        }
+    }
+}
+
+/// See [FnBehavior::with_create_remove_input_cache].
+type DisconnectFn = fn(ports: &[Port], port_index: usize, cache: &mut Cache) -> Option<CacheIndex>;
+
+/// Bridges plain functions to [NodeBehavior], for nodes which don't need any internal state of their own.
+pub struct FnBehavior {
+    init: Option<fn(ports: &[Port], cache: &mut Cache)>,
+    // TODO: Investigate whether storing all these funcs in Options is more efficient than just having empty funcs which are used if no func is given.
+    // Granted, this efficiency doesn't really matter for disconnect/connect functions, but compute() is gonna be called every frame, soooo...
+    compute: Option<fn(ports: &[Port], cache: &mut Cache, context: &FrameContext)>,
+    remove_all_cache: fn(ports: &[Port], cache: &mut Cache),
+    /// Called when an input port is disconnected and so a new value must be created in the Cache.
+    disconnect: Option<DisconnectFn>,
+    /// Called when an input port is connected and so the current ("internal") value must be removed from the [Cache].
+    connect: Option<fn(ports: &[Port], port_index: usize, cache: &mut Cache)>,
+}
+
+impl FnBehavior {
+    pub fn new(remove_all_cache: fn(ports: &[Port], cache: &mut Cache)) -> Self {
+        FnBehavior {
+            init: None,
+            compute: None,
+            remove_all_cache,
+            disconnect: None,
+            connect: None,
+        }
+    }
+
+    pub fn with_init(mut self, init_func: fn(ports: &[Port], cache: &mut Cache)) -> Self {
+        self.init = Some(init_func);
+        self
+    }
+
+    pub fn with_compute(mut self, compute_func: fn(ports: &[Port], cache: &mut Cache, context: &FrameContext)) -> Self {
+        self.compute = Some(compute_func);
+        self
+    }
+
+    pub fn with_create_remove_input_cache(
+        mut self,
+        disconnect: DisconnectFn,
+        connect: fn(ports: &[Port], port_index: usize, cache: &mut Cache),
+    ) -> Self {
+        self.disconnect = Some(disconnect);
+        self.connect = Some(connect);
+        self
+    }
+}
+
+impl NodeBehavior for FnBehavior {
+    fn init(&mut self, ports: &Vec<Port>, cache: &mut Cache) {
+        if let Some(init_func) = self.init {
+            (init_func)(ports, cache);
+        }
+    }
+
+    fn compute(&mut self, ports: &Vec<Port>, cache: &mut Cache, context: &FrameContext) {
+        if let Some(compute_func) = self.compute {
+            (compute_func)(ports, cache, context);
+        }
+    }
+
+    fn on_connect(&mut self, ports: &mut Vec<Port>, port_index: usize, cache: &mut Cache) {
+        if let Some(connect) = self.connect {
+            (connect)(ports, port_index, cache);
+        }
+    }
+
+    fn on_disconnect(&mut self, ports: &mut Vec<Port>, port_index: usize, cache: &mut Cache) {
+        if let Some(disconnect) = self.disconnect {
+            if let Some(new_default_cache_index) = (disconnect)(ports, port_index, cache) {
+                ports[port_index].set_default_cache_index(new_default_cache_index);
+            }
+        }
+    }
+
+    fn teardown(&mut self, ports: &Vec<Port>, cache: &mut Cache) {
+        (self.remove_all_cache)(ports, cache);
+    }
+}
@@ -0,0 +1,472 @@
+use std::{any::TypeId, collections::HashMap, error::Error, fmt};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::core::{App, Cache, CacheIndex, Direction, EdgeError, EdgeType, NodeId};
+
+/// Upgrades a document from the version before it, e.g. by renaming ports or node types after a node's layout has
+/// changed. Works on the raw JSON so that it can handle documents which no longer match [GraphDocument].
+pub type Migration = fn(document: &mut Value);
+
+/// The on-disk representation of an [App], plus the position of each node in the graph editor.
+#[derive(Serialize, Deserialize)]
+pub struct GraphDocument {
+    pub version: u32,
+    /// See [App::set_seed].
+    #[serde(default)]
+    pub seed: u64,
+    pub nodes: Vec<SavedNode>,
+    pub edges: Vec<SavedEdge>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedNode {
+    /// The key of the factory the node was created by.
+    pub node_type: String,
+    /// See [FrameContext::salt](crate::core::FrameContext::salt). Documents from before it was saved use the node's
+    /// index instead.
+    #[serde(default)]
+    pub salt: Option<u64>,
+    #[serde(default)]
+    pub position: Option<(f64, f64)>,
+    /// Default values of the node's ports, by port name.
+    #[serde(default)]
+    pub values: Vec<SavedValue>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedValue {
+    pub port: String,
+    pub value: Value,
+}
+
+/// Nodes are referred to by their index in [GraphDocument::nodes], ports by name.
+#[derive(Serialize, Deserialize)]
+pub struct SavedEdge {
+    pub from_node: usize,
+    pub from_port: String,
+    pub to_node: usize,
+    pub to_port: String,
+    #[serde(default)]
+    pub feedback: bool,
+}
+
+/// A node created by [App::load].
+pub struct LoadedNode {
+    pub node_id: NodeId,
+    pub node_type: &'static str,
+    pub position: Option<(f64, f64)>,
+}
+
+/// Returned by [App::load] along with the nodes it created.
+pub struct LoadResult {
+    pub nodes: Vec<LoadedNode>,
+    /// Parts of the document which were skipped, in the order they were found.
+    pub warnings: Vec<LoadWarning>,
+}
+
+/// Something in a document which no longer fits the nodes it belongs to, and so was skipped while the rest of the
+/// document was loaded. The [fmt::Display] implementation is intended to be shown to the user.
+#[derive(Debug)]
+pub enum LoadWarning {
+    /// The node has no port with this name.
+    UnknownPort { node_type: String, port: String },
+    /// The saved value couldn't be read as the port's type.
+    InvalidValue {
+        node_type: String,
+        port: String,
+        type_name: String,
+    },
+    /// One of the edge's nodes or ports no longer exists.
+    MissingEdgeEnd { from_port: String, to_port: String },
+    Edge(EdgeError),
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadWarning::UnknownPort { node_type, port } => {
+                write!(f, "Skipped value for '{}': {} has no such port", port, node_type)
+            }
+            LoadWarning::InvalidValue {
+                node_type,
+                port,
+                type_name,
+            } => write!(f, "Skipped value for '{}' on {}: it isn't a valid {}", port, node_type, type_name),
+            LoadWarning::MissingEdgeEnd { from_port, to_port } => {
+                write!(f, "Skipped edge from '{}' to '{}': port not found", from_port, to_port)
+            }
+            LoadWarning::Edge(error) => write!(f, "Skipped edge: {}", error),
+        }
+    }
+}
+
+/// Returned when a document can't be loaded. The [fmt::Display] implementation is intended to be shown to the user.
+#[derive(Debug)]
+pub enum LoadError {
+    Parse(serde_json::Error),
+    MissingVersion,
+    UnsupportedVersion(u32),
+    UnknownNodeType(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Parse(error) => write!(f, "Couldn't read graph: {}", error),
+            LoadError::MissingVersion => write!(f, "Couldn't read graph: missing version"),
+            LoadError::UnsupportedVersion(version) => {
+                write!(f, "Graph was saved by a newer version (format version {})", version)
+            }
+            LoadError::UnknownNodeType(node_type) => write!(f, "Unknown node type '{}'", node_type),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+impl From<serde_json::Error> for LoadError {
+    fn from(error: serde_json::Error) -> Self {
+        LoadError::Parse(error)
+    }
+}
+
+type SaveFn = Box<dyn Fn(&Cache, &CacheIndex) -> Option<Value>>;
+type LoadFn = Box<dyn Fn(&mut Cache, &CacheIndex, Value) -> bool>;
+
+/// Converts values of one type in the [Cache] to and from JSON. See [App::with_serializers].
+pub struct ValueSerializer {
+    type_id: TypeId,
+    save: SaveFn,
+    /// Returns false if the value couldn't be deserialised.
+    load: LoadFn,
+}
+
+impl ValueSerializer {
+    pub fn new<T: Clone + Serialize + DeserializeOwned + 'static>() -> ValueSerializer {
+        ValueSerializer::with_conversion::<T, T>(|value| value.clone(), |value| value)
+    }
+
+    /// For types which don't implement [Serialize] themselves, goes through [S] instead.
+    pub fn with_conversion<T: 'static, S: Serialize + DeserializeOwned + 'static>(
+        to: fn(&T) -> S,
+        from: fn(S) -> T,
+    ) -> ValueSerializer {
+        ValueSerializer {
+            type_id: TypeId::of::<T>(),
+            save: Box::new(move |cache, cache_index| serde_json::to_value(to(cache.get::<T>(cache_index)?)).ok()),
+            load: Box::new(move |cache, cache_index, value| match serde_json::from_value::<S>(value) {
+                Ok(value) => {
+                    cache.set(cache_index, from(value));
+                    true
+                }
+                Err(_) => false,
+            }),
+        }
+    }
+
+    pub fn type_id(&self) -> &TypeId {
+        &self.type_id
+    }
+
+    pub fn save(&self, cache: &Cache, cache_index: &CacheIndex) -> Option<Value> {
+        (self.save)(cache, cache_index)
+    }
+
+    pub fn load(&self, cache: &mut Cache, cache_index: &CacheIndex, value: Value) -> bool {
+        (self.load)(cache, cache_index, value)
+    }
+}
+
+impl GraphDocument {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+impl App {
+    /// The version of documents saved by this [App]. Starts at 1 and increases with every [Migration].
+    pub fn format_version(&self) -> u32 {
+        self.migrations().len() as u32 + 1
+    }
+
+    pub fn save(&self) -> GraphDocument {
+        let mut document_indices = HashMap::new();
+        let mut nodes = Vec::new();
+
+        for node_id in self.nodes().handles() {
+            let node = self.get_node(node_id).unwrap();
+            let values = node
+                .get_ports()
+                .iter()
+                .filter_map(|port| {
+                    let serializer = self.serializers().get(port.get_type_id())?;
+                    Some(SavedValue {
+                        port: port.get_name().to_string(),
+                        value: serializer.save(self.get_cache(), port.get_default_cache_index())?,
+                    })
+                })
+                .collect();
+
+            document_indices.insert(node_id, nodes.len());
+            nodes.push(SavedNode {
+                node_type: node.get_factory_name().to_string(),
+                salt: Some(node.get_salt()),
+                position: self.get_position(node_id),
+                values,
+            });
+        }
+
+        let edges = self
+            .edges()
+            .iter()
+            .map(|edge| SavedEdge {
+                from_node: document_indices[&edge.from_node],
+                from_port: self.get_node(edge.from_node).unwrap().get_ports()[edge.from_port].get_name().to_string(),
+                to_node: document_indices[&edge.to_node],
+                to_port: self.get_node(edge.to_node).unwrap().get_ports()[edge.to_port].get_name().to_string(),
+                feedback: matches!(edge.edge_type, EdgeType::Post),
+            })
+            .collect();
+
+        GraphDocument {
+            version: self.format_version(),
+            seed: self.get_seed(),
+            nodes,
+            edges,
+        }
+    }
+
+    /// Replaces the current graph with the one in [json], running any [Migration]s needed first. Values and edges
+    /// which no longer fit the nodes they belong to are skipped and returned as [LoadWarning]s. Nothing is changed if
+    /// an error is returned, otherwise the undo history is cleared.
+    pub fn load(&mut self, json: &str) -> Result<LoadResult, LoadError> {
+        let mut document: Value = serde_json::from_str(json)?;
+        let mut version = document
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or(LoadError::MissingVersion)? as u32;
+        if version == 0 || version > self.format_version() {
+            return Err(LoadError::UnsupportedVersion(version));
+        }
+        while version < self.format_version() {
+            (self.migrations()[version as usize - 1])(&mut document);
+            version += 1;
+            document["version"] = Value::from(version);
+        }
+        let document: GraphDocument = serde_json::from_value(document)?;
+
+        let mut node_types = Vec::new();
+        for saved_node in &document.nodes {
+            match self.find_factory(&saved_node.node_type) {
+                Some(node_type) => node_types.push(node_type),
+                None => return Err(LoadError::UnknownNodeType(saved_node.node_type.clone())),
+            }
+        }
+
+        self.clear();
+        self.set_seed(document.seed);
+
+        let mut loaded_nodes = Vec::new();
+        let mut warnings = Vec::new();
+        for (index, (saved_node, node_type)) in document.nodes.into_iter().zip(node_types).enumerate() {
+            let node_id = self.add_node(node_type);
+            self.set_salt(node_id, saved_node.salt.unwrap_or(index as u64));
+            if let Some(position) = saved_node.position {
+                self.set_position(node_id, position);
+            }
+            // Some ports only exist once the node has seen the values of others, e.g. the variables of an expression,
+            // so values for ports which aren't there yet are tried again after the ports have been updated.
+            let mut later = Vec::new();
+            for saved_value in saved_node.values {
+                match self.load_value(node_id, &saved_value) {
+                    Some(LoadWarning::UnknownPort { .. }) => later.push(saved_value),
+                    Some(warning) => warnings.push(warning),
+                    None => (),
+                }
+            }
+            self.update_ports(node_id);
+            for saved_value in later {
+                warnings.extend(self.load_value(node_id, &saved_value));
+            }
+            loaded_nodes.push(LoadedNode {
+                node_id,
+                node_type,
+                position: saved_node.position,
+            });
+        }
+
+        for saved_edge in &document.edges {
+            let from = loaded_nodes.get(saved_edge.from_node).map(|node| node.node_id);
+            let to = loaded_nodes.get(saved_edge.to_node).map(|node| node.node_id);
+            let from_port = from.and_then(|from| self.find_port(from, &saved_edge.from_port, Direction::Output));
+            let to_port = to.and_then(|to| self.find_port(to, &saved_edge.to_port, Direction::Input));
+            let result = match (from, from_port, to, to_port) {
+                (Some(from), Some(from_port), Some(to), Some(to_port)) if saved_edge.feedback => {
+                    self.add_feedback_edge(from, from_port, to, to_port)
+                }
+                (Some(from), Some(from_port), Some(to), Some(to_port)) => self.add_edge(from, from_port, to, to_port),
+                _ => {
+                    warnings.push(LoadWarning::MissingEdgeEnd {
+                        from_port: saved_edge.from_port.clone(),
+                        to_port: saved_edge.to_port.clone(),
+                    });
+                    continue;
+                }
+            };
+            if let Err(error) = result {
+                warnings.push(LoadWarning::Edge(error));
+            }
+        }
+
+        self.clear_history();
+        Ok(LoadResult {
+            nodes: loaded_nodes,
+            warnings,
+        })
+    }
+
+    fn load_value(&mut self, node_id: NodeId, saved_value: &SavedValue) -> Option<LoadWarning> {
+        let node = self.get_node(node_id).unwrap();
+        let node_type = node.get_factory_name().to_string();
+        let port = match node.get_ports().iter().find(|port| port.get_name() == saved_value.port) {
+            Some(port) => port,
+            None => {
+                return Some(LoadWarning::UnknownPort {
+                    node_type,
+                    port: saved_value.port.clone(),
+                })
+            }
+        };
+        let type_name = self.get_type_name(port);
+        let cache_index = port.get_default_cache_index().clone();
+        if self.deserialize_value(&cache_index, saved_value.value.clone()) {
+            None
+        } else {
+            Some(LoadWarning::InvalidValue {
+                node_type,
+                port: saved_value.port.clone(),
+                type_name,
+            })
+        }
+    }
+
+    pub(crate) fn find_port(&self, node_id: NodeId, name: &str, direction: Direction) -> Option<usize> {
+        self.get_node(node_id)?
+            .get_ports()
+            .iter()
+            .position(|port| port.get_name() == name && port.get_direction() == &direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::{self, expression_node, math_node, vector_node};
+
+    // Ports of the "Add" node.
+    const A: usize = 0;
+    const RESULT: usize = 2;
+
+    fn number(app: &App, node_id: NodeId, port: usize) -> f64 {
+        let port = &app.get_node(node_id).unwrap().get_ports()[port];
+        *app.get_cache().get::<f64>(port.get_cache_index()).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut app = nodes::new_app();
+        let a = app.add_node(math_node::add::NAME);
+        let b = app.add_node(math_node::add::NAME);
+        app.set_value(a, A, 2.);
+        app.set_position(a, (10., 20.));
+        app.add_edge(a, RESULT, b, A).unwrap();
+        let json = app.save().to_json();
+
+        let mut loaded_app = nodes::new_app();
+        loaded_app.add_node(vector_node::NAME);
+        let loaded = loaded_app.load(&json).unwrap();
+        assert!(loaded.warnings.is_empty());
+        assert_eq!(loaded.nodes.len(), 2);
+        assert_eq!(loaded.nodes[0].position, Some((10., 20.)));
+        assert_eq!(loaded_app.nodes().len(), 2);
+        assert_eq!(loaded_app.edges().len(), 1);
+        loaded_app.compute();
+        assert_eq!(number(&loaded_app, loaded.nodes[1].node_id, RESULT), 2.);
+    }
+
+    #[test]
+    fn values_of_added_ports_are_loaded() {
+        let mut app = nodes::new_app();
+        let node = app.add_node(expression_node::NAME);
+        app.set_value(node, expression_node::FORMULA, String::from("x * 2"));
+        app.set_value(node, expression_node::FIRST_VARIABLE, 3.);
+        let json = app.save().to_json();
+
+        let mut loaded_app = nodes::new_app();
+        let loaded = loaded_app.load(&json).unwrap();
+        assert!(loaded.warnings.is_empty());
+        loaded_app.compute();
+        assert_eq!(number(&loaded_app, loaded.nodes[0].node_id, expression_node::RESULT), 6.);
+    }
+
+    #[test]
+    fn skipped_parts_become_warnings() {
+        let json = r#"{"version": 1, "nodes": [
+            {"node_type": "Add", "values": [{"port": "A", "value": "text"}, {"port": "C", "value": 1}]},
+            {"node_type": "Add"}
+        ], "edges": [
+            {"from_node": 0, "from_port": "Result", "to_node": 1, "to_port": "C"},
+            {"from_node": 0, "from_port": "Result", "to_node": 0, "to_port": "A", "feedback": true}
+        ]}"#;
+        // Without feedback types, so that the last edge can't be added.
+        let mut app = App::new()
+            .with_factories(nodes::node_factories())
+            .with_serializers(nodes::serializers())
+            .with_type_names(nodes::type_names());
+        let loaded = app.load(json).unwrap();
+        let warnings: Vec<String> = loaded.warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            warnings,
+            [
+                "Skipped value for 'A' on Add: it isn't a valid Number",
+                "Skipped value for 'C': Add has no such port",
+                "Skipped edge from 'Result' to 'C': port not found",
+                "Skipped edge: 'Result' (Number) can't be part of a feedback loop",
+            ]
+        );
+        assert_eq!(app.nodes().len(), 2);
+    }
+
+    #[test]
+    fn errors_leave_graph_unchanged() {
+        let mut app = nodes::new_app();
+        app.add_node(math_node::add::NAME);
+        assert!(matches!(
+            app.load(r#"{"version": 99, "nodes": [], "edges": []}"#),
+            Err(LoadError::UnsupportedVersion(99))
+        ));
+        assert!(matches!(
+            app.load(r#"{"version": 1, "nodes": [{"node_type": "Nope"}], "edges": []}"#),
+            Err(LoadError::UnknownNodeType(_))
+        ));
+        assert!(matches!(app.load(r#"{"nodes": []}"#), Err(LoadError::MissingVersion)));
+        assert_eq!(app.nodes().len(), 1);
+    }
+
+    #[test]
+    fn migrations_upgrade_old_documents() {
+        let rename: Migration = |document| {
+            for node in document["nodes"].as_array_mut().unwrap() {
+                if node["node_type"] == "Sum" {
+                    node["node_type"] = Value::from("Add");
+                }
+            }
+        };
+        let mut app = nodes::new_app().with_migrations(vec![rename]);
+        let loaded = app.load(r#"{"version": 1, "nodes": [{"node_type": "Sum"}], "edges": []}"#).unwrap();
+        assert_eq!(loaded.nodes[0].node_type, math_node::add::NAME);
+        assert_eq!(app.save().version, 2);
+    }
+}
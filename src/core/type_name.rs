@@ -0,0 +1,25 @@
+use std::any::TypeId;
+
+/// A readable name for values of one type, shown to the user (e.g. in error messages) instead of the type's full name.
+/// See [App::with_type_names].
+pub struct TypeName {
+    type_id: TypeId,
+    name: &'static str,
+}
+
+impl TypeName {
+    pub fn new<T: 'static>(name: &'static str) -> TypeName {
+        TypeName {
+            type_id: TypeId::of::<T>(),
+            name,
+        }
+    }
+
+    pub fn type_id(&self) -> &TypeId {
+        &self.type_id
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
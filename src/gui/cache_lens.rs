@@ -2,22 +2,22 @@ use std::{cell::RefCell, marker::PhantomData, rc::Rc};
 
 use druid::{Data, Lens};
 
-use crate::core::{App, Direction};
+use crate::core::{App, Direction, NodeId};
 
 pub struct CacheLens<T> {
     phantom: PhantomData<T>,
-    node_index: usize,
+    node_id: NodeId,
     port_index: usize,
 }
 
 impl<T> CacheLens<T> {
     pub fn new(
-        node_index: usize,
+        node_id: NodeId,
         port_index: usize,
     ) -> CacheLens<T> {
         CacheLens {
             phantom: PhantomData::<T>,
-            node_index,
+            node_id,
             port_index
         }
     }
@@ -27,7 +27,8 @@ impl<T: Data + 'static> Lens<Rc<RefCell<App>>, T> for CacheLens<T> {
     fn with<R, F: FnOnce(&T) -> R>(&self, data: &Rc<RefCell<App>>, f: F) -> R {
         let app = data.borrow();
         let cache_index = app
-            .get_node(self.node_index)
+            .get_node(self.node_id)
+            .unwrap()
             .get_ports()
             .get(self.port_index)
             .unwrap()
@@ -39,7 +40,8 @@ impl<T: Data + 'static> Lens<Rc<RefCell<App>>, T> for CacheLens<T> {
     fn with_mut<R, F: FnOnce(&mut T) -> R>(&self, data: &mut Rc<RefCell<App>>, f: F) -> R {
         let app = data.borrow();
         let port = app
-            .get_node(self.node_index)
+            .get_node(self.node_id)
+            .unwrap()
             .get_ports()
             .get(self.port_index)
             .unwrap();
@@ -57,7 +59,7 @@ impl<T: Data + 'static> Lens<Rc<RefCell<App>>, T> for CacheLens<T> {
 
         if can_mutate && changed {
             let mut app_mut = data.borrow_mut();
-            app_mut.get_cache_mut().set(&cache_index, new_value);
+            app_mut.set_value(self.node_id, self.port_index, new_value);
         }
 
         result
@@ -1,26 +1,36 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use druid::{AppDelegate, Command, DelegateCtx, Env, Handled, Point, Selector, Target, Widget, WindowId};
+use druid::{commands, AppDelegate, Command, DelegateCtx, Env, FileSpec, Handled, Point, Selector, Target, Widget, WindowId};
 
-use crate::core::App;
+use crate::core::{App, Direction, EdgeRecord, Edit, NodeId};
+
+use super::{graph_viewer::SHOW_ERROR, graph_widget::{CLEAR_GRAPH, SYNC_GRAPH}};
 
 pub const ADD_NODE: Selector<(&'static str, Point)> = Selector::new("add_node");
-pub const ADD_EDGE: Selector<(usize, usize)> = Selector::new("begin_edge");
+pub const ADD_EDGE: Selector<(NodeId, usize)> = Selector::new("begin_edge");
 pub const ADD_NODE_WIDGET: Selector<(
-    usize,
+    NodeId,
     Point,
-    fn(index: usize) -> Box<dyn Widget<Rc<RefCell<App>>>>,
+    fn(index: NodeId) -> Box<dyn Widget<Rc<RefCell<App>>>>,
 )> = Selector::new("add_node_widget");
 
+// Playback
+pub const TOGGLE_PLAYBACK: Selector = Selector::new("toggle_playback");
+pub const STEP: Selector = Selector::new("step");
+pub const RESET: Selector = Selector::new("reset");
+pub const SET_SPEED: Selector<f64> = Selector::new("set_speed");
+
+pub const GRAPH_FILE_TYPE: FileSpec = FileSpec::new("Graph", &["json"]);
+
 pub struct Delegate {
     creating_new_edge: bool,
-    current_edge_end: Option<(usize, usize)>,
-    node_widget_factories: HashMap<&'static str, fn(index: usize) -> Box<dyn Widget<Rc<RefCell<App>>>>>,
+    current_edge_end: Option<(NodeId, usize)>,
+    node_widget_factories: HashMap<&'static str, fn(index: NodeId) -> Box<dyn Widget<Rc<RefCell<App>>>>>,
 }
 
 impl Delegate {
     pub fn new(
-        node_widget_factories: HashMap<&'static str, fn(index: usize) -> Box<dyn Widget<Rc<RefCell<App>>>>>,
+        node_widget_factories: HashMap<&'static str, fn(index: NodeId) -> Box<dyn Widget<Rc<RefCell<App>>>>>,
     ) -> Delegate {
         Delegate {
             creating_new_edge: false,
@@ -44,19 +54,33 @@ impl AppDelegate<Rc<RefCell<App>>> for Delegate {
 
         if command.is(ADD_NODE) {
             let (new_node_type, new_node_position) = command.get(ADD_NODE).unwrap();
-            let new_node = app.add_node(*new_node_type);
-            ctx.submit_command(Command::new(
-                ADD_NODE_WIDGET,
-                (new_node, *new_node_position, *self.node_widget_factories.get(*new_node_type).unwrap()),
-                Target::Global,
-            ));
+            let _ = app.edit(Edit::AddNode {
+                node_type: *new_node_type,
+                position: (new_node_position.x, new_node_position.y),
+            });
+            ctx.submit_command(Command::new(SYNC_GRAPH, (), Target::Global));
         } else if command.is(ADD_EDGE) {
             if let Some(end) = command.get(ADD_EDGE) {
                 match self.current_edge_end {
                     Some(start) => {
                         if start.0 != end.0 {
-                            // TODO: Receiving node needs to say this is okay. Can sort this out later when I move the whole connect func to be dynamic.
-                            app.add_edge(start.0, start.1, end.0, end.1);
+                            // Edges can be dragged in either direction.
+                            let start_is_input = app
+                                .get_node(start.0)
+                                .and_then(|node| node.get_ports().get(start.1))
+                                .map_or(false, |port| port.get_direction() == &Direction::Input);
+                            let (from, to) = if start_is_input { (*end, start) } else { (start, *end) };
+
+                            let edge = EdgeRecord {
+                                from_node: from.0,
+                                from_port: from.1,
+                                to_node: to.0,
+                                to_port: to.1,
+                                feedback: false,
+                            };
+                            if let Err(error) = app.edit(Edit::Connect(edge)) {
+                                ctx.submit_command(Command::new(SHOW_ERROR, error.to_string(), Target::Global));
+                            }
                         }
                         self.creating_new_edge = false;
                         self.current_edge_end = None;
@@ -69,6 +93,46 @@ impl AppDelegate<Rc<RefCell<App>>> for Delegate {
                 }
                 return Handled::Yes;
             }
+        } else if command.is(TOGGLE_PLAYBACK) {
+            app.toggle_playback();
+            return Handled::Yes;
+        } else if command.is(STEP) {
+            app.step();
+            return Handled::Yes;
+        } else if command.is(RESET) {
+            app.reset();
+            return Handled::Yes;
+        } else if let Some(speed) = command.get(SET_SPEED) {
+            app.set_speed(*speed);
+            return Handled::Yes;
+        } else if let Some(file_info) = command.get(commands::OPEN_FILE) {
+            let loaded = std::fs::read_to_string(file_info.path())
+                .map_err(|error| error.to_string())
+                .and_then(|json| app.load(&json).map_err(|error| error.to_string()));
+
+            match loaded {
+                Ok(loaded) => {
+                    ctx.submit_command(Command::new(CLEAR_GRAPH, (), Target::Global));
+                    for loaded_node in loaded.nodes {
+                        let (x, y) = loaded_node.position.unwrap_or((0., 0.));
+                        ctx.submit_command(Command::new(
+                            ADD_NODE_WIDGET,
+                            (
+                                loaded_node.node_id,
+                                Point::new(x, y),
+                                *self.node_widget_factories.get(loaded_node.node_type).unwrap(),
+                            ),
+                            Target::Global,
+                        ));
+                    }
+                    if !loaded.warnings.is_empty() {
+                        let warnings: Vec<String> = loaded.warnings.iter().map(ToString::to_string).collect();
+                        ctx.submit_command(Command::new(SHOW_ERROR, warnings.join("\n"), Target::Global));
+                    }
+                }
+                Err(error) => ctx.submit_command(Command::new(SHOW_ERROR, error, Target::Global)),
+            }
+            return Handled::Yes;
         }
         Handled::No
     }
@@ -1,15 +1,23 @@
-use druid::{BoxConstraints, Command, Data, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx, Menu, MenuItem, PaintCtx, Point, Size, Target, UpdateCtx, Widget, WidgetPod, widget::{Axis, Scroll}};
+use druid::{commands, FileDialogOptions, piet::{Text, TextLayout, TextLayoutBuilder}, BoxConstraints, Color, Command, Data, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx, Menu, MenuItem, PaintCtx, Point, RenderContext, Selector, Size, Target, UpdateCtx, Widget, WidgetPod, widget::{Axis, Scroll}};
 
-use super::delegate::ADD_NODE;
+use super::delegate::{ADD_NODE, GRAPH_FILE_TYPE, RESET, SET_SPEED, STEP, TOGGLE_PLAYBACK};
+
+/// Displays a message in the corner of the graph until the next click.
+pub const SHOW_ERROR: Selector<String> = Selector::new("show_error");
 
 pub struct GraphViewer<T, W> {
-    inner: WidgetPod<T, Scroll<T, W>>
+    inner: WidgetPod<T, Scroll<T, W>>,
+    /// The node types listed in the context menu, as submenus of categories.
+    node_categories: Vec<(&'static str, Vec<&'static str>)>,
+    error_message: Option<String>,
 }
 
 impl<T: Data, W: Widget<T>> GraphViewer<T, W> {
-    pub fn new(inner: Scroll<T, W>) -> GraphViewer<T, W> {
+    pub fn new(inner: Scroll<T, W>, node_categories: Vec<(&'static str, Vec<&'static str>)>) -> GraphViewer<T, W> {
         GraphViewer {
-            inner: WidgetPod::new(inner)
+            inner: WidgetPod::new(inner),
+            node_categories,
+            error_message: None,
         }
     }
 }
@@ -23,18 +31,55 @@ impl<T: Data, W: Widget<T>> Widget<T> for GraphViewer<T, W> {
         env: &Env,
     ) {
         match event {
+            Event::Command(command) => {
+                if let Some(message) = command.get(SHOW_ERROR) {
+                    self.error_message = Some(message.clone());
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseDown(_) => {
+                if self.error_message.take().is_some() {
+                    ctx.request_paint();
+                }
+            }
             Event::MouseUp(mouse) => {
                 if mouse.button.is_right() {
                     let mut menu = Menu::new("Add Node");
 
-                    for i in ["Value", "Vector2D", "Particle", "Circle"].iter() {
-                        menu = menu.entry(MenuItem::new(i.to_string()).command(Command::new(
-                            ADD_NODE,
-                            (i, (self.inner.widget().viewport_rect().origin().to_vec2() + mouse.pos.to_vec2()).to_point()),
-                            Target::Global,
-                        )));
+                    let position = (self.inner.widget().viewport_rect().origin().to_vec2() + mouse.pos.to_vec2()).to_point();
+                    for (category, node_types) in &self.node_categories {
+                        let mut category_menu = Menu::new(*category);
+                        for node_type in node_types {
+                            category_menu = category_menu.entry(MenuItem::new(*node_type).command(Command::new(
+                                ADD_NODE,
+                                (*node_type, position),
+                                Target::Global,
+                            )));
+                        }
+                        menu = menu.entry(category_menu);
                     }
                     
+                    let file_dialog_options = FileDialogOptions::new()
+                        .allowed_types(vec![GRAPH_FILE_TYPE])
+                        .default_type(GRAPH_FILE_TYPE);
+                    menu = menu
+                        .separator()
+                        .entry(MenuItem::new("Save Graph...").command(commands::SHOW_SAVE_PANEL.with(file_dialog_options.clone())))
+                        .entry(MenuItem::new("Open Graph...").command(commands::SHOW_OPEN_PANEL.with(file_dialog_options)));
+
+                    let mut speed_menu = Menu::new("Speed");
+                    for speed in [0.25, 0.5, 1., 2., 4.].iter() {
+                        speed_menu = speed_menu.entry(
+                            MenuItem::new(format!("{}x", speed)).command(SET_SPEED.with(*speed)),
+                        );
+                    }
+                    menu = menu
+                        .separator()
+                        .entry(MenuItem::new("Play/Pause").command(TOGGLE_PLAYBACK))
+                        .entry(MenuItem::new("Step").command(STEP))
+                        .entry(MenuItem::new("Reset").command(RESET))
+                        .entry(speed_menu);
+
                     ctx.show_context_menu::<T>(
                         menu,
                         mouse.pos,
@@ -81,5 +126,16 @@ impl<T: Data, W: Widget<T>> Widget<T> for GraphViewer<T, W> {
 
     fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
         self.inner.paint(ctx, data, env);
+
+        if let Some(message) = &self.error_message {
+            let layout = ctx
+                .text()
+                .new_text_layout(message.clone())
+                .text_color(Color::rgb8(230, 80, 80))
+                .build()
+                .unwrap();
+            let height = ctx.size().height;
+            ctx.draw_text(&layout, (10., height - layout.size().height - 10.));
+        }
     }
 }
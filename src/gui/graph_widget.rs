@@ -1,12 +1,18 @@
 use std::{cell::RefCell, collections::HashMap, ops::Add, rc::Rc, time::Instant};
 
-use druid::{BoxConstraints, Code, Color, Command, Env, Event, EventCtx, KeyCode, LayoutCtx, LifeCycle, LifeCycleCtx, LocalizedString, Menu, MenuItem, PaintCtx, Point, RenderContext, Selector, Size, Target, UpdateCtx, Widget, WidgetPod, kurbo::QuadBez, widget::LabelText};
+use druid::{commands, BoxConstraints, Code, Color, Command, Env, Event, EventCtx, KeyCode, LayoutCtx, LifeCycle, LifeCycleCtx, LocalizedString, Menu, MenuItem, PaintCtx, Point, RenderContext, Selector, Size, Target, UpdateCtx, Widget, WidgetPod, kurbo::QuadBez, widget::LabelText};
 
-use crate::core::App;
+use crate::core::{App, Edit, EdgeType, NodeId};
 
-use super::delegate::{ADD_NODE, ADD_NODE_WIDGET};
+use super::{delegate::{ADD_NODE, ADD_NODE_WIDGET}, graph_viewer::SHOW_ERROR};
 
-pub const REGISTER_PORT_LOCATION: Selector<(usize, usize, PortDirection, Point)> =
+/// Removes every node widget, e.g. before a saved graph is loaded.
+pub const CLEAR_GRAPH: Selector<()> = Selector::new("clear_graph");
+
+/// Adds/removes/moves node widgets to match the [App], e.g. after an undo.
+pub const SYNC_GRAPH: Selector<()> = Selector::new("sync_graph");
+
+pub const REGISTER_PORT_LOCATION: Selector<(NodeId, usize, PortDirection, Point)> =
     Selector::new("register_port_location");
 
 #[derive(Clone, Copy)]
@@ -32,29 +38,36 @@ impl GraphWidgetNode {
 }
 
 pub struct Graph {
-    nodes: Vec<GraphWidgetNode>, // TODO: Will need to replace with AllocatedVec so that the indexes match if nodes are removed.
+    nodes: HashMap<NodeId, GraphWidgetNode>,
+    node_widget_factories: HashMap<&'static str, fn(index: NodeId) -> Box<dyn Widget<Rc<RefCell<App>>>>>,
     // maybe replace edges with their own widgets so that they can be selected and stuff.
-    port_locations: HashMap<usize, (HashMap<usize, Point>, HashMap<usize, Point>)>,
-    node_render_order: Vec<usize>,
+    port_locations: HashMap<NodeId, (HashMap<usize, Point>, HashMap<usize, Point>)>,
+    node_render_order: Vec<NodeId>,
     is_translating_nodes: bool,
+    /// Where the selected nodes were when the current drag started, so the move can be recorded once it ends.
+    drag_start_positions: HashMap<NodeId, Point>,
     last_mouse_pos: Point,
     last_layout_instant: Instant,
 }
 
 impl Graph {
-    pub fn new() -> Self {
+    pub fn new(
+        node_widget_factories: HashMap<&'static str, fn(index: NodeId) -> Box<dyn Widget<Rc<RefCell<App>>>>>,
+    ) -> Self {
         Graph {
-            nodes: Vec::new(),
+            nodes: HashMap::new(),
+            node_widget_factories,
             port_locations: HashMap::new(),
             node_render_order: Vec::new(),
             is_translating_nodes: false,
+            drag_start_positions: HashMap::new(),
             last_mouse_pos: Point::ZERO,
             last_layout_instant: Instant::now(),
         }
     }
 
     fn deselect_all_nodes(&mut self, ctx: &mut EventCtx) {
-        self.nodes.iter_mut().for_each(|node| {
+        self.nodes.values_mut().for_each(|node| {
             node.is_selected = false;
             ctx.submit_command(Command::new(
                 Selector::<bool>::new("update_selected"),
@@ -64,8 +77,58 @@ impl Graph {
         });
     }
 
-    fn find_node_at_pos(&self, pos: &Point) -> Option<&usize> {
-        self.node_render_order.iter().rev().find(|&&node_id| {
+    fn port_location(&self, node_id: NodeId, port: usize, direction: PortDirection) -> Option<Point> {
+        let (inputs, outputs) = self.port_locations.get(&node_id)?;
+        let location = match direction {
+            PortDirection::Input => inputs.get(&port)?,
+            PortDirection::Output => outputs.get(&port)?,
+        };
+        Some(*location + self.nodes.get(&node_id)?.position.to_vec2())
+    }
+
+    fn sync_with_app(&mut self, ctx: &mut EventCtx, app: &App) {
+        let removed_nodes: Vec<NodeId> = self
+            .nodes
+            .keys()
+            .filter(|node_id| app.get_node(**node_id).is_none())
+            .copied()
+            .collect();
+        for node_id in &removed_nodes {
+            self.nodes.remove(node_id);
+            self.port_locations.remove(node_id);
+        }
+        let nodes = &self.nodes;
+        self.node_render_order.retain(|node_id| nodes.contains_key(node_id));
+        let mut children_changed = !removed_nodes.is_empty();
+
+        for node_id in app.nodes().handles() {
+            let position = app.get_position(node_id).map(|(x, y)| Point::new(x, y));
+            match self.nodes.get_mut(&node_id) {
+                Some(node) => {
+                    if let Some(position) = position {
+                        node.position = position;
+                    }
+                }
+                None => {
+                    let node_type = app.get_node(node_id).unwrap().get_factory_name();
+                    if let Some(factory) = self.node_widget_factories.get(node_type) {
+                        let node = GraphWidgetNode::new(factory(node_id), position.unwrap_or(Point::ZERO));
+                        self.nodes.insert(node_id, node);
+                        self.node_render_order.push(node_id);
+                        children_changed = true;
+                    }
+                }
+            }
+        }
+
+        if children_changed {
+            ctx.children_changed();
+        }
+        ctx.request_layout();
+    }
+
+    fn find_node_at_pos(&self, pos: &Point) -> Option<&NodeId> {
+        self.node_render_order.iter().rev().find(|node_id| {
             self.nodes
                 .get(node_id)
                 .unwrap()
@@ -78,20 +141,40 @@ impl Graph {
 
 impl Widget<Rc<RefCell<App>>> for Graph {
     fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Rc<RefCell<App>>, env: &Env) {
-        for node_index in self.node_render_order.iter().rev() {
-            let node = self.nodes.get_mut(*node_index).unwrap();
+        // Everything changed by a single click or drag (e.g. dragging a slider) is undone in one step.
+        if let Event::MouseDown(_) = event {
+            data.borrow_mut().begin_edit_group();
+        }
+
+        for node_id in self.node_render_order.iter().rev() {
+            let node = self.nodes.get_mut(node_id).unwrap();
             node.widget.event(ctx, event, data, env);
         }
 
         match event {
             Event::Command(command) => {
                 if command.is(ADD_NODE_WIDGET) {
-                    let (index, position, func) = command.get(ADD_NODE_WIDGET).unwrap();
-                    let node = GraphWidgetNode::new(func(*index), *position);
-                    self.node_render_order.push(self.nodes.len());
-                    self.nodes.push(node);
+                    let (node_id, position, func) = command.get(ADD_NODE_WIDGET).unwrap();
+                    let node = GraphWidgetNode::new(func(*node_id), *position);
+                    self.node_render_order.push(*node_id);
+                    self.nodes.insert(*node_id, node);
+                    ctx.children_changed();
+                }
+                if command.is(CLEAR_GRAPH) {
+                    self.nodes.clear();
+                    self.node_render_order.clear();
+                    self.port_locations.clear();
                     ctx.children_changed();
                 }
+                if command.is(SYNC_GRAPH) {
+                    self.sync_with_app(ctx, &data.borrow());
+                }
+                if let Some(file_info) = command.get(commands::SAVE_FILE_AS) {
+                    let json = data.borrow().save().to_json();
+                    if let Err(error) = std::fs::write(file_info.path(), json) {
+                        ctx.submit_command(Command::new(SHOW_ERROR, error.to_string(), Target::Global));
+                    }
+                }
                 if command.is(REGISTER_PORT_LOCATION) {
                     let (node, port, direction, position) =
                         command.get(REGISTER_PORT_LOCATION).unwrap();
@@ -109,13 +192,13 @@ impl Widget<Rc<RefCell<App>>> for Graph {
                         PortDirection::Input => inputs,
                         PortDirection::Output => outputs,
                     })
-                    .insert(*port, (*position - self.nodes[*node].position).to_point());
+                    .insert(*port, (*position - self.nodes[node].position).to_point());
                 }
             }
             Event::MouseDown(mouse) => {
                 if mouse.button.is_left() {
                     let mut has_active = false;
-                    for node in &self.nodes {
+                    for node in self.nodes.values() {
                         if node.widget.is_active() {
                             has_active = true;
                             break;
@@ -130,15 +213,15 @@ impl Widget<Rc<RefCell<App>>> for Graph {
                         let node_index = self.find_node_at_pos(&mouse.pos);
 
                         if let Some(value) = node_index {
-                            let index = value.clone();
+                            let node_id = *value;
                             let render_order_index = self
                                 .node_render_order
                                 .iter()
-                                .position(|node_index| node_index == &index)
+                                .position(|other_node_id| other_node_id == &node_id)
                                 .unwrap();
                             self.node_render_order.remove(render_order_index);
-                            self.node_render_order.push(index);
-                            let node = self.nodes.get_mut(index).unwrap();
+                            self.node_render_order.push(node_id);
+                            let node = self.nodes.get_mut(&node_id).unwrap();
 
                             node.is_selected = true;
                             ctx.request_paint();
@@ -146,7 +229,14 @@ impl Widget<Rc<RefCell<App>>> for Graph {
 
                         self.is_translating_nodes = true;
                         self.last_mouse_pos = mouse.pos;
+                        self.drag_start_positions = self
+                            .nodes
+                            .iter()
+                            .filter(|(_, node)| node.is_selected)
+                            .map(|(node_id, node)| (*node_id, node.position))
+                            .collect();
                     } else {
+                        ctx.request_focus();
                         self.deselect_all_nodes(ctx);
                         ctx.request_paint();
                     }
@@ -154,17 +244,29 @@ impl Widget<Rc<RefCell<App>>> for Graph {
                     self.is_translating_nodes = false;
                 }
             }
-            Event::MouseUp(mouse) => {
+            Event::MouseUp(_mouse) => {
+                let mut app = data.borrow_mut();
                 if self.is_translating_nodes {
+                    for (node_id, start_position) in self.drag_start_positions.drain() {
+                        if let Some(node) = self.nodes.get(&node_id) {
+                            if node.position != start_position {
+                                let _ = app.edit(Edit::MoveNode {
+                                    node_id,
+                                    position: (node.position.x, node.position.y),
+                                });
+                            }
+                        }
+                    }
                     ctx.request_layout();
                 }
                 self.is_translating_nodes = false;
+                app.end_edit_group();
             }
             Event::MouseMove(mouse) => {
                 // TODO: is_translating_nodes might be able to be replace by simply checking mouse.buttons? Lol.
                 if self.is_translating_nodes {
                     let delta = mouse.pos - self.last_mouse_pos;
-                    self.nodes.iter_mut().for_each(|node| {
+                    self.nodes.values_mut().for_each(|node| {
                         if node.is_selected {
                             node.position += (delta.x, delta.y);
                         }
@@ -175,11 +277,34 @@ impl Widget<Rc<RefCell<App>>> for Graph {
                     }
                 }
             }
-            // Event::KeyDown(key) => {
-            //     if let Code::Delete = key.code {
-                    
-            //     }
-            // }
+            Event::KeyDown(key) if !ctx.is_handled() => {
+                let mut app = data.borrow_mut();
+                let is_shortcut = key.mods.ctrl() || key.mods.meta();
+                let changed = match key.code {
+                    Code::KeyZ if is_shortcut && key.mods.shift() => app.redo(),
+                    Code::KeyZ if is_shortcut => app.undo(),
+                    Code::KeyY if is_shortcut => app.redo(),
+                    Code::Space => {
+                        app.toggle_playback();
+                        false
+                    }
+                    Code::Delete => {
+                        app.begin_edit_group();
+                        for (node_id, node) in &self.nodes {
+                            if node.is_selected {
+                                let _ = app.edit(Edit::RemoveNode(*node_id));
+                            }
+                        }
+                        app.end_edit_group();
+                        true
+                    }
+                    _ => false,
+                };
+                if changed {
+                    self.sync_with_app(ctx, &app);
+                    ctx.set_handled();
+                }
+            }
             _ => (),
         }
     }
@@ -191,8 +316,8 @@ impl Widget<Rc<RefCell<App>>> for Graph {
         data: &Rc<RefCell<App>>,
         env: &Env,
     ) {
-        for node_index in &self.node_render_order {
-            let node = self.nodes.get_mut(*node_index).unwrap();
+        for node_id in &self.node_render_order {
+            let node = self.nodes.get_mut(node_id).unwrap();
             node.widget.lifecycle(ctx, event, data, env);
         }
     }
@@ -204,8 +329,8 @@ impl Widget<Rc<RefCell<App>>> for Graph {
         data: &Rc<RefCell<App>>,
         env: &Env,
     ) {
-        for node_index in &self.node_render_order {
-            let node = self.nodes.get_mut(*node_index).unwrap();
+        for node_id in &self.node_render_order {
+            let node = self.nodes.get_mut(node_id).unwrap();
             node.widget.update(ctx, data, env);
         }
     }
@@ -218,8 +343,8 @@ impl Widget<Rc<RefCell<App>>> for Graph {
         env: &Env,
     ) -> Size {
         let child_box_constraints = BoxConstraints::new(Size::ZERO, Size::new(1000., 1000.));
-        for node_index in &self.node_render_order {
-            let node = self.nodes.get_mut(*node_index).unwrap();
+        for node_id in &self.node_render_order {
+            let node = self.nodes.get_mut(node_id).unwrap();
             node.widget.layout(ctx, &child_box_constraints, data, env);
             node.widget.set_origin(ctx, data, env, node.position);
         }
@@ -234,33 +359,31 @@ impl Widget<Rc<RefCell<App>>> for Graph {
         ctx.fill(clip_rect, &Color::rgb8(15, 15, 30));
 
         for edge in data.borrow().edges() {
-            let start = *self
-                .port_locations
-                .get(&edge.from_node)
-                .unwrap()
-                .1 // outputs
-                .get(&edge.from_port)
-                .unwrap()
-                + self.nodes.get(edge.from_node).unwrap().position.to_vec2();
-            let end = *self
-                .port_locations
-                .get(&edge.to_node)
-                .unwrap()
-                .0 // inputs
-                .get(&edge.to_port)
-                .unwrap()
-                + self.nodes.get(edge.to_node).unwrap().position.to_vec2();
+            // Ports register their locations after their node widget is added, so may not be known yet.
+            let start = match self.port_location(edge.from_node, edge.from_port, PortDirection::Output) {
+                Some(start) => start,
+                None => continue,
+            };
+            let end = match self.port_location(edge.to_node, edge.to_port, PortDirection::Input) {
+                Some(end) => end,
+                None => continue,
+            };
             let path = QuadBez::new(
                 start,
                 // need to figure out a cheaper way to droop the cables. Or maybe not?
                 Point::lerp(start, end, 0.5).add((0., 1. * ((start - end).hypot() + 1.).log(1.1))),
                 end,
             );
-            ctx.stroke(path, &Color::rgb8(100, 100, 100), 2.0);
+            let color = match edge.edge_type {
+                EdgeType::Normal => Color::rgb8(100, 100, 100),
+                // Feedback edges are a different color since their value arrives a frame late.
+                EdgeType::Post => Color::rgb8(100, 100, 160),
+            };
+            ctx.stroke(path, &color, 2.0);
         }
 
-        for node_index in &self.node_render_order {
-            let node = self.nodes.get_mut(*node_index).unwrap();
+        for node_id in &self.node_render_order {
+            let node = self.nodes.get_mut(node_id).unwrap();
             if node.is_selected {
                 let node_rect = node.widget.layout_rect();
                 ctx.stroke(
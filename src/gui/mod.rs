@@ -4,4 +4,5 @@ pub mod graph_widget;
 pub mod node_widget;
 pub mod port_widget;
 pub mod viewer_2d;
-pub mod cache_lens;
\ No newline at end of file
+pub mod cache_lens;
+pub mod standard_widget;
@@ -1,15 +1,17 @@
-use std::{ops::Add, time::Duration};
+use std::{any::TypeId, ops::Add, time::Duration};
 
 use druid::{kurbo::Circle, widget::prelude::*, Color, Command, Point, Target, TimerToken};
 
-use crate::gui::delegate::ADD_EDGE;
+use nalgebra::Vector2;
+
+use crate::{core::NodeId, gui::delegate::ADD_EDGE, nodes::common::{shapes::Circle, Bounds, ForceField, Particle, ParticleSystem}};
 
 use super::graph_widget::{PortDirection, REGISTER_PORT_LOCATION};
 
 const RADIUS: f64 = 5.;
 
 pub struct PortWidget {
-    node: usize,
+    node: NodeId,
     port: usize,
     direction: PortDirection,
     color: Color,
@@ -23,8 +25,33 @@ impl PortWidget {
     pub const VECTOR2F64: Color = Color::rgb8(191, 191, 75);
     pub const SHAPE: Color = Color::rgb8(114, 94, 242);
     pub const PARTICLE: Color = Color::rgb8(191, 59, 59);
+    pub const PARTICLE_SYSTEM: Color = Color::rgb8(230, 120, 60);
+    pub const FORCE_FIELD: Color = Color::rgb8(75, 170, 200);
+    pub const BOUNDS: Color = Color::rgb8(200, 200, 200);
+    pub const OTHER: Color = Color::rgb8(140, 140, 140);
+
+    /// Picks the port color for values of the given type.
+    pub fn color_for(type_id: &TypeId) -> Color {
+        if *type_id == TypeId::of::<f64>() {
+            PortWidget::F64
+        } else if *type_id == TypeId::of::<Vector2<f64>>() {
+            PortWidget::VECTOR2F64
+        } else if *type_id == TypeId::of::<Circle>() {
+            PortWidget::SHAPE
+        } else if *type_id == TypeId::of::<Particle>() {
+            PortWidget::PARTICLE
+        } else if *type_id == TypeId::of::<ParticleSystem>() {
+            PortWidget::PARTICLE_SYSTEM
+        } else if *type_id == TypeId::of::<ForceField>() {
+            PortWidget::FORCE_FIELD
+        } else if *type_id == TypeId::of::<Bounds>() {
+            PortWidget::BOUNDS
+        } else {
+            PortWidget::OTHER
+        }
+    }
 
-    pub fn new(node: usize, port: usize, direction: PortDirection, color: Color) -> PortWidget {
+    pub fn new(node: NodeId, port: usize, direction: PortDirection, color: Color) -> PortWidget {
         PortWidget {
             node,
             port,
@@ -0,0 +1,104 @@
+use std::{any::TypeId, cell::RefCell, rc::Rc};
+
+use druid::{
+    widget::{Checkbox, Container, CrossAxisAlignment, Flex, Label, Slider},
+    Color, Widget, WidgetExt,
+};
+
+use crate::{
+    core::{App, Direction, NodeId},
+    gui::{cache_lens::CacheLens, graph_widget::PortDirection, node_widget::NodeWidget, port_widget::PortWidget},
+};
+
+/// Describes a port for [standard_widget]. Generated by [define_node].
+pub struct PortDescription {
+    name: String,
+    index: usize,
+    direction: Direction,
+    type_id: TypeId,
+    /// The range of the slider for f64 inputs.
+    range: (f64, f64),
+}
+
+impl PortDescription {
+    pub fn new<T: 'static>(name: impl Into<String>, index: usize, direction: Direction) -> PortDescription {
+        PortDescription::with_type_id(name, index, direction, TypeId::of::<T>())
+    }
+
+    /// For ports whose type is only known at runtime.
+    pub fn with_type_id(name: impl Into<String>, index: usize, direction: Direction, type_id: TypeId) -> PortDescription {
+        PortDescription {
+            name: name.into(),
+            index,
+            direction,
+            type_id,
+            range: (-5., 5.),
+        }
+    }
+
+    /// Replaces the default slider range of -5 to 5, e.g. for counts or distances in pixels.
+    pub fn with_range(mut self, min: f64, max: f64) -> PortDescription {
+        self.range = (min, max);
+        self
+    }
+}
+
+/// Builds a widget with a row for every port. Unconnected f64 and bool inputs also get a slider/checkbox to edit their
+/// default value.
+pub fn standard_widget(title: &'static str, node: NodeId, ports: Vec<PortDescription>) -> Box<dyn Widget<Rc<RefCell<App>>>> {
+    node_frame(
+        Flex::column()
+            .with_child(Label::new(title))
+            .with_spacer(5.)
+            .with_child(port_list(node, ports)),
+    )
+}
+
+/// The port rows of [standard_widget], inputs first, for nodes which need extra widgets around them.
+pub fn port_list(node: NodeId, ports: Vec<PortDescription>) -> Flex<Rc<RefCell<App>>> {
+    let mut inputs = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+    let mut outputs = Flex::column().cross_axis_alignment(CrossAxisAlignment::End);
+
+    for port in ports {
+        let color = PortWidget::color_for(&port.type_id);
+        match port.direction {
+            Direction::Input => {
+                let mut row = Flex::row()
+                    .with_child(PortWidget::new(node, port.index, PortDirection::Input, color))
+                    .with_spacer(5.)
+                    .with_child(Label::new(port.name));
+                if port.type_id == TypeId::of::<f64>() {
+                    let (min, max) = port.range;
+                    row.add_child(Slider::new().with_range(min, max).lens(CacheLens::<f64>::new(node, port.index)));
+                } else if port.type_id == TypeId::of::<bool>() {
+                    row.add_child(Checkbox::new("").lens(CacheLens::<bool>::new(node, port.index)));
+                }
+                inputs.add_child(row);
+                inputs.add_spacer(5.);
+            }
+            Direction::Output => {
+                outputs.add_child(
+                    Flex::row()
+                        .with_child(Label::new(port.name))
+                        .with_spacer(5.)
+                        .with_child(PortWidget::new(node, port.index, PortDirection::Output, color)),
+                );
+                outputs.add_spacer(5.);
+            }
+        }
+    }
+
+    Flex::column()
+        .with_child(inputs.expand_width())
+        .with_child(outputs.expand_width())
+}
+
+/// Wraps [content] in the background and border shared by every node, and makes it selectable and draggable.
+pub fn node_frame(content: impl Widget<Rc<RefCell<App>>> + 'static) -> Box<dyn Widget<Rc<RefCell<App>>>> {
+    Box::new(NodeWidget::new(
+        Container::new(content.fix_width(200.).padding(5.))
+            .rounded(10.)
+            .background(Color::rgba8(50, 50, 50, 230))
+            .border(Color::rgb8(25, 25, 25), 1.),
+    ))
+}
@@ -1,17 +1,21 @@
-use std::{cell::RefCell, rc::Rc, time::Duration};
+use std::{cell::RefCell, rc::Rc, time::{Duration, Instant}};
 
 use druid::{kurbo, Color, Event, LifeCycle, Point, RenderContext, TimerToken, Widget};
 
-use crate::{core::App, nodes::common::shapes::Circle};
+use crate::{core::App, nodes::common::{shapes::Circle, ParticleSystem}};
 
 pub struct Viewer2D {
     render_timer_token: TimerToken,
+    /// When the simulation was last advanced. The timer is only used to wake up, the elapsed time decides how many
+    /// steps are computed.
+    last_tick: Option<Instant>,
 }
 
 impl Viewer2D {
     pub fn new() -> Viewer2D {
         Viewer2D {
             render_timer_token: TimerToken::INVALID,
+            last_tick: None,
         }
     }
 }
@@ -27,8 +31,12 @@ impl Widget<Rc<RefCell<App>>> for Viewer2D {
         match event {
             Event::Timer(token) => {
                 if *token == self.render_timer_token {
+                    let now = Instant::now();
+                    let elapsed = self.last_tick.map_or(0., |last_tick| (now - last_tick).as_secs_f64());
+                    self.last_tick = Some(now);
+
                     let mut app = data.borrow_mut();
-                    app.compute();
+                    app.advance(elapsed);
                     self.render_timer_token = ctx.request_timer(Duration::from_millis(17));
                     ctx.request_paint();
                 }
@@ -71,28 +79,28 @@ impl Widget<Rc<RefCell<App>>> for Viewer2D {
     fn paint(&mut self, ctx: &mut druid::PaintCtx, data: &Rc<RefCell<App>>, _env: &druid::Env) {
         let clip_rect = ctx.size().to_rect();
         ctx.fill(clip_rect, &Color::BLACK);
-        if data
-            .borrow()
-            .get_cache()
-            .get_all_of_type::<Circle>()
-            .is_some()
-        {
-            for i in data
-                .borrow()
-                .get_cache()
-                .get_all_of_type::<Circle>()
-                .unwrap()
-                .iter()
-            {
-                if let Some(circle) = i {
-                    ctx.fill(
-                        kurbo::Circle::new(
-                            Point::new(circle.get_position().x, circle.get_position().y),
-                            *circle.get_radius(),
-                        ),
-                        &Color::WHITE,
-                    )
-                }
+        let app = data.borrow();
+        for circle in app.get_cache().values_of_type::<Circle>() {
+            ctx.fill(
+                kurbo::Circle::new(
+                    Point::new(circle.get_position().x, circle.get_position().y),
+                    *circle.get_radius(),
+                ),
+                &Color::WHITE,
+            )
+        }
+
+        // Particles fade out over their lifetime.
+        for system in app.get_cache().values_of_type::<ParticleSystem>() {
+            for (index, particle) in system.iter() {
+                let alpha = 1. - system.get_life_fraction(index).unwrap_or(0.);
+                ctx.fill(
+                    kurbo::Circle::new(
+                        Point::new(particle.get_position().x, particle.get_position().y),
+                        *particle.get_radius(),
+                    ),
+                    &Color::WHITE.with_alpha(alpha),
+                )
             }
         }
     }
@@ -0,0 +1,193 @@
+//! Runs a saved graph without opening a window, writing every frame to disk as an SVG. Intended for rendering on
+//! build servers and in tests.
+//!
+//! Usage: `generative_nodes --headless <graph.json> [--frames N] [--fps FPS] [--dt SECONDS] [--speed X]
+//! [--skip STEPS] [--seed SEED] [--out DIR] [--width W] [--height H]`
+
+use std::{fmt::Write, fs, path::PathBuf};
+
+use crate::{
+    core::App,
+    nodes::{
+        self,
+        common::{shapes::Circle, ParticleSystem},
+    },
+};
+
+struct Options {
+    graph: PathBuf,
+    frames: u64,
+    /// Frames written per second of (unscaled) simulated time.
+    fps: f64,
+    /// Simulated seconds per step.
+    dt: f64,
+    /// Playback speed, see [App::set_speed].
+    speed: f64,
+    /// Steps computed before the first frame is written.
+    skip: u64,
+    /// Replaces the seed saved with the graph.
+    seed: Option<u64>,
+    out: PathBuf,
+    width: f64,
+    height: f64,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut graph = None;
+        let mut options = Options {
+            graph: PathBuf::new(),
+            frames: 60,
+            fps: 60.,
+            dt: 1. / 60.,
+            speed: 1.,
+            skip: 0,
+            seed: None,
+            out: PathBuf::from("frames"),
+            width: 800.,
+            height: 600.,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().cloned().ok_or(format!("Missing value for {}", name));
+            match arg.as_str() {
+                "--frames" => options.frames = parse_number(&value(arg)?, arg)?,
+                "--fps" => options.fps = parse_positive(&value(arg)?, arg)?,
+                "--speed" => options.speed = parse_number(&value(arg)?, arg)?,
+                "--seed" => options.seed = Some(parse_number(&value(arg)?, arg)?),
+                "--skip" => options.skip = parse_number(&value(arg)?, arg)?,
+                "--dt" => options.dt = parse_positive(&value(arg)?, arg)?,
+                "--out" => options.out = PathBuf::from(value(arg)?),
+                "--width" => options.width = parse_number(&value(arg)?, arg)?,
+                "--height" => options.height = parse_number(&value(arg)?, arg)?,
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+                _ => graph = Some(PathBuf::from(arg)),
+            }
+        }
+
+        options.graph = graph.ok_or("No graph file given")?;
+        Ok(options)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid value '{}' for {}", value, name))
+}
+
+/// For options which are divided by or stepped with, where zero or a negative value would never finish.
+fn parse_positive(value: &str, name: &str) -> Result<f64, String> {
+    match parse_number::<f64>(value, name)? {
+        number if number > 0. && number.is_finite() => Ok(number),
+        _ => Err(format!("{} must be a positive number, not '{}'", name, value)),
+    }
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args)?;
+
+    let json = fs::read_to_string(&options.graph)
+        .map_err(|error| format!("Couldn't read {}: {}", options.graph.display(), error))?;
+    let mut app = nodes::new_app().with_timestep(options.dt).map_err(|error| error.to_string())?;
+    let loaded = app.load(&json).map_err(|error| error.to_string())?;
+    for warning in &loaded.warnings {
+        eprintln!("{}", warning);
+    }
+
+    fs::create_dir_all(&options.out)
+        .map_err(|error| format!("Couldn't create {}: {}", options.out.display(), error))?;
+
+    if let Some(seed) = options.seed {
+        app.set_seed(seed);
+    }
+    app.set_speed(options.speed);
+    for _ in 0..options.skip {
+        app.step();
+    }
+    for frame in 0..options.frames {
+        app.advance(1. / options.fps);
+        let path = options.out.join(format!("frame_{:05}.svg", frame));
+        fs::write(&path, render_svg(&app, options.width, options.height))
+            .map_err(|error| format!("Couldn't write {}: {}", path.display(), error))?;
+    }
+    Ok(())
+}
+
+/// Draws every [Circle] and [ParticleSystem] in the [Cache], the same way [Viewer2D] does.
+pub fn render_svg(app: &App, width: f64, height: f64) -> String {
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        width, height
+    );
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="black"/>"#);
+    for circle in app.get_cache().values_of_type::<Circle>() {
+        let _ = writeln!(
+            svg,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="white"/>"#,
+            circle.get_position().x,
+            circle.get_position().y,
+            circle.get_radius()
+        );
+    }
+    // Particles fade out over their lifetime.
+    for system in app.get_cache().values_of_type::<ParticleSystem>() {
+        for (index, particle) in system.iter() {
+            let _ = writeln!(
+                svg,
+                r#"<circle cx="{}" cy="{}" r="{}" fill="white" fill-opacity="{}"/>"#,
+                particle.get_position().x,
+                particle.get_position().y,
+                particle.get_radius(),
+                1. - system.get_life_fraction(index).unwrap_or(0.)
+            );
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::{circle_node, emitter_node};
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parses_options() {
+        let options = parse(&["graph.json", "--fps", "30", "--dt", "0.01", "--seed", "4"]).unwrap();
+        assert_eq!(options.graph, PathBuf::from("graph.json"));
+        assert_eq!(options.fps, 30.);
+        assert_eq!(options.dt, 0.01);
+        assert_eq!(options.seed, Some(4));
+    }
+
+    #[test]
+    fn rejects_invalid_rates() {
+        for (option, value) in [("--fps", "0"), ("--fps", "-30"), ("--dt", "0"), ("--dt", "inf")] {
+            let error = parse(&["graph.json", option, value]).err().unwrap();
+            assert_eq!(error, format!("{} must be a positive number, not '{}'", option, value));
+        }
+        assert!(parse(&["graph.json", "--fps", "fast"]).is_err());
+        assert!(parse(&["--fps", "30"]).is_err());
+    }
+
+    #[test]
+    fn renders_circles_and_particles() {
+        let mut app = nodes::new_app();
+        app.add_node(circle_node::NAME);
+        let emitter = app.add_node(emitter_node::NAME);
+        // Enough for 3 particles in the first step.
+        app.set_value(emitter, emitter_node::RATE, 180.);
+        app.compute();
+
+        let svg = render_svg(&app, 100., 50.);
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50""#));
+        assert_eq!(svg.matches("<circle").count(), 4);
+        assert_eq!(svg.matches(r#"fill-opacity="#).count(), 3);
+    }
+}
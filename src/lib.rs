@@ -0,0 +1,9 @@
+//! A node graph for generative art. The engine ([core]) and the node types ([nodes]) don't depend on druid, so they
+//! can be embedded in other tools. The graph editor ([gui]) is only built with the `gui` feature, which is on by
+//! default.
+
+pub mod core;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod headless;
+pub mod nodes;
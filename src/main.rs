@@ -1,26 +1,30 @@
-mod core;
-mod gui;
-mod nodes;
-
 use std::{cell::RefCell, rc::Rc};
 
 use druid::{Point, Rect, Size};
 use druid::{widget::{Split, Scroll, WidgetExt}, AppLauncher, PlatformError, WindowDesc};
-use gui::graph_viewer::GraphViewer;
-use gui::viewer_2d::Viewer2D;
-
-use crate::core::App;
-use crate::gui::delegate::Delegate;
-use crate::gui::graph_widget::Graph;
+use generative_nodes::gui::graph_viewer::GraphViewer;
+use generative_nodes::gui::viewer_2d::Viewer2D;
+use generative_nodes::gui::delegate::Delegate;
+use generative_nodes::gui::graph_widget::Graph;
+use generative_nodes::{headless, nodes};
 
 // TODO: Need to go through all files and check where I use vec[i] vs. vec.get(i) and make sure it all makes sense and stuff.
 
 fn main() -> Result<(), PlatformError> {
-    let app = App::new().with_factories(nodes::node_factories());
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--headless") {
+        if let Err(error) = headless::run(&args[1..]) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let app = nodes::new_app();
 
-    let mut scroll = Scroll::new(Graph::new());
+    let mut scroll = Scroll::new(Graph::new(nodes::node_widget_factories()));
 
-    let main_window = WindowDesc::new(Split::columns( GraphViewer::new(scroll), Viewer2D::new()).draggable(true));
+    let main_window = WindowDesc::new(Split::columns( GraphViewer::new(scroll, nodes::node_categories()), Viewer2D::new()).draggable(true));
 
     AppLauncher::with_window(main_window)
         .delegate(Delegate::new(nodes::node_widget_factories()))
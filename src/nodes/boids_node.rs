@@ -0,0 +1,85 @@
+//! Reynolds' boids. Steers each particle of a [ParticleSystem] by its neighbours and caps its speed. Connect a Particle
+//! Emitter's particles to the input and the flock back to the emitter's "Feedback" input, so that the emitter keeps
+//! simulating the steered particles; the edge closing the loop becomes a feedback edge.
+
+use super::common::{Flocking, ParticleSystem};
+
+define_node! {
+    name: "Boids",
+    inputs: {
+        PARTICLES(particles): ParticleSystem = ParticleSystem::new(0) => "Particles",
+        SEPARATION(separation): f64 = 1.5 => "Separation" [0., 5.],
+        ALIGNMENT(alignment): f64 = 1. => "Alignment" [0., 5.],
+        COHESION(cohesion): f64 = 1. => "Cohesion" [0., 5.],
+        NEIGHBOUR_RADIUS(neighbour_radius): f64 = 25. => "Neighbour Radius" [0., 200.],
+        MAX_SPEED(max_speed): f64 = 100. => "Max Speed" [0., 500.],
+        MAX_FORCE(max_force): f64 = 200. => "Max Force" [0., 1000.],
+    },
+    outputs: {
+        FLOCK(flock): ParticleSystem = ParticleSystem::new(0) => "Flock",
+    },
+    compute(context) {
+        flock = particles;
+        let flocking = Flocking::new(separation, alignment, cohesion, neighbour_radius, max_speed, max_force);
+        flocking.apply(&mut flock, context.dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+
+    use super::*;
+    use crate::{
+        core::{App, NodeId},
+        nodes::{self, emitter_node},
+    };
+
+    /// How well the particles' headings agree, from 0 when they cancel out to 1 when they're all the same.
+    fn order(app: &App, emitter: NodeId) -> f64 {
+        let port = &app.get_node(emitter).unwrap().get_ports()[emitter_node::PARTICLES];
+        let particles = app.get_cache().get::<ParticleSystem>(port.get_cache_index()).unwrap();
+        let headings: Vector2<f64> = particles.iter().map(|(_, particle)| particle.get_velocity().normalize()).sum();
+        headings.norm() / particles.get_count() as f64
+    }
+
+    fn max_speed(app: &App, boids: NodeId) -> f64 {
+        let port = &app.get_node(boids).unwrap().get_ports()[FLOCK];
+        let particles = app.get_cache().get::<ParticleSystem>(port.get_cache_index()).unwrap();
+        particles.iter().map(|(_, particle)| particle.get_velocity().norm()).fold(0., f64::max)
+    }
+
+    /// A burst of particles heading every which way, looped through a Boids node if [flocking] is true.
+    fn swarm(flocking: bool) -> (App, NodeId, NodeId) {
+        let mut app = nodes::new_app();
+        let emitter = app.add_node(emitter_node::NAME);
+        app.set_value(emitter, emitter_node::POSITION_SPREAD, 50.);
+        app.set_value(emitter, emitter_node::VELOCITY_SPREAD, 50.);
+        app.set_value(emitter, emitter_node::RATE, 100. * 60.);
+        app.set_value(emitter, emitter_node::MAX_COUNT, 100.);
+        app.set_value(emitter, emitter_node::LIFETIME, 0.);
+        // Only alignment, with every particle a neighbour of every other, so that they all end up heading the same way.
+        let boids = app.add_node(NAME);
+        app.set_value(boids, SEPARATION, 0.);
+        app.set_value(boids, COHESION, 0.);
+        app.set_value(boids, NEIGHBOUR_RADIUS, 200.);
+        app.add_edge(emitter, emitter_node::PARTICLES, boids, PARTICLES).unwrap();
+        if flocking {
+            app.add_edge(boids, FLOCK, emitter, emitter_node::FEEDBACK).unwrap();
+        }
+        (app, emitter, boids)
+    }
+
+    #[test]
+    fn flock_lines_up() {
+        let (mut free, free_emitter, _) = swarm(false);
+        let (mut flock, flock_emitter, boids) = swarm(true);
+        for _ in 0..60 {
+            free.compute();
+            flock.compute();
+        }
+        assert!(order(&free, free_emitter) < 0.5);
+        assert!(order(&flock, flock_emitter) > 0.9);
+        assert!(max_speed(&flock, boids) <= 100. + 1e-9);
+    }
+}
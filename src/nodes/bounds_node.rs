@@ -0,0 +1,47 @@
+//! Nodes which generate [Bounds](super::common::Bounds) to keep particles in, applied through the "Bounds" input of
+//! particles and emitters. The mode is picked by its index in [BoundsMode::ALL](super::common::BoundsMode::ALL):
+//! 0 wraps, 1 bounces, 2 clamps and 3 kills.
+
+pub mod rectangle {
+    use nalgebra::Vector2;
+
+    use crate::nodes::common::{Bounds, BoundsMode, BoundsShape};
+
+    define_node! {
+        name: "Rectangle Bounds",
+        inputs: {
+            MIN(min): Vector2<f64> = Vector2::new(0., 0.) => "Min",
+            MAX(max): Vector2<f64> = Vector2::new(800., 600.) => "Max",
+            MODE(mode): f64 = 1. => "Mode" [0., 3.],
+            RESTITUTION(restitution): f64 = 0.8 => "Restitution" [0., 1.],
+        },
+        outputs: {
+            BOUNDS(bounds): Bounds = Bounds::unbounded() => "Bounds",
+        },
+        compute(_context) {
+            bounds = Bounds::new(BoundsShape::Rectangle { min, max }, BoundsMode::from_index(mode), restitution);
+        }
+    }
+}
+
+pub mod circle {
+    use nalgebra::Vector2;
+
+    use crate::nodes::common::{Bounds, BoundsMode, BoundsShape};
+
+    define_node! {
+        name: "Circle Bounds",
+        inputs: {
+            CENTRE(centre): Vector2<f64> = Vector2::new(400., 300.) => "Centre",
+            RADIUS(radius): f64 = 250. => "Radius" [0., 1000.],
+            MODE(mode): f64 = 1. => "Mode" [0., 3.],
+            RESTITUTION(restitution): f64 = 0.8 => "Restitution" [0., 1.],
+        },
+        outputs: {
+            BOUNDS(bounds): Bounds = Bounds::unbounded() => "Bounds",
+        },
+        compute(_context) {
+            bounds = Bounds::new(BoundsShape::Circle { centre, radius }, BoundsMode::from_index(mode), restitution);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use nalgebra::Vector2;
+
+use super::Particle;
+
+/// What happens to particles which leave the [Bounds].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BoundsMode {
+    /// Reappear on the opposite side.
+    Wrap,
+    /// Bounce off the edge, keeping [Bounds::get_restitution] of their speed.
+    Bounce,
+    /// Stop at the edge.
+    Clamp,
+    /// Die. Particle nodes, which can't die, are reset instead.
+    Kill,
+}
+
+impl BoundsMode {
+    /// In the order of their index, see [BoundsMode::from_index].
+    pub const ALL: [BoundsMode; 4] = [BoundsMode::Wrap, BoundsMode::Bounce, BoundsMode::Clamp, BoundsMode::Kill];
+
+    /// Picks a mode from a node input, rounding to the nearest index and clamping out of range values.
+    pub fn from_index(index: f64) -> BoundsMode {
+        let index = if index.is_finite() { index.round().max(0.) as usize } else { 0 };
+        BoundsMode::ALL[index.min(BoundsMode::ALL.len() - 1)]
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum BoundsShape {
+    /// Particles can go anywhere.
+    Unbounded,
+    Rectangle { min: Vector2<f64>, max: Vector2<f64> },
+    Circle { centre: Vector2<f64>, radius: f64 },
+}
+
+/// The area particles are kept in. Bouncing and clamping take the particles' radius into account so that they stay
+/// entirely inside, wrapping and killing only look at their centre.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Bounds {
+    shape: BoundsShape,
+    mode: BoundsMode,
+    restitution: f64,
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Bounds::unbounded()
+    }
+}
+
+impl Bounds {
+    pub fn new(shape: BoundsShape, mode: BoundsMode, restitution: f64) -> Self {
+        Bounds {
+            shape,
+            mode,
+            restitution,
+        }
+    }
+
+    /// Bounds which never affect any particle.
+    pub fn unbounded() -> Self {
+        Bounds::new(BoundsShape::Unbounded, BoundsMode::Wrap, 1.)
+    }
+
+    pub fn get_shape(&self) -> &BoundsShape {
+        &self.shape
+    }
+
+    pub fn get_mode(&self) -> BoundsMode {
+        self.mode
+    }
+
+    pub fn get_restitution(&self) -> f64 {
+        self.restitution
+    }
+
+    /// Moves [particle] back inside the bounds if it has left them. Returns false if the particle should be killed
+    /// instead, in which case it isn't changed.
+    pub fn apply(&self, particle: &mut Particle) -> bool {
+        let radius = match self.mode {
+            BoundsMode::Bounce | BoundsMode::Clamp => particle.get_radius().max(0.),
+            BoundsMode::Wrap | BoundsMode::Kill => 0.,
+        };
+        // Where the particle would have to be to be inside, and the outward normal of the edge it crossed.
+        let (inside, normal) = match &self.shape {
+            BoundsShape::Unbounded => return true,
+            BoundsShape::Rectangle { min, max } => {
+                let (min, max) = (min.inf(max), min.sup(max));
+                let position = *particle.get_position();
+                let inside = match self.mode {
+                    BoundsMode::Wrap => min + (position - min).zip_zip_map(&min, &max, |offset, min, max| {
+                        if max > min { offset.rem_euclid(max - min) } else { 0. }
+                    }),
+                    _ => {
+                        // The centre of a particle too big to fit is kept in the middle.
+                        let (inner_min, inner_max) = (min.add_scalar(radius), max.add_scalar(-radius));
+                        position.zip_zip_map(&inner_min, &inner_max, |position, min, max| {
+                            if min > max { (min + max) / 2. } else { position.clamp(min, max) }
+                        })
+                    }
+                };
+                if inside == position {
+                    return true;
+                }
+                // Zero along any axis the particle is still inside on.
+                let normal = (position - inside).map(|offset| {
+                    if offset == 0. { 0. } else { offset.signum() }
+                });
+                (inside, normal)
+            }
+            BoundsShape::Circle { centre, radius: bounds_radius } => {
+                let limit = (bounds_radius - radius).max(0.);
+                let offset = particle.get_position() - centre;
+                let distance = offset.norm();
+                if distance <= limit {
+                    return true;
+                }
+                let normal = offset / distance;
+                let inside = match self.mode {
+                    // Comes back in opposite the point where it left.
+                    BoundsMode::Wrap => centre - normal * (2. * limit - distance).clamp(-limit, limit),
+                    _ => centre + normal * limit,
+                };
+                (inside, normal)
+            }
+        };
+
+        let velocity = *particle.get_velocity();
+        match self.mode {
+            BoundsMode::Kill => return false,
+            BoundsMode::Wrap => (),
+            BoundsMode::Bounce | BoundsMode::Clamp => {
+                let restitution = if self.mode == BoundsMode::Bounce { self.restitution } else { 0. };
+                let mut new_velocity = velocity;
+                match &self.shape {
+                    BoundsShape::Circle { .. } => {
+                        let speed = velocity.dot(&normal);
+                        if speed > 0. {
+                            new_velocity -= normal * speed * (1. + restitution);
+                        }
+                    }
+                    // Only the velocity along the axes the particle left by is affected.
+                    _ => {
+                        for axis in 0..2 {
+                            if velocity[axis] * normal[axis] > 0. {
+                                new_velocity[axis] = -velocity[axis] * restitution;
+                            }
+                        }
+                    }
+                }
+                *particle.get_mut_velocity() = new_velocity;
+            }
+        }
+        *particle.get_mut_position() = inside;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn particle(position: Vector2<f64>, velocity: Vector2<f64>) -> Particle {
+        let mut particle = Particle::new();
+        *particle.get_mut_position() = position;
+        *particle.get_mut_velocity() = velocity;
+        *particle.get_mut_radius() = 1.;
+        particle
+    }
+
+    fn rectangle(mode: BoundsMode) -> Bounds {
+        let shape = BoundsShape::Rectangle {
+            min: Vector2::new(0., 0.),
+            max: Vector2::new(10., 10.),
+        };
+        Bounds::new(shape, mode, 0.5)
+    }
+
+    #[test]
+    fn from_index_rounds_and_clamps() {
+        assert_eq!(BoundsMode::from_index(0.6), BoundsMode::Bounce);
+        assert_eq!(BoundsMode::from_index(-2.), BoundsMode::Wrap);
+        assert_eq!(BoundsMode::from_index(10.), BoundsMode::Kill);
+        assert_eq!(BoundsMode::from_index(f64::NAN), BoundsMode::Wrap);
+    }
+
+    #[test]
+    fn particle_inside_is_unchanged() {
+        let mut inside = particle(Vector2::new(5., 5.), Vector2::new(1., 1.));
+        assert!(rectangle(BoundsMode::Kill).apply(&mut inside));
+        assert_eq!(inside.get_position(), &Vector2::new(5., 5.));
+        assert_eq!(inside.get_velocity(), &Vector2::new(1., 1.));
+    }
+
+    #[test]
+    fn rectangle_modes() {
+        let leaving = || particle(Vector2::new(12., 5.), Vector2::new(2., 1.));
+
+        let mut wrapped = leaving();
+        assert!(rectangle(BoundsMode::Wrap).apply(&mut wrapped));
+        assert_eq!(wrapped.get_position(), &Vector2::new(2., 5.));
+        assert_eq!(wrapped.get_velocity(), &Vector2::new(2., 1.));
+
+        // Bouncing and clamping keep the whole particle inside, and only affect the velocity across the edge.
+        let mut bounced = leaving();
+        assert!(rectangle(BoundsMode::Bounce).apply(&mut bounced));
+        assert_eq!(bounced.get_position(), &Vector2::new(9., 5.));
+        assert_eq!(bounced.get_velocity(), &Vector2::new(-1., 1.));
+
+        let mut clamped = leaving();
+        assert!(rectangle(BoundsMode::Clamp).apply(&mut clamped));
+        assert_eq!(clamped.get_position(), &Vector2::new(9., 5.));
+        assert_eq!(clamped.get_velocity().x, 0.);
+
+        let mut killed = leaving();
+        assert!(!rectangle(BoundsMode::Kill).apply(&mut killed));
+        assert_eq!(killed.get_position(), &Vector2::new(12., 5.));
+    }
+
+    #[test]
+    fn circle_bounce_reflects_velocity() {
+        let shape = BoundsShape::Circle {
+            centre: Vector2::new(0., 0.),
+            radius: 10.,
+        };
+        let bounds = Bounds::new(shape, BoundsMode::Bounce, 1.);
+        let mut bounced = particle(Vector2::new(0., 12.), Vector2::new(1., 3.));
+        assert!(bounds.apply(&mut bounced));
+        assert_eq!(bounced.get_position(), &Vector2::new(0., 9.));
+        assert_eq!(bounced.get_velocity(), &Vector2::new(1., -3.));
+    }
+
+    #[test]
+    fn unbounded_never_changes_particles() {
+        let mut far = particle(Vector2::new(1e9, -1e9), Vector2::new(1., 1.));
+        assert!(Bounds::unbounded().apply(&mut far));
+        assert_eq!(far.get_position(), &Vector2::new(1e9, -1e9));
+    }
+}
@@ -0,0 +1,712 @@
+//! A small formula language for the Expression node, e.g. `sin(t * 3) * r + 10` or `P + vec(cos(a), sin(a)) * 5`.
+//!
+//! Values are either numbers or [Vector2]s. Free variables starting with an uppercase letter are vectors, all other
+//! variables are numbers. Vectors support `+`, `-`, scaling by a number and `.x`/`.y`. See [FUNCTIONS] for the
+//! built-in functions; `pi` and `tau` are constants. Types are checked while parsing, so a parsed [Expression] can
+//! always be evaluated. Names can be reserved so that no variable uses them, e.g. the names of a node's other ports.
+
+use std::{error::Error, f64::consts, fmt};
+
+use nalgebra::Vector2;
+
+use ValueType::{Number as N, Vector as V};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ValueType {
+    Number,
+    Vector,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Value {
+    Number(f64),
+    Vector(Vector2<f64>),
+}
+
+impl Value {
+    /// Only used on values whose type has already been checked, so any mismatch falls back to 0.
+    fn number(&self) -> f64 {
+        match self {
+            Value::Number(number) => *number,
+            Value::Vector(_) => 0.,
+        }
+    }
+
+    fn vector(&self) -> Vector2<f64> {
+        match self {
+            Value::Number(_) => Vector2::zeros(),
+            Value::Vector(vector) => *vector,
+        }
+    }
+
+    /// Replaces infinity and NaN with 0, the same as the math nodes do.
+    fn finite_or_zero(self) -> Value {
+        let finite = |value: f64| if value.is_finite() { value } else { 0. };
+        match self {
+            Value::Number(number) => Value::Number(finite(number)),
+            Value::Vector(vector) => Value::Vector(vector.map(finite)),
+        }
+    }
+}
+
+/// Returned by [Expression::parse]. The [fmt::Display] implementation is intended to be shown to the user.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    /// The character the error was found at.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at character {})", self.message, self.position + 1)
+    }
+}
+
+impl Error for ParseError {}
+
+pub struct Function {
+    pub name: &'static str,
+    arguments: &'static [ValueType],
+    result: ValueType,
+    function: fn(&[Value]) -> Value,
+}
+
+/// Every built-in function. A name may appear more than once with different argument types.
+pub const FUNCTIONS: &[Function] = &[
+    Function { name: "sin", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().sin()) },
+    Function { name: "cos", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().cos()) },
+    Function { name: "tan", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().tan()) },
+    Function { name: "asin", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().asin()) },
+    Function { name: "acos", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().acos()) },
+    Function { name: "atan", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().atan()) },
+    Function {
+        name: "atan2",
+        arguments: &[N, N],
+        result: N,
+        function: |a| Value::Number(a[0].number().atan2(a[1].number())),
+    },
+    Function { name: "sqrt", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().sqrt()) },
+    Function { name: "exp", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().exp()) },
+    Function { name: "ln", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().ln()) },
+    Function { name: "abs", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().abs()) },
+    Function { name: "floor", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().floor()) },
+    Function { name: "ceil", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().ceil()) },
+    Function { name: "round", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().round()) },
+    Function { name: "fract", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().fract()) },
+    Function { name: "sign", arguments: &[N], result: N, function: |a| Value::Number(a[0].number().signum()) },
+    Function {
+        name: "min",
+        arguments: &[N, N],
+        result: N,
+        function: |a| Value::Number(a[0].number().min(a[1].number())),
+    },
+    Function {
+        name: "max",
+        arguments: &[N, N],
+        result: N,
+        function: |a| Value::Number(a[0].number().max(a[1].number())),
+    },
+    Function {
+        name: "clamp",
+        arguments: &[N, N, N],
+        result: N,
+        function: |a| Value::Number(a[0].number().max(a[1].number()).min(a[2].number())),
+    },
+    Function {
+        name: "lerp",
+        arguments: &[N, N, N],
+        result: N,
+        function: |a| Value::Number(a[0].number() + (a[1].number() - a[0].number()) * a[2].number()),
+    },
+    Function {
+        name: "lerp",
+        arguments: &[V, V, N],
+        result: V,
+        function: |a| Value::Vector(a[0].vector() + (a[1].vector() - a[0].vector()) * a[2].number()),
+    },
+    Function {
+        name: "vec",
+        arguments: &[N, N],
+        result: V,
+        function: |a| Value::Vector(Vector2::new(a[0].number(), a[1].number())),
+    },
+    Function {
+        name: "polar",
+        arguments: &[N, N],
+        result: V,
+        function: |a| Value::Vector(Vector2::new(a[0].number().cos(), a[0].number().sin()) * a[1].number()),
+    },
+    Function { name: "length", arguments: &[V], result: N, function: |a| Value::Number(a[0].vector().norm()) },
+    Function {
+        name: "normalize",
+        arguments: &[V],
+        result: V,
+        function: |a| Value::Vector(a[0].vector().try_normalize(0.).unwrap_or_else(Vector2::zeros)),
+    },
+    Function {
+        name: "distance",
+        arguments: &[V, V],
+        result: N,
+        function: |a| Value::Number((a[1].vector() - a[0].vector()).norm()),
+    },
+    Function {
+        name: "dot",
+        arguments: &[V, V],
+        result: N,
+        function: |a| Value::Number(a[0].vector().dot(&a[1].vector())),
+    },
+    Function {
+        name: "cross",
+        arguments: &[V, V],
+        result: N,
+        function: |a| Value::Number(a[0].vector().perp(&a[1].vector())),
+    },
+    Function {
+        name: "rotate",
+        arguments: &[V, N],
+        result: V,
+        function: |a| {
+            let (vector, (sin, cos)) = (a[0].vector(), a[1].number().sin_cos());
+            Value::Vector(Vector2::new(vector.x * cos - vector.y * sin, vector.x * sin + vector.y * cos))
+        },
+    },
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Power,
+}
+
+impl Operator {
+    fn from_char(character: char) -> Option<Operator> {
+        match character {
+            '+' => Some(Operator::Add),
+            '-' => Some(Operator::Subtract),
+            '*' => Some(Operator::Multiply),
+            '/' => Some(Operator::Divide),
+            '%' => Some(Operator::Remainder),
+            '^' => Some(Operator::Power),
+            _ => None,
+        }
+    }
+
+    fn result_type(&self, left: ValueType, right: ValueType) -> Result<ValueType, &'static str> {
+        match (self, left, right) {
+            (_, N, N) => Ok(N),
+            (Operator::Add, V, V) | (Operator::Subtract, V, V) => Ok(V),
+            (Operator::Multiply, V, N) | (Operator::Multiply, N, V) | (Operator::Divide, V, N) => Ok(V),
+            (Operator::Multiply, V, V) => Err("Can't multiply two vectors, use dot() or cross() instead"),
+            (Operator::Add, _, _) => Err("Can't add a number and a vector"),
+            (Operator::Subtract, _, _) => Err("Can't subtract a number and a vector"),
+            (Operator::Divide, _, _) => Err("Can only divide a vector by a number"),
+            (Operator::Remainder, _, _) | (Operator::Power, _, _) => Err("Only works on numbers"),
+        }
+    }
+
+    fn apply(&self, left: Value, right: Value) -> Value {
+        match (self, left, right) {
+            (Operator::Add, Value::Vector(a), Value::Vector(b)) => Value::Vector(a + b),
+            (Operator::Subtract, Value::Vector(a), Value::Vector(b)) => Value::Vector(a - b),
+            (Operator::Multiply, Value::Vector(a), Value::Number(b))
+            | (Operator::Multiply, Value::Number(b), Value::Vector(a)) => Value::Vector(a * b),
+            (Operator::Divide, Value::Vector(a), Value::Number(b)) => Value::Vector(a / b),
+            (operator, a, b) => {
+                let (a, b) = (a.number(), b.number());
+                Value::Number(match operator {
+                    Operator::Add => a + b,
+                    Operator::Subtract => a - b,
+                    Operator::Multiply => a * b,
+                    Operator::Divide => a / b,
+                    Operator::Remainder => a.rem_euclid(b),
+                    Operator::Power => a.powf(b),
+                })
+            }
+        }
+    }
+}
+
+enum Ast {
+    Constant(Value),
+    /// Index into [Expression::variables].
+    Variable(usize),
+    Negate(Box<Ast>),
+    Binary(Operator, Box<Ast>, Box<Ast>),
+    Call(fn(&[Value]) -> Value, Vec<Ast>),
+    X(Box<Ast>),
+    Y(Box<Ast>),
+}
+
+impl Ast {
+    fn evaluate(&self, variables: &[Value]) -> Value {
+        match self {
+            Ast::Constant(value) => *value,
+            Ast::Variable(index) => variables.get(*index).copied().unwrap_or(Value::Number(0.)),
+            Ast::Negate(inner) => match inner.evaluate(variables) {
+                Value::Number(number) => Value::Number(-number),
+                Value::Vector(vector) => Value::Vector(-vector),
+            },
+            Ast::Binary(operator, left, right) => operator.apply(left.evaluate(variables), right.evaluate(variables)),
+            Ast::Call(function, arguments) => {
+                let arguments: Vec<Value> = arguments.iter().map(|argument| argument.evaluate(variables)).collect();
+                function(&arguments)
+            }
+            Ast::X(inner) => Value::Number(inner.evaluate(variables).vector().x),
+            Ast::Y(inner) => Value::Number(inner.evaluate(variables).vector().y),
+        }
+    }
+}
+
+pub struct Expression {
+    root: Ast,
+    result_type: ValueType,
+    /// In order of first appearance in the formula.
+    variables: Vec<(String, ValueType)>,
+}
+
+impl Expression {
+    /// Fails if any variable is named one of [reserved].
+    pub fn parse(formula: &str, reserved: &[&str]) -> Result<Expression, ParseError> {
+        let mut parser = Parser {
+            tokens: match tokenize(formula) {
+                (tokens, None) => tokens,
+                (_, Some(error)) => return Err(error),
+            },
+            next: 0,
+            variables: Vec::new(),
+            reserved,
+        };
+        let (root, result_type) = parser.parse_sum()?;
+        match parser.peek() {
+            (Token::End, _) => Ok(Expression {
+                root,
+                result_type,
+                variables: parser.variables,
+            }),
+            (token, position) => Err(unexpected(token, *position)),
+        }
+    }
+
+    pub fn get_result_type(&self) -> ValueType {
+        self.result_type
+    }
+
+    pub fn get_variables(&self) -> &Vec<(String, ValueType)> {
+        &self.variables
+    }
+
+    /// [variables] must be in the same order as [Expression::get_variables]. Infinite and NaN results are replaced
+    /// with 0.
+    pub fn evaluate(&self, variables: &[Value]) -> Value {
+        self.root.evaluate(variables).finite_or_zero()
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Symbol(char),
+    End,
+}
+
+/// Also returns the first error found, if any. Invalid characters are skipped so that [find_variables] can still
+/// work on the rest of the formula.
+fn tokenize(formula: &str) -> (Vec<(Token, usize)>, Option<ParseError>) {
+    let characters: Vec<char> = formula.chars().collect();
+    let mut tokens = Vec::new();
+    let mut error = None;
+    let mut index = 0;
+
+    while index < characters.len() {
+        let character = characters[index];
+        let start = index;
+        if character.is_whitespace() {
+            index += 1;
+        } else if character.is_ascii_digit()
+            || (character == '.' && characters.get(index + 1).is_some_and(char::is_ascii_digit))
+        {
+            while index < characters.len() && (characters[index].is_ascii_digit() || characters[index] == '.') {
+                index += 1;
+            }
+            let text: String = characters[start..index].iter().collect();
+            match text.parse() {
+                Ok(number) => tokens.push((Token::Number(number), start)),
+                Err(_) => {
+                    error = error.or(Some(ParseError {
+                        position: start,
+                        message: format!("Invalid number '{}'", text),
+                    }))
+                }
+            }
+        } else if character.is_alphabetic() || character == '_' {
+            while index < characters.len() && (characters[index].is_alphanumeric() || characters[index] == '_') {
+                index += 1;
+            }
+            tokens.push((Token::Identifier(characters[start..index].iter().collect()), start));
+        } else if "+-*/%^(),.".contains(character) {
+            tokens.push((Token::Symbol(character), start));
+            index += 1;
+        } else {
+            error = error.or(Some(ParseError {
+                position: start,
+                message: format!("Unexpected '{}'", character),
+            }));
+            index += 1;
+        }
+    }
+
+    tokens.push((Token::End, characters.len()));
+    (tokens, error)
+}
+
+/// The free variables of [formula] in order of first appearance, the same as [Expression::get_variables] would
+/// return. Also works on formulas which can't be parsed, so that a node's inputs don't depend on whether its formula
+/// is currently valid. Names in [reserved] are left out.
+pub fn find_variables(formula: &str, reserved: &[&str]) -> Vec<(String, ValueType)> {
+    let (tokens, _) = tokenize(formula);
+    let mut variables: Vec<(String, ValueType)> = Vec::new();
+    for (index, (token, _)) in tokens.iter().enumerate() {
+        if let Token::Identifier(name) = token {
+            let is_call = matches!(tokens.get(index + 1), Some((Token::Symbol('('), _)));
+            let is_component = index > 0 && tokens[index - 1].0 == Token::Symbol('.');
+            let is_constant = name == "pi" || name == "tau";
+            let is_reserved = reserved.contains(&name.as_str());
+            if !is_call
+                && !is_component
+                && !is_constant
+                && !is_reserved
+                && !variables.iter().any(|(other, _)| other == name)
+            {
+                variables.push((name.clone(), variable_type(name)));
+            }
+        }
+    }
+    variables
+}
+
+fn variable_type(name: &str) -> ValueType {
+    if name.starts_with(char::is_uppercase) {
+        V
+    } else {
+        N
+    }
+}
+
+fn unexpected(token: &Token, position: usize) -> ParseError {
+    let message = match token {
+        Token::Number(number) => format!("Unexpected number {}", number),
+        Token::Identifier(name) => format!("Unexpected '{}'", name),
+        Token::Symbol(symbol) => format!("Unexpected '{}'", symbol),
+        Token::End => "Unexpected end of formula".to_string(),
+    };
+    ParseError { position, message }
+}
+
+/// Recursive descent parser, from lowest to highest precedence: `+ -`, `* / %`, unary `-`, `^`, then numbers,
+/// variables, calls, brackets and `.x`/`.y`.
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+    variables: Vec<(String, ValueType)>,
+    reserved: &'a [&'a str],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &(Token, usize) {
+        &self.tokens[self.next]
+    }
+
+    fn advance(&mut self) -> (Token, usize) {
+        let token = self.tokens[self.next].clone();
+        if token.0 != Token::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), ParseError> {
+        match self.advance() {
+            (Token::Symbol(found), _) if found == symbol => Ok(()),
+            (token, position) => Err(ParseError {
+                message: format!("Expected '{}', {}", symbol, unexpected(&token, position).message.to_lowercase()),
+                position,
+            }),
+        }
+    }
+
+    /// Parses the operators in [symbols] left-associatively, with operands parsed by [operand].
+    fn parse_binary(
+        &mut self,
+        symbols: &str,
+        operand: fn(&mut Self) -> Result<(Ast, ValueType), ParseError>,
+    ) -> Result<(Ast, ValueType), ParseError> {
+        let (mut left, mut left_type) = operand(self)?;
+        while let (Token::Symbol(symbol), position) = self.peek().clone() {
+            if !symbols.contains(symbol) {
+                break;
+            }
+            self.advance();
+            let operator = Operator::from_char(symbol).unwrap();
+            let (right, right_type) = operand(self)?;
+            left_type = operator.result_type(left_type, right_type).map_err(|message| ParseError {
+                position,
+                message: message.to_string(),
+            })?;
+            left = Ast::Binary(operator, Box::new(left), Box::new(right));
+        }
+        Ok((left, left_type))
+    }
+
+    fn parse_sum(&mut self) -> Result<(Ast, ValueType), ParseError> {
+        self.parse_binary("+-", Self::parse_product)
+    }
+
+    fn parse_product(&mut self) -> Result<(Ast, ValueType), ParseError> {
+        self.parse_binary("*/%", Self::parse_negation)
+    }
+
+    fn parse_negation(&mut self) -> Result<(Ast, ValueType), ParseError> {
+        if let (Token::Symbol('-'), _) = self.peek() {
+            self.advance();
+            let (inner, inner_type) = self.parse_negation()?;
+            return Ok((Ast::Negate(Box::new(inner)), inner_type));
+        }
+        self.parse_power()
+    }
+
+    /// Right-associative, and binds tighter than unary minus on its left, so `-2^2` is -4 and `2^-1` is 0.5.
+    fn parse_power(&mut self) -> Result<(Ast, ValueType), ParseError> {
+        let (base, base_type) = self.parse_postfix()?;
+        if let (Token::Symbol('^'), position) = self.peek().clone() {
+            self.advance();
+            let (exponent, exponent_type) = self.parse_negation()?;
+            let result_type = Operator::Power
+                .result_type(base_type, exponent_type)
+                .map_err(|message| ParseError {
+                    position,
+                    message: message.to_string(),
+                })?;
+            return Ok((Ast::Binary(Operator::Power, Box::new(base), Box::new(exponent)), result_type));
+        }
+        Ok((base, base_type))
+    }
+
+    fn parse_postfix(&mut self) -> Result<(Ast, ValueType), ParseError> {
+        let (mut ast, mut ast_type) = self.parse_atom()?;
+        while let (Token::Symbol('.'), position) = self.peek().clone() {
+            self.advance();
+            let component = match self.advance() {
+                (Token::Identifier(name), _) if name == "x" || name == "y" => name,
+                (token, position) => {
+                    return Err(ParseError {
+                        message: format!("Expected x or y, {}", unexpected(&token, position).message.to_lowercase()),
+                        position,
+                    })
+                }
+            };
+            if ast_type != V {
+                return Err(ParseError {
+                    position,
+                    message: format!("Can't take .{} of a number", component),
+                });
+            }
+            ast = if component == "x" { Ast::X(Box::new(ast)) } else { Ast::Y(Box::new(ast)) };
+            ast_type = N;
+        }
+        Ok((ast, ast_type))
+    }
+
+    fn parse_atom(&mut self) -> Result<(Ast, ValueType), ParseError> {
+        match self.advance() {
+            (Token::Number(number), _) => Ok((Ast::Constant(Value::Number(number)), N)),
+            (Token::Symbol('('), _) => {
+                let inner = self.parse_sum()?;
+                self.expect(')')?;
+                Ok(inner)
+            }
+            (Token::Identifier(name), position) => {
+                if let (Token::Symbol('('), _) = self.peek() {
+                    self.advance();
+                    return self.parse_call(&name, position);
+                }
+                Ok(match name.as_str() {
+                    "pi" => (Ast::Constant(Value::Number(consts::PI)), N),
+                    "tau" => (Ast::Constant(Value::Number(consts::TAU)), N),
+                    _ => self.variable(name, position)?,
+                })
+            }
+            (token, position) => Err(unexpected(&token, position)),
+        }
+    }
+
+    /// Called after the opening bracket.
+    fn parse_call(&mut self, name: &str, position: usize) -> Result<(Ast, ValueType), ParseError> {
+        let mut arguments = Vec::new();
+        let mut argument_types = Vec::new();
+        if let (Token::Symbol(')'), _) = self.peek() {
+            self.advance();
+        } else {
+            loop {
+                let (argument, argument_type) = self.parse_sum()?;
+                arguments.push(argument);
+                argument_types.push(argument_type);
+                match self.advance() {
+                    (Token::Symbol(','), _) => (),
+                    (Token::Symbol(')'), _) => break,
+                    (token, position) => {
+                        return Err(ParseError {
+                            message: format!("Expected ',' or ')', {}", unexpected(&token, position).message.to_lowercase()),
+                            position,
+                        })
+                    }
+                }
+            }
+        }
+
+        let overloads: Vec<&Function> = FUNCTIONS.iter().filter(|function| function.name == name).collect();
+        if overloads.is_empty() {
+            return Err(ParseError {
+                position,
+                message: format!("Unknown function '{}'", name),
+            });
+        }
+        match overloads.iter().find(|function| function.arguments == argument_types.as_slice()) {
+            Some(function) => Ok((Ast::Call(function.function, arguments), function.result)),
+            None => Err(ParseError {
+                position,
+                message: format!(
+                    "'{}' takes ({})",
+                    name,
+                    overloads.iter().map(|function| describe(function.arguments)).collect::<Vec<_>>().join(") or (")
+                ),
+            }),
+        }
+    }
+
+    fn variable(&mut self, name: String, position: usize) -> Result<(Ast, ValueType), ParseError> {
+        if self.reserved.contains(&name.as_str()) {
+            return Err(ParseError {
+                position,
+                message: format!("'{}' can't be used as a variable name", name),
+            });
+        }
+        let index = match self.variables.iter().position(|(other, _)| *other == name) {
+            Some(index) => index,
+            None => {
+                let value_type = variable_type(&name);
+                self.variables.push((name, value_type));
+                self.variables.len() - 1
+            }
+        };
+        Ok((Ast::Variable(index), self.variables[index].1))
+    }
+}
+
+fn describe(arguments: &[ValueType]) -> String {
+    arguments
+        .iter()
+        .map(|argument| match argument {
+            N => "number",
+            V => "vector",
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(formula: &str, variables: &[Value]) -> Value {
+        Expression::parse(formula, &[]).unwrap().evaluate(variables)
+    }
+
+    fn error(formula: &str) -> ParseError {
+        Expression::parse(formula, &["Result"]).err().unwrap()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(evaluate("1 + 2 * 3", &[]), Value::Number(7.));
+        assert_eq!(evaluate("(1 + 2) * 3", &[]), Value::Number(9.));
+        assert_eq!(evaluate("10 - 4 - 3", &[]), Value::Number(3.));
+        assert_eq!(evaluate("2 ^ 3 ^ 2", &[]), Value::Number(512.));
+        assert_eq!(evaluate("7 % 4 * 2", &[]), Value::Number(6.));
+        assert_eq!(evaluate("-7 % 4", &[]), Value::Number(1.));
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(evaluate("-2 ^ 2", &[]), Value::Number(-4.));
+        assert_eq!(evaluate("2 ^ -1", &[]), Value::Number(0.5));
+        assert_eq!(evaluate("--3", &[]), Value::Number(3.));
+        assert_eq!(evaluate("2 * -3", &[]), Value::Number(-6.));
+        assert_eq!(evaluate("-P", &[Value::Vector(Vector2::new(1., -2.))]), Value::Vector(Vector2::new(-1., 2.)));
+    }
+
+    #[test]
+    fn functions_and_vectors() {
+        assert_eq!(evaluate("max(1, min(5, 3))", &[]), Value::Number(3.));
+        assert_eq!(evaluate("lerp(vec(0, 0), vec(4, 8), 0.25)", &[]), Value::Vector(Vector2::new(1., 2.)));
+        assert_eq!(evaluate("length(vec(3, 4))", &[]), Value::Number(5.));
+        assert_eq!(evaluate("(vec(1, 2) * 3).y", &[]), Value::Number(6.));
+        assert_eq!(evaluate("cos(pi)", &[]), Value::Number(-1.));
+        // Infinite results are replaced with 0.
+        assert_eq!(evaluate("1 / 0", &[]), Value::Number(0.));
+    }
+
+    #[test]
+    fn variables_in_order_of_appearance() {
+        let expression = Expression::parse("b * sin(a) + b + P.x", &[]).unwrap();
+        assert_eq!(
+            expression.get_variables(),
+            &vec![(String::from("b"), N), (String::from("a"), N), (String::from("P"), V)]
+        );
+        assert_eq!(expression.get_result_type(), N);
+        let variables = [Value::Number(2.), Value::Number(0.), Value::Vector(Vector2::new(5., 0.))];
+        assert_eq!(expression.evaluate(&variables), Value::Number(7.));
+    }
+
+    #[test]
+    fn find_variables_works_without_parsing() {
+        assert_eq!(
+            find_variables("x + (y * vec(Q, pi) +", &["Result"]),
+            vec![(String::from("x"), N), (String::from("y"), N), (String::from("Q"), V)]
+        );
+        assert_eq!(find_variables("Result * a", &["Result"]), vec![(String::from("a"), N)]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            error("1 +"),
+            ParseError {
+                position: 3,
+                message: String::from("Unexpected end of formula"),
+            }
+        );
+        assert_eq!(error("2 $ 3").message, "Unexpected '$'");
+        assert_eq!(error("(1 + 2").message, "Expected ')', unexpected end of formula");
+        assert_eq!(error("foo(1)").message, "Unknown function 'foo'");
+        assert_eq!(error("lerp(1, 2)").message, "'lerp' takes (number, number, number) or (vector, vector, number)");
+        assert_eq!(error("P + 1").message, "Can't add a number and a vector");
+        assert_eq!(error("P * Q").message, "Can't multiply two vectors, use dot() or cross() instead");
+        assert_eq!(error("a.x").message, "Can't take .x of a number");
+        assert_eq!(error("1.2.3").message, "Invalid number '1.2.3'");
+    }
+
+    #[test]
+    fn reserved_names_are_rejected() {
+        let error = error("a + Result");
+        assert_eq!(error.position, 4);
+        assert_eq!(error.to_string(), "'Result' can't be used as a variable name (at character 5)");
+        // Only whole names are reserved.
+        assert!(Expression::parse("Results.x + result", &["Result"]).is_ok());
+    }
+}
@@ -0,0 +1,155 @@
+use nalgebra::Vector2;
+
+use super::{limit_length, ParticleSystem, SpatialHash};
+
+/// Settings for Reynolds' boids, steering each particle of a [ParticleSystem] by its neighbours.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Flocking {
+    /// Steers away from neighbours, more strongly the closer they are.
+    separation: f64,
+    /// Steers towards the neighbours' average heading.
+    alignment: f64,
+    /// Steers towards the neighbours' average position.
+    cohesion: f64,
+    /// Only particles closer than this are neighbours.
+    neighbour_radius: f64,
+    /// The speed every boid is steered towards, and which it can't go faster than.
+    max_speed: f64,
+    /// The greatest acceleration each of the three rules can cause.
+    max_force: f64,
+}
+
+impl Flocking {
+    /// The weights scale the acceleration from each rule.
+    pub fn new(
+        separation: f64,
+        alignment: f64,
+        cohesion: f64,
+        neighbour_radius: f64,
+        max_speed: f64,
+        max_force: f64,
+    ) -> Self {
+        Flocking {
+            separation,
+            alignment,
+            cohesion,
+            neighbour_radius,
+            max_speed,
+            max_force,
+        }
+    }
+
+    /// Returns false if these settings can't steer any particle.
+    pub fn is_active(&self) -> bool {
+        self.neighbour_radius > 0. && self.max_force > 0. && self.max_speed > 0. && self.max_speed.is_finite()
+    }
+
+    /// The acceleration which turns [velocity] into [direction] at full speed, limited to the max force.
+    fn steer(&self, direction: Vector2<f64>, velocity: &Vector2<f64>) -> Vector2<f64> {
+        let length = direction.norm();
+        if length == 0. {
+            return Vector2::new(0., 0.);
+        }
+        limit_length(direction * (self.max_speed / length) - velocity, self.max_force)
+    }
+
+    /// Steers every particle for [dt] seconds, based on where its neighbours are now, then slows down any particle
+    /// going faster than the max speed. Neighbours are found with a [SpatialHash], so that each particle only has to
+    /// look at those nearby.
+    pub fn apply(&self, particles: &mut ParticleSystem, dt: f64) {
+        if !self.is_active() {
+            return;
+        }
+        let mut spatial_hash = SpatialHash::new(self.neighbour_radius);
+        for (index, particle) in particles.iter() {
+            spatial_hash.insert(index, particle.get_position());
+        }
+
+        // Worked out for every particle before any are changed, so that the order doesn't matter.
+        let mut accelerations = Vec::with_capacity(particles.get_count());
+        for (index, particle) in particles.iter() {
+            let (position, velocity) = (particle.get_position(), particle.get_velocity());
+            let mut neighbours = 0;
+            let mut away = Vector2::new(0., 0.);
+            let mut total_velocity = Vector2::new(0., 0.);
+            let mut total_position = Vector2::new(0., 0.);
+            for other in spatial_hash.query(position, self.neighbour_radius) {
+                let other_particle = match particles.get(other) {
+                    Some(other_particle) if other != index => other_particle,
+                    _ => continue,
+                };
+                let offset = position - other_particle.get_position();
+                let distance = offset.norm();
+                if distance >= self.neighbour_radius {
+                    continue;
+                }
+                neighbours += 1;
+                if distance > 0. {
+                    // The direction away, weighted by 1 / distance.
+                    away += offset / (distance * distance);
+                }
+                total_velocity += other_particle.get_velocity();
+                total_position += other_particle.get_position();
+            }
+            if neighbours == 0 {
+                continue;
+            }
+
+            let count = neighbours as f64;
+            let acceleration = self.steer(away, velocity) * self.separation
+                + self.steer(total_velocity / count, velocity) * self.alignment
+                + self.steer(total_position / count - position, velocity) * self.cohesion;
+            accelerations.push((index, acceleration));
+        }
+
+        for (index, acceleration) in accelerations {
+            if let Some(particle) = particles.get_mut(index) {
+                *particle.get_mut_velocity() += acceleration * dt;
+            }
+        }
+        for (_, particle) in particles.iter_mut() {
+            let velocity = particle.get_mut_velocity();
+            *velocity = limit_length(*velocity, self.max_speed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::common::Particle;
+
+    fn spawn(particles: &mut ParticleSystem, position: Vector2<f64>, velocity: Vector2<f64>) -> usize {
+        let mut particle = Particle::new();
+        *particle.get_mut_position() = position;
+        *particle.get_mut_velocity() = velocity;
+        particles.spawn(particle, f64::INFINITY).unwrap()
+    }
+
+    #[test]
+    fn neighbours_steer_and_speed_is_limited() {
+        let mut particles = ParticleSystem::new(3);
+        let a = spawn(&mut particles, Vector2::new(0., 0.), Vector2::new(10., 0.));
+        let b = spawn(&mut particles, Vector2::new(0., 5.), Vector2::new(0., 10.));
+        // Too far away to be anyone's neighbour, but too fast.
+        let c = spawn(&mut particles, Vector2::new(1000., 0.), Vector2::new(300., 0.));
+
+        Flocking::new(0., 1., 0., 25., 100., 200.).apply(&mut particles, 0.1);
+        let velocity = |index| *particles.get(index).unwrap().get_velocity();
+        // Alignment turns each towards the other's heading.
+        assert!(velocity(a).y > 0.);
+        assert!(velocity(b).x > 0.);
+        assert_eq!(velocity(c), Vector2::new(100., 0.));
+    }
+
+    #[test]
+    fn separation_pushes_neighbours_apart() {
+        let mut particles = ParticleSystem::new(2);
+        let a = spawn(&mut particles, Vector2::new(0., 0.), Vector2::new(0., 0.));
+        let b = spawn(&mut particles, Vector2::new(5., 0.), Vector2::new(0., 0.));
+
+        Flocking::new(1., 0., 0., 25., 100., 200.).apply(&mut particles, 0.1);
+        assert!(particles.get(a).unwrap().get_velocity().x < 0.);
+        assert!(particles.get(b).unwrap().get_velocity().x > 0.);
+    }
+}
@@ -0,0 +1,364 @@
+use nalgebra::Vector2;
+
+use super::{
+    noise::{self, NoiseType},
+    Particle,
+};
+
+/// A single force generator, evaluated for a particle's position and velocity.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Force {
+    /// The same force everywhere.
+    Constant(Vector2<f64>),
+    /// The same acceleration everywhere, regardless of mass.
+    Gravity(Vector2<f64>),
+    /// Opposes the velocity, proportionally to the speed and to the speed squared.
+    Drag { linear: f64, quadratic: f64 },
+    /// Accelerates particles towards [position], or away from it with a negative [strength]. The acceleration is
+    /// [strength] within [radius] and falls off with the distance to the power of [falloff] beyond it, e.g. 2 for an
+    /// inverse square law.
+    Attractor {
+        position: Vector2<f64>,
+        strength: f64,
+        radius: f64,
+        falloff: f64,
+    },
+    /// Accelerates particles around [position], clockwise on screen for a positive [strength]. Falls off like
+    /// [Force::Attractor].
+    Vortex {
+        position: Vector2<f64>,
+        strength: f64,
+        radius: f64,
+        falloff: f64,
+    },
+    /// A constant [direction] plus turbulence from 3D simplex noise, with time as the third dimension so that it
+    /// drifts. [scale] is the noise's frequency in space.
+    Wind {
+        direction: Vector2<f64>,
+        turbulence: f64,
+        scale: f64,
+        time: f64,
+        seed: u64,
+    },
+    /// Pulls particles towards, or pushes them away from, [anchor] until they're [rest_length] away from it, with a
+    /// force proportional to the [stiffness]. [damping] resists the speed at which the distance changes, relative to
+    /// [anchor_velocity].
+    Spring {
+        anchor: Vector2<f64>,
+        anchor_velocity: Vector2<f64>,
+        rest_length: f64,
+        stiffness: f64,
+        damping: f64,
+    },
+}
+
+/// How much of [strength] is left at [distance], see [Force::Attractor].
+fn falloff_strength(strength: f64, distance: f64, radius: f64, falloff: f64) -> f64 {
+    if distance <= radius {
+        strength
+    } else {
+        strength * (radius.max(0.) / distance).powf(falloff)
+    }
+}
+
+impl Force {
+    pub fn force_at(&self, position: &Vector2<f64>, velocity: &Vector2<f64>, mass: f64) -> Vector2<f64> {
+        match self {
+            Force::Constant(force) => *force,
+            Force::Gravity(acceleration) => acceleration * mass,
+            Force::Drag { linear, quadratic } => -velocity * (linear + quadratic * velocity.norm()),
+            Force::Attractor {
+                position: centre,
+                strength,
+                radius,
+                falloff,
+            } => {
+                let offset = centre - position;
+                let distance = offset.norm();
+                if distance == 0. {
+                    return Vector2::new(0., 0.);
+                }
+                offset / distance * falloff_strength(*strength, distance, *radius, *falloff) * mass
+            }
+            Force::Vortex {
+                position: centre,
+                strength,
+                radius,
+                falloff,
+            } => {
+                let offset = position - centre;
+                let distance = offset.norm();
+                if distance == 0. {
+                    return Vector2::new(0., 0.);
+                }
+                let tangent = Vector2::new(-offset.y, offset.x) / distance;
+                tangent * falloff_strength(*strength, distance, *radius, *falloff) * mass
+            }
+            Force::Wind {
+                direction,
+                turbulence,
+                scale,
+                time,
+                seed,
+            } => {
+                let point = [position.x * scale, position.y * scale, *time];
+                // Different seeds for each axis, so that the turbulence isn't always diagonal.
+                let gust = Vector2::new(
+                    noise::sample(NoiseType::Simplex, *seed, &point),
+                    noise::sample(NoiseType::Simplex, seed.wrapping_add(1), &point),
+                );
+                direction + gust * *turbulence
+            }
+            Force::Spring {
+                anchor,
+                anchor_velocity,
+                rest_length,
+                stiffness,
+                damping,
+            } => {
+                let offset = position - anchor;
+                let distance = offset.norm();
+                if distance == 0. {
+                    return Vector2::new(0., 0.);
+                }
+                let direction = offset / distance;
+                let stretch = distance - rest_length;
+                let stretch_speed = (velocity - anchor_velocity).dot(&direction);
+                -direction * (stiffness * stretch + damping * stretch_speed)
+            }
+        }
+    }
+}
+
+/// Moves a particle after it's been integrated, to hold it somewhere a force only pulls it towards, like the end of a
+/// rope which shouldn't stretch. The velocity changes by however far the particle is moved, over the step.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Constraint {
+    /// Moves particles [strength] of the way towards being [rest_length] away from [anchor]. A [strength] of 1 keeps
+    /// them exactly [rest_length] away.
+    Distance {
+        anchor: Vector2<f64>,
+        rest_length: f64,
+        strength: f64,
+    },
+}
+
+impl Constraint {
+    /// How far a particle at [position] has to move to satisfy the constraint.
+    pub fn correction_at(&self, position: &Vector2<f64>) -> Vector2<f64> {
+        match self {
+            Constraint::Distance {
+                anchor,
+                rest_length,
+                strength,
+            } => {
+                let offset = position - anchor;
+                let distance = offset.norm();
+                if distance == 0. {
+                    return Vector2::new(0., 0.);
+                }
+                -offset / distance * (distance - rest_length) * strength.clamp(0., 1.)
+            }
+        }
+    }
+}
+
+/// The sum of any number of [Force]s, along with any [Constraint]s. Passed between force nodes so that forces can be
+/// combined before they're applied to particles, which evaluate it wherever their [Integrator](super::Integrator)
+/// needs it.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct ForceField {
+    forces: Vec<Force>,
+    constraints: Vec<Constraint>,
+}
+
+impl ForceField {
+    /// A field without any forces.
+    pub fn new() -> Self {
+        ForceField {
+            forces: Vec::new(),
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn from_force(force: Force) -> Self {
+        ForceField {
+            forces: vec![force],
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn from_constraint(constraint: Constraint) -> Self {
+        ForceField {
+            forces: Vec::new(),
+            constraints: vec![constraint],
+        }
+    }
+
+    /// A field with the forces and constraints of both fields.
+    pub fn combine(&self, other: &ForceField) -> ForceField {
+        ForceField {
+            forces: self.forces.iter().chain(&other.forces).cloned().collect(),
+            constraints: self.constraints.iter().chain(&other.constraints).cloned().collect(),
+        }
+    }
+
+    pub fn get_forces(&self) -> &Vec<Force> {
+        &self.forces
+    }
+
+    pub fn get_constraints(&self) -> &Vec<Constraint> {
+        &self.constraints
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.forces.is_empty() && self.constraints.is_empty()
+    }
+
+    /// The total force on a particle with the given [position], [velocity] and [mass].
+    pub fn force_at(&self, position: &Vector2<f64>, velocity: &Vector2<f64>, mass: f64) -> Vector2<f64> {
+        self.forces
+            .iter()
+            .fold(Vector2::new(0., 0.), |total, force| total + force.force_at(position, velocity, mass))
+    }
+
+    /// Moves [particle] by the sum of the constraints' corrections, changing its velocity to match over [dt] seconds.
+    /// Applied after the particle has been integrated.
+    pub fn constrain(&self, particle: &mut Particle, dt: f64) {
+        if self.constraints.is_empty() {
+            return;
+        }
+        let correction = self
+            .constraints
+            .iter()
+            .fold(Vector2::new(0., 0.), |total, constraint| total + constraint.correction_at(particle.get_position()));
+        *particle.get_mut_position() += correction;
+        if dt > 0. {
+            *particle.get_mut_velocity() += correction / dt;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spring_pulls_towards_rest_length() {
+        let spring = Force::Spring {
+            anchor: Vector2::new(0., 0.),
+            anchor_velocity: Vector2::new(0., 0.),
+            rest_length: 10.,
+            stiffness: 2.,
+            damping: 0.,
+        };
+        let at = |x: f64| spring.force_at(&Vector2::new(x, 0.), &Vector2::new(0., 0.), 1.);
+        assert_eq!(at(15.), Vector2::new(-10., 0.));
+        assert_eq!(at(5.), Vector2::new(10., 0.));
+        assert_eq!(at(10.), Vector2::new(0., 0.));
+    }
+
+    #[test]
+    fn gravity_scales_with_mass() {
+        let gravity = Force::Gravity(Vector2::new(0., 10.));
+        let velocity = Vector2::new(5., 0.);
+        assert_eq!(gravity.force_at(&Vector2::new(3., 4.), &velocity, 1.), Vector2::new(0., 10.));
+        assert_eq!(gravity.force_at(&Vector2::new(-3., 4.), &velocity, 2.5), Vector2::new(0., 25.));
+    }
+
+    #[test]
+    fn drag_opposes_velocity() {
+        let drag = |linear, quadratic, velocity| {
+            Force::Drag { linear, quadratic }.force_at(&Vector2::new(0., 0.), &velocity, 1.)
+        };
+        assert_eq!(drag(0.5, 0., Vector2::new(4., 0.)), Vector2::new(-2., 0.));
+        assert_eq!(drag(0., 0.5, Vector2::new(0., -4.)), Vector2::new(0., 8.));
+        assert_eq!(drag(0.5, 0.5, Vector2::new(3., 4.)), Vector2::new(-9., -12.));
+        assert_eq!(drag(0.5, 0.5, Vector2::new(0., 0.)), Vector2::new(0., 0.));
+    }
+
+    #[test]
+    fn attractor_falls_off_beyond_its_radius() {
+        let attractor = |strength| Force::Attractor {
+            position: Vector2::new(0., 0.),
+            strength,
+            radius: 10.,
+            falloff: 2.,
+        };
+        let at = |strength, x, mass| attractor(strength).force_at(&Vector2::new(x, 0.), &Vector2::new(0., 0.), mass);
+        assert_eq!(at(100., 5., 1.), Vector2::new(-100., 0.));
+        assert_eq!(at(100., 10., 1.), Vector2::new(-100., 0.));
+        assert_eq!(at(100., 20., 1.), Vector2::new(-25., 0.));
+        assert_eq!(at(100., -40., 2.), Vector2::new(12.5, 0.));
+        // A negative strength repels.
+        assert_eq!(at(-100., 5., 1.), Vector2::new(100., 0.));
+        assert_eq!(at(-100., 20., 1.), Vector2::new(25., 0.));
+        assert_eq!(at(100., 0., 1.), Vector2::new(0., 0.));
+    }
+
+    #[test]
+    fn vortex_turns_clockwise_on_screen() {
+        let vortex = |strength| Force::Vortex {
+            position: Vector2::new(10., 10.),
+            strength,
+            radius: 10.,
+            falloff: 1.,
+        };
+        let at = |strength, x, y| vortex(strength).force_at(&Vector2::new(x, y), &Vector2::new(0., 0.), 1.);
+        // With y pointing down, right of the centre turns down, and below it turns left.
+        assert_eq!(at(1., 15., 10.), Vector2::new(0., 1.));
+        assert_eq!(at(1., 10., 15.), Vector2::new(-1., 0.));
+        assert_eq!(at(1., 30., 10.), Vector2::new(0., 0.5));
+        assert_eq!(at(-1., 15., 10.), Vector2::new(0., -1.));
+        assert_eq!(at(1., 10., 10.), Vector2::new(0., 0.));
+    }
+
+    #[test]
+    fn wind_gusts_around_its_direction() {
+        let wind = |turbulence, time, seed| Force::Wind {
+            direction: Vector2::new(20., 0.),
+            turbulence,
+            scale: 0.1,
+            time,
+            seed,
+        };
+        let at = |force: &Force, x| force.force_at(&Vector2::new(x, 3.), &Vector2::new(0., 0.), 1.);
+        assert_eq!(at(&wind(0., 1.5, 0), 7.), Vector2::new(20., 0.));
+
+        let gusty = wind(10., 1.5, 0);
+        assert_eq!(at(&gusty, 7.), at(&wind(10., 1.5, 0), 7.));
+        assert_ne!(at(&gusty, 7.), at(&gusty, 12.));
+        assert_ne!(at(&gusty, 7.), at(&wind(10., 2., 0), 7.));
+        assert_ne!(at(&gusty, 7.), at(&wind(10., 1.5, 1), 7.));
+        // The noise stays within [-1, 1], so the gusts stay within the turbulence.
+        for x in 0..100 {
+            let gust = at(&gusty, x as f64) - Vector2::new(20., 0.);
+            assert!(gust.x.abs() <= 10. && gust.y.abs() <= 10., "{}", gust);
+        }
+    }
+
+    #[test]
+    fn distance_constraint_corrects_position_and_velocity() {
+        let field = ForceField::from_constraint(Constraint::Distance {
+            anchor: Vector2::new(0., 0.),
+            rest_length: 10.,
+            strength: 1.,
+        });
+        let mut particle = Particle::new();
+        *particle.get_mut_position() = Vector2::new(0., 14.);
+        field.constrain(&mut particle, 0.5);
+        assert_eq!(*particle.get_position(), Vector2::new(0., 10.));
+        assert_eq!(*particle.get_velocity(), Vector2::new(0., -8.));
+
+        // Half strength only goes half of the way, and constraints add up when fields are combined.
+        let half = ForceField::from_constraint(Constraint::Distance {
+            anchor: Vector2::new(0., 0.),
+            rest_length: 10.,
+            strength: 0.5,
+        });
+        let mut particle = Particle::new();
+        *particle.get_mut_position() = Vector2::new(4., 0.);
+        half.combine(&ForceField::new()).constrain(&mut particle, 1.);
+        assert_eq!(*particle.get_position(), Vector2::new(7., 0.));
+    }
+}
@@ -0,0 +1,157 @@
+use nalgebra::Vector2;
+
+/// How a [Particle](super::Particle) is moved forward in time. Verlet and RK4 follow constant accelerations exactly,
+/// while semi-implicit Euler overshoots the position by half the acceleration times dt squared every step, which
+/// shrinks with the timestep. They differ more for accelerations which depend on the particle's position or velocity
+/// (springs, drag, orbits etc.).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Integrator {
+    /// Updates the velocity, then moves by the new velocity. Cheap and stable enough for most sketches.
+    SemiImplicitEuler,
+    /// Velocity Verlet. Conserves energy well, so orbits don't spiral in or out.
+    Verlet,
+    /// Classic 4th order Runge-Kutta. The most accurate, at the cost of evaluating the acceleration 4 times per step.
+    RungeKutta4,
+}
+
+impl Integrator {
+    /// In the order of their index, see [Integrator::from_index].
+    pub const ALL: [Integrator; 3] = [Integrator::SemiImplicitEuler, Integrator::Verlet, Integrator::RungeKutta4];
+
+    /// Picks an integrator from a node input, rounding to the nearest index and clamping out of range values.
+    pub fn from_index(index: f64) -> Integrator {
+        let index = if index.is_finite() { index.round().max(0.) as usize } else { 0 };
+        Integrator::ALL[index.min(Integrator::ALL.len() - 1)]
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Integrator::SemiImplicitEuler => "Semi-Implicit Euler",
+            Integrator::Verlet => "Verlet",
+            Integrator::RungeKutta4 => "RK4",
+        }
+    }
+
+    /// Advances [position] and [velocity] by [dt] seconds. [acceleration] gives the acceleration for a position and
+    /// velocity, and may be evaluated at several points within the step.
+    pub fn step(
+        &self,
+        position: &mut Vector2<f64>,
+        velocity: &mut Vector2<f64>,
+        dt: f64,
+        acceleration: impl Fn(&Vector2<f64>, &Vector2<f64>) -> Vector2<f64>,
+    ) {
+        match self {
+            Integrator::SemiImplicitEuler => {
+                *velocity += acceleration(position, velocity) * dt;
+                *position += *velocity * dt;
+            }
+            Integrator::Verlet => {
+                let start_acceleration = acceleration(position, velocity);
+                *position += *velocity * dt + start_acceleration * (0.5 * dt * dt);
+                // The velocity at the end of the step isn't known yet, so estimate it for the new acceleration.
+                let predicted_velocity = *velocity + start_acceleration * dt;
+                let end_acceleration = acceleration(position, &predicted_velocity);
+                *velocity += (start_acceleration + end_acceleration) * (0.5 * dt);
+            }
+            Integrator::RungeKutta4 => {
+                let (p1, v1) = (*position, *velocity);
+                let a1 = acceleration(&p1, &v1);
+                let (p2, v2) = (p1 + v1 * (0.5 * dt), v1 + a1 * (0.5 * dt));
+                let a2 = acceleration(&p2, &v2);
+                let (p3, v3) = (p1 + v2 * (0.5 * dt), v1 + a2 * (0.5 * dt));
+                let a3 = acceleration(&p3, &v3);
+                let (p4, v4) = (p1 + v3 * dt, v1 + a3 * dt);
+                let a4 = acceleration(&p4, &v4);
+                *position += (v1 + v2 * 2. + v3 * 2. + v4) * (dt / 6.);
+                *velocity += (a1 + a2 * 2. + a3 * 2. + a4) * (dt / 6.);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs [integrator] from [position] and [velocity] for [steps] steps of [dt], returning where it ends up.
+    fn run(
+        integrator: Integrator,
+        mut position: Vector2<f64>,
+        mut velocity: Vector2<f64>,
+        dt: f64,
+        steps: usize,
+        acceleration: impl Fn(&Vector2<f64>, &Vector2<f64>) -> Vector2<f64>,
+    ) -> (Vector2<f64>, Vector2<f64>) {
+        for _ in 0..steps {
+            integrator.step(&mut position, &mut velocity, dt, &acceleration);
+        }
+        (position, velocity)
+    }
+
+    #[test]
+    fn constant_acceleration() {
+        let (position, velocity, gravity) = (Vector2::new(1., 2.), Vector2::new(3., 4.), Vector2::new(0., -10.));
+        let (dt, steps) = (0.1, 20);
+        let time = dt * steps as f64;
+        let expected_position = position + velocity * time + gravity * (0.5 * time * time);
+        let expected_velocity = velocity + gravity * time;
+
+        for integrator in [Integrator::Verlet, Integrator::RungeKutta4] {
+            let (end_position, end_velocity) = run(integrator, position, velocity, dt, steps, |_, _| gravity);
+            assert!((end_position - expected_position).norm() < 1e-9, "{:?}", integrator);
+            assert!((end_velocity - expected_velocity).norm() < 1e-9, "{:?}", integrator);
+        }
+
+        // Semi-implicit Euler gets the velocity right, but is ahead by half a step's worth of velocity change.
+        let (end_position, end_velocity) =
+            run(Integrator::SemiImplicitEuler, position, velocity, dt, steps, |_, _| gravity);
+        assert!((end_velocity - expected_velocity).norm() < 1e-9);
+        let overshoot = gravity * (0.5 * dt * time);
+        assert!((end_position - (expected_position + overshoot)).norm() < 1e-9);
+    }
+
+    #[test]
+    fn spring_follows_cosine() {
+        // x'' = -x, so starting at rest at x = 1 the position is cos(t) and the velocity -sin(t).
+        let spring = |position: &Vector2<f64>, _: &Vector2<f64>| -position;
+        let (dt, steps) = (0.01, 100);
+        let time = dt * steps as f64;
+        let expected_position = Vector2::new(time.cos(), 0.);
+        let expected_velocity = Vector2::new(-time.sin(), 0.);
+
+        let error = |integrator| {
+            let (position, velocity) = run(integrator, Vector2::new(1., 0.), Vector2::zeros(), dt, steps, spring);
+            (position - expected_position).norm() + (velocity - expected_velocity).norm()
+        };
+        let (euler, verlet, rk4) = (
+            error(Integrator::SemiImplicitEuler),
+            error(Integrator::Verlet),
+            error(Integrator::RungeKutta4),
+        );
+        assert!(euler < 1e-2, "{}", euler);
+        assert!(verlet < 1e-4, "{}", verlet);
+        assert!(rk4 < 1e-9, "{}", rk4);
+        assert!(rk4 < verlet && verlet < euler);
+    }
+
+    #[test]
+    fn verlet_keeps_orbit_energy() {
+        let spring = |position: &Vector2<f64>, _: &Vector2<f64>| -position;
+        let energy = |(position, velocity): (Vector2<f64>, Vector2<f64>)| {
+            0.5 * (position.norm_squared() + velocity.norm_squared())
+        };
+        let start = (Vector2::new(1., 0.), Vector2::new(0., 1.));
+        // Around 16 orbits.
+        let end = run(Integrator::Verlet, start.0, start.1, 0.05, 2000, spring);
+        assert!((energy(end) - energy(start)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn from_index_rounds_and_clamps() {
+        assert_eq!(Integrator::from_index(0.4), Integrator::SemiImplicitEuler);
+        assert_eq!(Integrator::from_index(1.), Integrator::Verlet);
+        assert_eq!(Integrator::from_index(7.), Integrator::RungeKutta4);
+        assert_eq!(Integrator::from_index(f64::NAN), Integrator::SemiImplicitEuler);
+    }
+}
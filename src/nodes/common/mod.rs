@@ -1,4 +1,21 @@
+mod bounds;
+pub mod expression;
+mod flocking;
+mod force_field;
+mod integrator;
+pub mod noise;
 mod particle;
+mod particle_system;
+pub mod random;
 pub mod shapes;
+mod spatial_hash;
+mod vector;
 
-pub use particle::Particle;
\ No newline at end of file
+pub use bounds::{Bounds, BoundsMode, BoundsShape};
+pub use flocking::Flocking;
+pub use force_field::{Constraint, Force, ForceField};
+pub use integrator::Integrator;
+pub use particle::Particle;
+pub use particle_system::ParticleSystem;
+pub use spatial_hash::SpatialHash;
+pub use vector::limit_length;
\ No newline at end of file
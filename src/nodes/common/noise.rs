@@ -0,0 +1,287 @@
+//! Seeded coherent noise in 1 to 3 dimensions. Lattice values and gradients are hashed from the seed and the lattice
+//! point (see [random]) instead of being looked up in a permutation table, so any seed works and the noise doesn't
+//! repeat. Every function returns values in [-1, 1].
+
+use super::random;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NoiseType {
+    /// Gradient noise on a square lattice.
+    Perlin,
+    /// Gradient noise on a simplex lattice, with fewer directional artifacts than [NoiseType::Perlin].
+    Simplex,
+    /// Interpolated random values, blockier than gradient noise.
+    Value,
+}
+
+/// Samples noise at [point], which must have 1 to 3 coordinates.
+pub fn sample(noise_type: NoiseType, seed: u64, point: &[f64]) -> f64 {
+    let value = match (noise_type, point.len()) {
+        (NoiseType::Perlin, _) => perlin(seed, point),
+        (NoiseType::Value, _) => value(seed, point),
+        (NoiseType::Simplex, 1) => simplex_1d(seed, point[0]),
+        (NoiseType::Simplex, 2) => simplex_2d(seed, point[0], point[1]),
+        (NoiseType::Simplex, _) => simplex_3d(seed, point[0], point[1], point[2]),
+    };
+    value.clamp(-1., 1.)
+}
+
+/// Sums [octaves] layers of noise, each [lacunarity] times the frequency and [persistence] times the amplitude of the
+/// one before, normalised back into [-1, 1].
+pub fn fractal(
+    noise_type: NoiseType,
+    seed: u64,
+    point: &[f64],
+    octaves: u32,
+    lacunarity: f64,
+    persistence: f64,
+) -> f64 {
+    let mut total = 0.;
+    let mut total_amplitude = 0.;
+    let mut amplitude = 1.;
+    let mut frequency = 1.;
+    let mut scaled = [0.; 3];
+    for octave in 0..octaves.max(1) {
+        for (scaled, coordinate) in scaled.iter_mut().zip(point) {
+            *scaled = coordinate * frequency;
+        }
+        // Each octave gets its own seed so that they don't line up at the origin.
+        total += amplitude * sample(noise_type, random::hash(&[seed, octave as u64]), &scaled[..point.len()]);
+        total_amplitude += amplitude;
+        amplitude *= persistence;
+        frequency *= lacunarity;
+    }
+    if total_amplitude == 0. {
+        0.
+    } else {
+        total / total_amplitude
+    }
+}
+
+/// Quintic smoothstep, so that the noise's second derivative is continuous across lattice cells.
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn lattice_hash(seed: u64, lattice_point: &[i64]) -> u64 {
+    let mut values = [seed, 0, 0, 0];
+    for (value, coordinate) in values[1..].iter_mut().zip(lattice_point) {
+        *value = *coordinate as u64;
+    }
+    random::hash(&values[..lattice_point.len() + 1])
+}
+
+/// A unit-ish gradient for [NoiseType::Perlin] and [NoiseType::Simplex], written into [gradient].
+fn lattice_gradient(seed: u64, lattice_point: &[i64], gradient: &mut [f64]) {
+    let hash = lattice_hash(seed, lattice_point);
+    match gradient.len() {
+        1 => gradient[0] = random::to_unit(hash) * 2. - 1.,
+        2 => {
+            let angle = random::to_unit(hash) * std::f64::consts::TAU;
+            gradient[0] = angle.cos();
+            gradient[1] = angle.sin();
+        }
+        _ => {
+            // The 12 edge midpoints of a cube, as in improved Perlin noise.
+            const EDGES: [[f64; 3]; 12] = [
+                [1., 1., 0.], [-1., 1., 0.], [1., -1., 0.], [-1., -1., 0.],
+                [1., 0., 1.], [-1., 0., 1.], [1., 0., -1.], [-1., 0., -1.],
+                [0., 1., 1.], [0., -1., 1.], [0., 1., -1.], [0., -1., -1.],
+            ];
+            gradient.copy_from_slice(&EDGES[(hash % 12) as usize]);
+        }
+    }
+}
+
+/// Interpolates a value for each corner of the lattice cell containing [point], weighted by [fade].
+fn interpolate_cell(point: &[f64], mut corner_value: impl FnMut(&[i64], &[f64]) -> f64) -> f64 {
+    let dimensions = point.len();
+    let mut total = 0.;
+    for corner in 0..(1 << dimensions) {
+        let mut lattice_point = [0; 3];
+        let mut offset = [0.; 3];
+        let mut weight = 1.;
+        for axis in 0..dimensions {
+            let cell = point[axis].floor();
+            let fraction = point[axis] - cell;
+            let is_upper = (corner >> axis) & 1 == 1;
+            lattice_point[axis] = cell as i64 + is_upper as i64;
+            offset[axis] = if is_upper { fraction - 1. } else { fraction };
+            weight *= if is_upper { fade(fraction) } else { 1. - fade(fraction) };
+        }
+        total += weight * corner_value(&lattice_point[..dimensions], &offset[..dimensions]);
+    }
+    total
+}
+
+fn value(seed: u64, point: &[f64]) -> f64 {
+    interpolate_cell(point, |lattice_point, _| random::to_unit(lattice_hash(seed, lattice_point)) * 2. - 1.)
+}
+
+fn perlin(seed: u64, point: &[f64]) -> f64 {
+    let mut gradient = [0.; 3];
+    let dimensions = point.len();
+    let total = interpolate_cell(point, |lattice_point, offset| {
+        lattice_gradient(seed, lattice_point, &mut gradient[..dimensions]);
+        gradient.iter().zip(offset).map(|(gradient, offset)| gradient * offset).sum::<f64>()
+    });
+    // Scales the largest possible value up to roughly 1.
+    match dimensions {
+        1 => total * 2.,
+        2 => total * std::f64::consts::SQRT_2,
+        _ => total,
+    }
+}
+
+fn simplex_1d(seed: u64, x: f64) -> f64 {
+    let cell = x.floor();
+    let mut total = 0.;
+    for corner in 0..2 {
+        let offset = x - cell - corner as f64;
+        let t = 1. - offset * offset;
+        let mut gradient = [0.];
+        lattice_gradient(seed, &[cell as i64 + corner], &mut gradient);
+        total += t.powi(4) * gradient[0] * offset;
+    }
+    // Scales the sum, which stays within about ±0.3 in practice, up to roughly [-1, 1].
+    total * 3.2
+}
+
+fn simplex_2d(seed: u64, x: f64, y: f64) -> f64 {
+    let skew = 0.5 * (3f64.sqrt() - 1.);
+    let unskew = (3. - 3f64.sqrt()) / 6.;
+
+    let s = (x + y) * skew;
+    let (i, j) = ((x + s).floor(), (y + s).floor());
+    let t = (i + j) * unskew;
+    let (x0, y0) = (x - (i - t), y - (j - t));
+    let (i1, j1) = if x0 > y0 { (1., 0.) } else { (0., 1.) };
+
+    let corners = [
+        (0., 0., x0, y0),
+        (i1, j1, x0 - i1 + unskew, y0 - j1 + unskew),
+        (1., 1., x0 - 1. + 2. * unskew, y0 - 1. + 2. * unskew),
+    ];
+    let mut total = 0.;
+    for (di, dj, dx, dy) in corners.iter() {
+        let t = 0.5 - dx * dx - dy * dy;
+        if t > 0. {
+            let mut gradient = [0.; 2];
+            lattice_gradient(seed, &[(i + di) as i64, (j + dj) as i64], &mut gradient);
+            total += t.powi(4) * (gradient[0] * dx + gradient[1] * dy);
+        }
+    }
+    // 70 is the usual factor for gradients of length √2, these are unit length.
+    total * 70. * std::f64::consts::SQRT_2
+}
+
+fn simplex_3d(seed: u64, x: f64, y: f64, z: f64) -> f64 {
+    let skew = 1. / 3.;
+    let unskew = 1. / 6.;
+
+    let s = (x + y + z) * skew;
+    let (i, j, k) = ((x + s).floor(), (y + s).floor(), (z + s).floor());
+    let t = (i + j + k) * unskew;
+    let (x0, y0, z0) = (x - (i - t), y - (j - t), z - (k - t));
+
+    // Which of the six tetrahedra in the skewed cube the point is in.
+    let (first, second) = if x0 >= y0 {
+        if y0 >= z0 {
+            ([1., 0., 0.], [1., 1., 0.])
+        } else if x0 >= z0 {
+            ([1., 0., 0.], [1., 0., 1.])
+        } else {
+            ([0., 0., 1.], [1., 0., 1.])
+        }
+    } else if y0 < z0 {
+        ([0., 0., 1.], [0., 1., 1.])
+    } else if x0 < z0 {
+        ([0., 1., 0.], [0., 1., 1.])
+    } else {
+        ([0., 1., 0.], [1., 1., 0.])
+    };
+
+    let mut total = 0.;
+    for (index, corner) in [[0., 0., 0.], first, second, [1., 1., 1.]].iter().enumerate() {
+        let offset = index as f64 * unskew;
+        let (dx, dy, dz) = (x0 - corner[0] + offset, y0 - corner[1] + offset, z0 - corner[2] + offset);
+        let t = 0.6 - dx * dx - dy * dy - dz * dz;
+        if t > 0. {
+            let mut gradient = [0.; 3];
+            let lattice_point = [(i + corner[0]) as i64, (j + corner[1]) as i64, (k + corner[2]) as i64];
+            lattice_gradient(seed, &lattice_point, &mut gradient);
+            total += t.powi(4) * (gradient[0] * dx + gradient[1] * dy + gradient[2] * dz);
+        }
+    }
+    total * 32.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TYPES: [NoiseType; 3] = [NoiseType::Perlin, NoiseType::Simplex, NoiseType::Value];
+
+    /// Points spread over several lattice cells, including negative ones, in the given number of dimensions.
+    fn points(dimensions: usize) -> impl Iterator<Item = Vec<f64>> {
+        (0..200).map(move |index| {
+            (0..dimensions)
+                .map(|axis| ((index * (7 + axis * 5)) % 97) as f64 * 0.173 - 8.)
+                .collect()
+        })
+    }
+
+    #[test]
+    fn values_stay_in_range() {
+        for noise_type in TYPES {
+            for dimensions in 1..=3 {
+                for point in points(dimensions) {
+                    let value = sample(noise_type, 3, &point);
+                    assert!((-1. ..=1.).contains(&value), "{:?} {:?} = {}", noise_type, point, value);
+                    let value = fractal(noise_type, 3, &point, 4, 2., 0.5);
+                    assert!((-1. ..=1.).contains(&value), "{:?} {:?} = {}", noise_type, point, value);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_noise() {
+        for noise_type in TYPES {
+            for point in points(2) {
+                assert_eq!(sample(noise_type, 7, &point), sample(noise_type, 7, &point));
+            }
+            // Different seeds give different noise.
+            assert!(points(2).any(|point| sample(noise_type, 7, &point) != sample(noise_type, 8, &point)));
+        }
+    }
+
+    #[test]
+    fn noise_is_continuous() {
+        for noise_type in TYPES {
+            for dimensions in 1..=3 {
+                for point in points(dimensions) {
+                    let nearby: Vec<f64> = point.iter().map(|coordinate| coordinate + 1e-4).collect();
+                    let change = (sample(noise_type, 1, &point) - sample(noise_type, 1, &nearby)).abs();
+                    assert!(change < 0.01, "{:?} jumps by {} at {:?}", noise_type, change, point);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn perlin_is_zero_on_lattice() {
+        for point in [[0., 0.], [3., -2.], [-5., 7.]] {
+            assert_eq!(sample(NoiseType::Perlin, 11, &point), 0.);
+        }
+    }
+
+    #[test]
+    fn fractal_with_one_octave_is_sample_with_octave_seed() {
+        let point = [1.3, -0.7];
+        let expected = sample(NoiseType::Simplex, random::hash(&[5, 0]), &point);
+        assert_eq!(fractal(NoiseType::Simplex, 5, &point, 1, 2., 0.5), expected);
+        // Zero octaves still gives one.
+        assert_eq!(fractal(NoiseType::Simplex, 5, &point, 0, 2., 0.5), expected);
+    }
+}
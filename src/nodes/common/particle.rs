@@ -1,26 +1,59 @@
 use nalgebra::Vector2;
 
+use super::Integrator;
+
+#[derive(Clone)]
 pub struct Particle {
     mass: f64,
+    /// Only used for drawing and collisions, the particle is simulated as a point.
+    radius: f64,
     acceleration: Vector2<f64>,
     velocity: Vector2<f64>,
     position: Vector2<f64>,
 }
 
+impl Default for Particle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Particle {
     pub fn new(
     ) -> Self {
         Particle {
             mass: 1.,
+            radius: 2.,
             acceleration: Vector2::new(0., 0.),
             velocity: Vector2::new(0., 0.),
             position: Vector2::new(0., 0.),
         }
     }
 
-    pub fn update(&mut self) {
-        self.velocity += self.acceleration;
-        self.position += self.velocity;
+    /// Integrates over [dt] seconds using semi-implicit Euler.
+    pub fn update(&mut self, dt: f64) {
+        self.integrate(Integrator::SemiImplicitEuler, dt);
+    }
+
+    /// Integrates over [dt] seconds using [integrator], then clears the forces applied since the last update.
+    pub fn integrate(&mut self, integrator: Integrator, dt: f64) {
+        self.integrate_with(integrator, dt, |_, _| Vector2::new(0., 0.));
+    }
+
+    /// Like [Particle::integrate], with an additional [force] depending on the particle's position and velocity, e.g.
+    /// a spring. Unlike forces passed to [Particle::apply_force], which stay constant over the step, it's evaluated
+    /// wherever the integrator needs it, which is what makes the higher order integrators more accurate.
+    pub fn integrate_with(
+        &mut self,
+        integrator: Integrator,
+        dt: f64,
+        force: impl Fn(&Vector2<f64>, &Vector2<f64>) -> Vector2<f64>,
+    ) {
+        let constant_acceleration = self.acceleration;
+        let mass = self.mass;
+        integrator.step(&mut self.position, &mut self.velocity, dt, |position, velocity| {
+            constant_acceleration + force(position, velocity) / mass
+        });
         self.acceleration.x = 0.;
         self.acceleration.y = 0.;
     }
@@ -33,6 +66,10 @@ impl Particle {
         &self.mass
     }
 
+    pub fn get_radius(&self) -> &f64 {
+        &self.radius
+    }
+
     pub fn get_acceleration(&self) -> &Vector2<f64> {
         &self.acceleration
     }
@@ -49,6 +86,10 @@ impl Particle {
         &mut self.mass
     }
 
+    pub fn get_mut_radius(&mut self) -> &mut f64 {
+        &mut self.radius
+    }
+
     pub fn get_mut_acceleration(&mut self) -> &mut Vector2<f64> {
         &mut self.acceleration
     }
@@ -0,0 +1,341 @@
+use nalgebra::Vector2;
+
+use super::{Bounds, ForceField, Integrator, Particle, SpatialHash};
+
+#[derive(Clone)]
+struct Slot {
+    particle: Particle,
+    /// Seconds since the particle was spawned.
+    age: f64,
+    /// Seconds the particle lives for, infinite if it never dies.
+    lifetime: f64,
+}
+
+/// A pool of up to [ParticleSystem::get_max_count] particles. Dead particles leave their slot free for the next one
+/// to be spawned, so the pool never grows past its maximum and slot indices of living particles stay stable.
+#[derive(Clone)]
+pub struct ParticleSystem {
+    slots: Vec<Option<Slot>>,
+    /// Indices of the empty slots in [slots], reused before any new slot is added.
+    free: Vec<usize>,
+    max_count: usize,
+}
+
+impl ParticleSystem {
+    pub fn new(max_count: usize) -> Self {
+        ParticleSystem {
+            slots: Vec::new(),
+            free: Vec::new(),
+            max_count,
+        }
+    }
+
+    pub fn get_max_count(&self) -> usize {
+        self.max_count
+    }
+
+    /// Kills any particles in slots past the new maximum.
+    pub fn set_max_count(&mut self, max_count: usize) {
+        self.max_count = max_count;
+        if self.slots.len() > max_count {
+            self.slots.truncate(max_count);
+            self.free.retain(|index| *index < max_count);
+        }
+    }
+
+    /// The number of living particles.
+    pub fn get_count(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.get_count() >= self.max_count
+    }
+
+    /// Adds [particle] to a free slot, returning its index, or None if the system is full. A [lifetime] of infinity
+    /// means the particle never dies.
+    pub fn spawn(&mut self, particle: Particle, lifetime: f64) -> Option<usize> {
+        let slot = Some(Slot {
+            particle,
+            age: 0.,
+            lifetime,
+        });
+        if let Some(index) = self.free.pop() {
+            self.slots[index] = slot;
+            Some(index)
+        } else if self.slots.len() < self.max_count {
+            self.slots.push(slot);
+            Some(self.slots.len() - 1)
+        } else {
+            None
+        }
+    }
+
+    pub fn kill(&mut self, index: usize) {
+        if let Some(slot) = self.slots.get_mut(index) {
+            if slot.take().is_some() {
+                self.free.push(index);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.free.clear();
+    }
+
+    /// Integrates every living particle over [dt] seconds, under [force_field] as well as any forces applied since the
+    /// last update, applies the field's constraints, and kills those which have outlived their lifetime.
+    pub fn update(&mut self, integrator: Integrator, dt: f64, force_field: &ForceField) {
+        for index in 0..self.slots.len() {
+            let is_expired = match &mut self.slots[index] {
+                Some(slot) => {
+                    let mass = *slot.particle.get_mass();
+                    slot.particle.integrate_with(integrator, dt, |position, velocity| {
+                        force_field.force_at(position, velocity, mass)
+                    });
+                    force_field.constrain(&mut slot.particle, dt);
+                    slot.age += dt;
+                    slot.age >= slot.lifetime
+                }
+                None => false,
+            };
+            if is_expired {
+                self.kill(index);
+            }
+        }
+    }
+
+    /// Applies the same [force] to every living particle, e.g. gravity.
+    pub fn apply_force(&mut self, force: Vector2<f64>) {
+        for (_, particle) in self.iter_mut() {
+            particle.apply_force(force);
+        }
+    }
+
+    /// Keeps every living particle inside [bounds], killing those it says should die.
+    pub fn apply_bounds(&mut self, bounds: &Bounds) {
+        for index in 0..self.slots.len() {
+            let is_inside = match &mut self.slots[index] {
+                Some(slot) => bounds.apply(&mut slot.particle),
+                None => true,
+            };
+            if !is_inside {
+                self.kill(index);
+            }
+        }
+    }
+
+    /// Separates overlapping particles and bounces them off each other, keeping [restitution] of their speed towards
+    /// each other. Heavier particles are moved less.
+    pub fn collide(&mut self, restitution: f64) {
+        let max_radius = self.iter().map(|(_, particle)| *particle.get_radius()).fold(0., f64::max);
+        if max_radius <= 0. {
+            return;
+        }
+
+        // Any two touching particles are at most 2 * max_radius apart, so only neighbouring cells need checking.
+        let mut spatial_hash = SpatialHash::new(2. * max_radius);
+        for (index, particle) in self.iter() {
+            spatial_hash.insert(index, particle.get_position());
+        }
+        for index in 0..self.slots.len() {
+            let (position, radius) = match self.get(index) {
+                Some(particle) => (*particle.get_position(), *particle.get_radius()),
+                None => continue,
+            };
+            // Each pair is only handled once, by its lower index.
+            for other in spatial_hash.query(&position, radius + max_radius) {
+                if other > index {
+                    self.collide_pair(index, other, restitution);
+                }
+            }
+        }
+    }
+
+    fn collide_pair(&mut self, a: usize, b: usize, restitution: f64) {
+        let (first, second) = self.slots.split_at_mut(b);
+        let (a, b) = match (&mut first[a], &mut second[0]) {
+            (Some(a), Some(b)) => (&mut a.particle, &mut b.particle),
+            _ => return,
+        };
+
+        let offset = b.get_position() - a.get_position();
+        let distance = offset.norm();
+        let overlap = a.get_radius() + b.get_radius() - distance;
+        if overlap <= 0. {
+            return;
+        }
+        // Particles at exactly the same position are pushed apart sideways.
+        let normal = if distance > 0. { offset / distance } else { Vector2::new(1., 0.) };
+        let inverse_mass = |particle: &Particle| if *particle.get_mass() > 0. { 1. / particle.get_mass() } else { 0. };
+        let (a_weight, b_weight) = (inverse_mass(a), inverse_mass(b));
+        let total_weight = a_weight + b_weight;
+        if total_weight == 0. {
+            return;
+        }
+
+        *a.get_mut_position() -= normal * (overlap * a_weight / total_weight);
+        *b.get_mut_position() += normal * (overlap * b_weight / total_weight);
+
+        let approach_speed = (b.get_velocity() - a.get_velocity()).dot(&normal);
+        if approach_speed < 0. {
+            let impulse = -(1. + restitution) * approach_speed / total_weight;
+            *a.get_mut_velocity() -= normal * (impulse * a_weight);
+            *b.get_mut_velocity() += normal * (impulse * b_weight);
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Particle> {
+        self.slots.get(index)?.as_ref().map(|slot| &slot.particle)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Particle> {
+        self.slots.get_mut(index)?.as_mut().map(|slot| &mut slot.particle)
+    }
+
+    /// How far through its life the particle in slot [index] is, from 0 when spawned to 1 when it dies. Always 0 for
+    /// particles which never die.
+    pub fn get_life_fraction(&self, index: usize) -> Option<f64> {
+        let slot = self.slots.get(index)?.as_ref()?;
+        Some((slot.age / slot.lifetime).min(1.))
+    }
+
+    /// The living particles along with their slot indices.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Particle)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| slot.as_ref().map(|slot| (index, &slot.particle)))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut Particle)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| slot.as_mut().map(|slot| (index, &mut slot.particle)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::common::{BoundsMode, BoundsShape};
+
+    fn particle_at(x: f64, y: f64) -> Particle {
+        let mut particle = Particle::new();
+        *particle.get_mut_position() = Vector2::new(x, y);
+        particle
+    }
+
+    #[test]
+    fn spawning_stops_at_max_count() {
+        let mut particles = ParticleSystem::new(2);
+        assert_eq!(particles.spawn(Particle::new(), f64::INFINITY), Some(0));
+        assert_eq!(particles.spawn(Particle::new(), f64::INFINITY), Some(1));
+        assert!(particles.is_full());
+        assert_eq!(particles.spawn(Particle::new(), f64::INFINITY), None);
+        assert_eq!(particles.get_count(), 2);
+
+        // Lowering the maximum kills the particles past it.
+        particles.set_max_count(1);
+        assert_eq!(particles.get_count(), 1);
+        assert!(particles.get(1).is_none());
+    }
+
+    #[test]
+    fn dead_particles_free_their_slot() {
+        let mut particles = ParticleSystem::new(3);
+        for x in 0..3 {
+            particles.spawn(particle_at(x as f64, 0.), f64::INFINITY);
+        }
+        particles.kill(1);
+        particles.kill(1);
+        assert_eq!(particles.get_count(), 2);
+        assert_eq!(particles.iter().map(|(index, _)| index).collect::<Vec<_>>(), vec![0, 2]);
+
+        // The free slot is reused, and the others keep their index.
+        assert_eq!(particles.spawn(particle_at(10., 0.), f64::INFINITY), Some(1));
+        assert_eq!(particles.get(1).unwrap().get_position().x, 10.);
+        assert_eq!(particles.get(2).unwrap().get_position().x, 2.);
+    }
+
+    #[test]
+    fn particles_die_after_their_lifetime() {
+        let mut particles = ParticleSystem::new(2);
+        let short = particles.spawn(Particle::new(), 0.25).unwrap();
+        let forever = particles.spawn(Particle::new(), f64::INFINITY).unwrap();
+
+        particles.update(Integrator::SemiImplicitEuler, 0.1, &ForceField::new());
+        particles.update(Integrator::SemiImplicitEuler, 0.1, &ForceField::new());
+        assert_eq!(particles.get_life_fraction(short), Some(0.8));
+        assert_eq!(particles.get_life_fraction(forever), Some(0.));
+
+        particles.update(Integrator::SemiImplicitEuler, 0.1, &ForceField::new());
+        assert!(particles.get(short).is_none());
+        assert!(particles.get(forever).is_some());
+        assert_eq!(particles.spawn(Particle::new(), 1.), Some(short));
+    }
+
+    fn moving(x: f64, velocity: f64, mass: f64) -> Particle {
+        let mut particle = particle_at(x, 0.);
+        *particle.get_mut_velocity() = Vector2::new(velocity, 0.);
+        *particle.get_mut_mass() = mass;
+        particle
+    }
+
+    #[test]
+    fn overlapping_particles_bounce_apart() {
+        // Both have the default radius of 2, so they overlap by 1.
+        let mut particles = ParticleSystem::new(3);
+        let a = particles.spawn(moving(0., 1., 1.), f64::INFINITY).unwrap();
+        let b = particles.spawn(moving(3., -1., 1.), f64::INFINITY).unwrap();
+        let far = particles.spawn(moving(50., -1., 1.), f64::INFINITY).unwrap();
+        particles.collide(0.5);
+
+        let (a, b, far) = (particles.get(a).unwrap(), particles.get(b).unwrap(), particles.get(far).unwrap());
+        assert_eq!(a.get_position(), &Vector2::new(-0.5, 0.));
+        assert_eq!(b.get_position(), &Vector2::new(3.5, 0.));
+        // They were approaching at 2, and separate at half of that.
+        assert_eq!(a.get_velocity(), &Vector2::new(-0.5, 0.));
+        assert_eq!(b.get_velocity(), &Vector2::new(0.5, 0.));
+        assert_eq!(far.get_position(), &Vector2::new(50., 0.));
+        assert_eq!(far.get_velocity(), &Vector2::new(-1., 0.));
+    }
+
+    #[test]
+    fn heavier_particles_move_less() {
+        let mut particles = ParticleSystem::new(2);
+        let heavy = particles.spawn(moving(0., 0., 3.), f64::INFINITY).unwrap();
+        let light = particles.spawn(moving(3., -4., 1.), f64::INFINITY).unwrap();
+        particles.collide(1.);
+
+        let (heavy, light) = (particles.get(heavy).unwrap(), particles.get(light).unwrap());
+        assert_eq!(heavy.get_position(), &Vector2::new(-0.25, 0.));
+        assert_eq!(light.get_position(), &Vector2::new(3.75, 0.));
+        // A perfectly elastic collision keeps the momentum and the energy.
+        assert_eq!(heavy.get_velocity(), &Vector2::new(-2., 0.));
+        assert_eq!(light.get_velocity(), &Vector2::new(2., 0.));
+    }
+
+    #[test]
+    fn killing_bounds_free_the_slot() {
+        let bounds = Bounds::new(
+            BoundsShape::Rectangle {
+                min: Vector2::new(0., 0.),
+                max: Vector2::new(10., 10.),
+            },
+            BoundsMode::Kill,
+            0.,
+        );
+        let mut particles = ParticleSystem::new(2);
+        let inside = particles.spawn(particle_at(5., 5.), f64::INFINITY).unwrap();
+        let outside = particles.spawn(particle_at(15., 5.), f64::INFINITY).unwrap();
+        particles.apply_bounds(&bounds);
+
+        assert_eq!(particles.get_count(), 1);
+        assert!(particles.get(inside).is_some());
+        assert!(particles.get(outside).is_none());
+        assert_eq!(particles.spawn(particle_at(5., 5.), f64::INFINITY), Some(outside));
+    }
+}
@@ -0,0 +1,67 @@
+//! Deterministic randomness. Everything is derived by hashing the graph's seed with whatever identifies a value (a
+//! node's seed input, the frame, a lattice point etc.), so the same graph always produces the same values regardless
+//! of compute order.
+
+use std::f64::consts::TAU;
+
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// The SplitMix64 finaliser. Every bit of [value] affects every bit of the result.
+pub fn mix(value: u64) -> u64 {
+    let mut z = value;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Combines [values] into a single hash. The order matters.
+pub fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(GOLDEN_GAMMA, |hash, value| mix(hash ^ value).wrapping_add(GOLDEN_GAMMA))
+}
+
+/// Maps a hash onto [0, 1).
+pub fn to_unit(hash: u64) -> f64 {
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Seeds typed into nodes are f64s, as that's what the sliders edit.
+pub fn seed_from_f64(seed: f64) -> u64 {
+    seed.round() as i64 as u64
+}
+
+/// A SplitMix64 generator, for nodes which need a sequence of values from one seed.
+#[derive(Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        mix(self.state)
+    }
+
+    /// Uniform in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        to_unit(self.next_u64())
+    }
+
+    /// Uniform in [min, max).
+    pub fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+
+    /// Normally distributed, using the Box-Muller transform.
+    pub fn normal(&mut self, mean: f64, standard_deviation: f64) -> f64 {
+        // 1 - x is in (0, 1], so the logarithm is always finite.
+        let radius = (-2. * (1. - self.next_f64()).ln()).sqrt();
+        let angle = TAU * self.next_f64();
+        mean + standard_deviation * radius * angle.cos()
+    }
+}
@@ -1,5 +1,6 @@
 use nalgebra::Vector2;
 
+#[derive(Clone)]
 pub struct Circle {
     position: Vector2<f64>,
     radius: f64
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use nalgebra::Vector2;
+
+/// Buckets indices by position on a grid of square cells, so that finding everything near a point only has to look at
+/// the cells around it rather than at every item. Works best with cells about as big as the distances queried.
+pub struct SpatialHash {
+    cell_size: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f64) -> Self {
+        SpatialHash {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn get_cell_size(&self) -> f64 {
+        self.cell_size
+    }
+
+    fn cell(&self, position: &Vector2<f64>) -> (i64, i64) {
+        (
+            (position.x / self.cell_size).floor() as i64,
+            (position.y / self.cell_size).floor() as i64,
+        )
+    }
+
+    pub fn insert(&mut self, index: usize, position: &Vector2<f64>) {
+        let cell = self.cell(position);
+        self.cells.entry(cell).or_default().push(index);
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    /// Every index inserted in a cell overlapping the square around [position] reaching [radius] in each direction.
+    /// This includes some further away than [radius], so the caller still needs to check the actual distance.
+    pub fn query(&self, position: &Vector2<f64>, radius: f64) -> impl Iterator<Item = usize> + '_ {
+        let reach = Vector2::new(radius, radius);
+        let (min_x, min_y) = self.cell(&(position - reach));
+        let (max_x, max_y) = self.cell(&(position + reach));
+        (min_x..=max_x)
+            .flat_map(move |x| (min_y..=max_y).filter_map(move |y| self.cells.get(&(x, y))))
+            .flatten()
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_finds_nearby_indices() {
+        let mut spatial_hash = SpatialHash::new(10.);
+        spatial_hash.insert(0, &Vector2::new(5., 5.));
+        spatial_hash.insert(1, &Vector2::new(14., 5.));
+        spatial_hash.insert(2, &Vector2::new(-3., -3.));
+        spatial_hash.insert(3, &Vector2::new(100., 100.));
+
+        let mut found: Vec<usize> = spatial_hash.query(&Vector2::new(6., 4.), 5.).collect();
+        found.sort_unstable();
+        // Index 1 is further away than the radius, but in a cell the square around the position overlaps.
+        assert_eq!(found, [0, 1]);
+        assert_eq!(spatial_hash.query(&Vector2::new(100., 100.), 1.).collect::<Vec<_>>(), [3]);
+    }
+
+    #[test]
+    fn clear_removes_everything() {
+        let mut spatial_hash = SpatialHash::new(1.);
+        spatial_hash.insert(0, &Vector2::new(0., 0.));
+        spatial_hash.clear();
+        assert_eq!(spatial_hash.query(&Vector2::new(0., 0.), 1.).count(), 0);
+    }
+}
@@ -0,0 +1,24 @@
+use nalgebra::Vector2;
+
+/// Scales [vector] down to [max_length] if it is longer, e.g. to cap a particle's speed.
+pub fn limit_length(vector: Vector2<f64>, max_length: f64) -> Vector2<f64> {
+    let max_length = max_length.max(0.);
+    let length = vector.norm();
+    if length > max_length {
+        vector * (max_length / length)
+    } else {
+        vector
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_longer_vectors_are_shortened() {
+        assert_eq!(limit_length(Vector2::new(3., 4.), 10.), Vector2::new(3., 4.));
+        assert_eq!(limit_length(Vector2::new(3., 4.), 2.5), Vector2::new(1.5, 2.));
+        assert_eq!(limit_length(Vector2::new(3., 4.), -1.), Vector2::new(0., 0.));
+    }
+}
@@ -0,0 +1,121 @@
+/** Generates everything a simple node type needs: a `NAME` constant, a constant index for every port, a
+    `node_factory`, a [NodeBehavior] and a `widget_factory` building a standard widget from the port list (only with
+    the `gui` feature).
+
+    Every input is cloned into a variable before `compute` runs, and every output is cloned into a mutable variable
+    which is written back to the [Cache] afterwards. Outputs go back to their default when the graph is reset. The
+    [FrameContext] is bound to the name given to `compute`. Default values stay in the [Cache] while an input is
+    connected, so they are still there when it is disconnected again. An f64 input can be followed by `[min, max]`, the
+    range of its slider in the standard widget. `internal_state: true` makes the node compute every frame, while
+    `internal_state_if: INPUT` only does so while the bool input `INPUT` is true.
+
+    ```ignore
+    define_node! {
+        name: "Add",
+        inputs: {
+            A(a): f64 = 0. => "A",
+            B(b): f64 = 0. => "B" [0., 100.],
+        },
+        outputs: {
+            SUM(sum): f64 = 0. => "Sum",
+        },
+        compute(context) {
+            sum = a + b;
+        }
+    }
+    ```
+*/
+macro_rules! define_node {
+    (
+        name: $name:literal,
+        $(internal_state: $internal_state:literal,)?
+        $(internal_state_if: $internal_state_if:ident,)?
+        inputs: {
+            $(
+                $input:ident($input_var:ident): $input_ty:ty = $input_default:expr => $input_label:literal
+                $([$input_min:expr, $input_max:expr])?
+            ),* $(,)?
+        },
+        outputs: {
+            $($output:ident($output_var:ident): $output_ty:ty = $output_default:expr => $output_label:literal),* $(,)?
+        },
+        compute($context:ident) $compute:block $(,)?
+    ) => {
+        pub const NAME: &str = $name;
+
+        define_node!(@indices 0usize; $($input,)* $($output,)*);
+
+        pub fn node_factory(cache: &mut $crate::core::Cache) -> $crate::core::Node {
+            let ports = vec![
+                $($crate::core::Port::new(
+                    $input_label,
+                    cache.insert::<$input_ty>($input_default),
+                    $crate::core::Direction::Input,
+                ),)*
+                $($crate::core::Port::new(
+                    $output_label,
+                    cache.insert::<$output_ty>($output_default),
+                    $crate::core::Direction::Output,
+                ),)*
+            ];
+
+            let node = $crate::core::Node::new(ports, Behavior);
+            $(let node = if $internal_state { node.with_internal_state() } else { node };)?
+            node
+        }
+
+        struct Behavior;
+
+        impl $crate::core::NodeBehavior for Behavior {
+            #[allow(unused_variables)]
+            fn init(&mut self, ports: &Vec<$crate::core::Port>, cache: &mut $crate::core::Cache) {
+                $(cache.set::<$output_ty>(ports[$output].get_cache_index(), $output_default);)*
+            }
+
+            #[allow(unused_variables, unused_mut, unused_assignments)]
+            fn compute(
+                &mut self,
+                ports: &Vec<$crate::core::Port>,
+                cache: &mut $crate::core::Cache,
+                $context: &$crate::core::FrameContext,
+            ) {
+                $(let $input_var: $input_ty = match cache.get::<$input_ty>(ports[$input].get_cache_index()) {
+                    Some(value) => value.clone(),
+                    None => return,
+                };)*
+                $(let mut $output_var: $output_ty = match cache.get::<$output_ty>(ports[$output].get_cache_index()) {
+                    Some(value) => value.clone(),
+                    None => return,
+                };)*
+
+                $compute
+
+                $(cache.set(ports[$output].get_cache_index(), $output_var);)*
+            }
+
+            $(
+                fn has_internal_state(&self, ports: &Vec<$crate::core::Port>, cache: &$crate::core::Cache) -> bool {
+                    cache.get::<bool>(ports[$internal_state_if].get_cache_index()).copied().unwrap_or(false)
+                }
+            )?
+        }
+
+        #[cfg(feature = "gui")]
+        pub fn widget_factory(
+            node: $crate::core::NodeId,
+        ) -> Box<dyn druid::Widget<std::rc::Rc<std::cell::RefCell<$crate::core::App>>>> {
+            use $crate::gui::standard_widget::{standard_widget, PortDescription};
+            standard_widget($name, node, vec![
+                $(PortDescription::new::<$input_ty>($input_label, $input, $crate::core::Direction::Input)
+                    $(.with_range($input_min, $input_max))?,)*
+                $(PortDescription::new::<$output_ty>($output_label, $output, $crate::core::Direction::Output),)*
+            ])
+        }
+    };
+
+    (@indices $index:expr;) => {};
+    (@indices $index:expr; $port:ident, $($rest:ident,)*) => {
+        const $port: usize = $index;
+        define_node!(@indices $index + 1; $($rest,)*);
+    };
+}
@@ -0,0 +1,307 @@
+//! Spawns particles into a [ParticleSystem] at a steady rate, and simulates them until their lifetime runs out. New
+//! particles are scattered randomly around the emitter's position and velocity, seeded like the Random node. Nodes
+//! which change the particles, like Boids, can be connected back to the "Feedback" input to keep simulating their
+//! changes.
+
+#[cfg(feature = "gui")]
+use std::{cell::RefCell, rc::Rc};
+
+#[cfg(feature = "gui")]
+use druid::Widget;
+use nalgebra::Vector2;
+
+use super::common::{
+    random::{self, Rng},
+    Bounds, ForceField, Integrator, Particle, ParticleSystem,
+};
+use crate::core::{Cache, Direction, FrameContext, Node, NodeBehavior, Port};
+#[cfg(feature = "gui")]
+use crate::{
+    core::{App, NodeId},
+    gui::standard_widget::{standard_widget, PortDescription},
+};
+
+pub const NAME: &str = "Particle Emitter";
+
+// Inputs
+pub const POSITION: usize = 0;
+pub const POSITION_SPREAD: usize = 1;
+pub const VELOCITY: usize = 2;
+pub const VELOCITY_SPREAD: usize = 3;
+pub const RATE: usize = 4;
+pub const MAX_COUNT: usize = 5;
+pub const LIFETIME: usize = 6;
+pub const MASS: usize = 7;
+pub const RADIUS: usize = 8;
+pub const FORCE: usize = 9;
+pub const FORCE_FIELD: usize = 10;
+pub const BOUNDS: usize = 11;
+/// Whether particles collide with each other.
+pub const COLLIDE: usize = 12;
+/// How much of their speed towards each other colliding particles keep.
+pub const RESTITUTION: usize = 13;
+/// While connected, the particles are replaced with these before each step, e.g. after another node has steered them.
+pub const FEEDBACK: usize = 14;
+/// Picks the [Integrator] by its index in [Integrator::ALL].
+pub const INTEGRATOR: usize = 15;
+pub const SEED: usize = 16;
+// Outputs
+pub const PARTICLES: usize = 17;
+pub const COUNT: usize = 18;
+
+const DEFAULT_MAX_COUNT: f64 = 100.;
+const COLLISION_PASSES: usize = 4;
+
+pub fn node_factory(cache: &mut Cache) -> Node {
+    let ports = vec![
+        Port::new("Position", cache.insert(Vector2::new(0., 0.)), Direction::Input),
+        Port::new("Position Spread", cache.insert(0.), Direction::Input),
+        Port::new("Velocity", cache.insert(Vector2::new(0., 0.)), Direction::Input),
+        Port::new("Velocity Spread", cache.insert(10.), Direction::Input),
+        Port::new("Rate", cache.insert(10.), Direction::Input),
+        Port::new("Max Count", cache.insert(DEFAULT_MAX_COUNT), Direction::Input),
+        Port::new("Lifetime", cache.insert(5.), Direction::Input),
+        Port::new("Mass", cache.insert(1.), Direction::Input),
+        Port::new("Radius", cache.insert(2.), Direction::Input),
+        Port::new("Force", cache.insert(Vector2::new(0., 0.)), Direction::Input),
+        Port::new("Force Field", cache.insert(ForceField::new()), Direction::Input),
+        Port::new("Bounds", cache.insert(Bounds::unbounded()), Direction::Input),
+        Port::new("Collide", cache.insert(false), Direction::Input),
+        Port::new("Restitution", cache.insert(0.5), Direction::Input),
+        Port::new("Feedback", cache.insert(ParticleSystem::new(0)), Direction::Input),
+        Port::new("Integrator", cache.insert(0.), Direction::Input),
+        Port::new("Seed", cache.insert(0.), Direction::Input),
+        Port::new(
+            "Particles",
+            cache.insert(ParticleSystem::new(DEFAULT_MAX_COUNT as usize)),
+            Direction::Output,
+        ),
+        Port::new("Count", cache.insert(0.), Direction::Output),
+    ];
+
+    Node::new(ports, EmitterBehavior::new()).with_internal_state()
+}
+
+/// A point within [radius] of [centre], uniformly distributed over the disc.
+fn scatter(rng: &mut Rng, centre: Vector2<f64>, radius: f64) -> Vector2<f64> {
+    let distance = radius * rng.next_f64().sqrt();
+    let angle = rng.range(0., std::f64::consts::TAU);
+    centre + Vector2::new(angle.cos(), angle.sin()) * distance
+}
+
+struct EmitterBehavior {
+    /// Particles which are due but haven't been spawned yet, as less than one particle may be due each frame.
+    pending: f64,
+}
+
+impl EmitterBehavior {
+    fn new() -> EmitterBehavior {
+        EmitterBehavior { pending: 0. }
+    }
+}
+
+impl NodeBehavior for EmitterBehavior {
+    /// Removes every particle.
+    fn init(&mut self, ports: &Vec<Port>, cache: &mut Cache) {
+        self.pending = 0.;
+        if let Some(particles) = cache.get_mut::<ParticleSystem>(ports[PARTICLES].get_cache_index()) {
+            particles.clear();
+        }
+        cache.set(ports[COUNT].get_cache_index(), 0.);
+    }
+
+    fn compute(&mut self, ports: &Vec<Port>, cache: &mut Cache, context: &FrameContext) {
+        let input = |port: usize| cache.get::<f64>(ports[port].get_cache_index()).copied().unwrap_or(0.);
+        let vector_input = |port: usize| {
+            cache
+                .get::<Vector2<f64>>(ports[port].get_cache_index())
+                .copied()
+                .unwrap_or_else(Vector2::zeros)
+        };
+        let position = vector_input(POSITION);
+        let position_spread = input(POSITION_SPREAD).max(0.);
+        let velocity = vector_input(VELOCITY);
+        let velocity_spread = input(VELOCITY_SPREAD).max(0.);
+        let rate = input(RATE).max(0.);
+        let max_count = input(MAX_COUNT).max(0.) as usize;
+        // A lifetime of 0 or less means particles live forever.
+        let lifetime = match input(LIFETIME) {
+            lifetime if lifetime > 0. => lifetime,
+            _ => f64::INFINITY,
+        };
+        let mass = input(MASS);
+        let radius = input(RADIUS);
+        let force = vector_input(FORCE);
+        let force_field = cache
+            .get::<ForceField>(ports[FORCE_FIELD].get_cache_index())
+            .cloned()
+            .unwrap_or_default();
+        let bounds = cache.get::<Bounds>(ports[BOUNDS].get_cache_index()).cloned().unwrap_or_default();
+        let collide = cache.get::<bool>(ports[COLLIDE].get_cache_index()).copied().unwrap_or(false);
+        let restitution = input(RESTITUTION);
+        let feedback = if ports[FEEDBACK].is_connected() {
+            cache.get::<ParticleSystem>(ports[FEEDBACK].get_cache_index()).cloned()
+        } else {
+            None
+        };
+        let integrator = Integrator::from_index(input(INTEGRATOR));
+        let seed = input(SEED);
+
+        let particles = match cache.get_mut::<ParticleSystem>(ports[PARTICLES].get_cache_index()) {
+            Some(particles) => particles,
+            None => return,
+        };
+        if let Some(feedback) = feedback {
+            *particles = feedback;
+        }
+        particles.set_max_count(max_count);
+        particles.apply_force(force);
+        particles.update(integrator, context.dt, &force_field);
+        particles.apply_bounds(&bounds);
+        if collide {
+            // Resolving one collision can cause another, or push a particle out of the bounds, so a few passes are
+            // needed for piles of particles to settle.
+            for _ in 0..COLLISION_PASSES {
+                particles.collide(restitution);
+                particles.apply_bounds(&bounds);
+            }
+        }
+
+        self.pending += rate * context.dt;
+        let mut rng = Rng::new(random::hash(&[
+            context.seed,
+            context.salt,
+            random::seed_from_f64(seed),
+            context.frame,
+        ]));
+        while self.pending >= 1. {
+            self.pending -= 1.;
+            let mut particle = Particle::new();
+            *particle.get_mut_mass() = mass;
+            *particle.get_mut_radius() = radius;
+            *particle.get_mut_position() = scatter(&mut rng, position, position_spread);
+            *particle.get_mut_velocity() = scatter(&mut rng, velocity, velocity_spread);
+            if particles.spawn(particle, lifetime).is_none() {
+                // Particles which don't fit aren't saved up for later, otherwise a burst would follow every death.
+                self.pending = self.pending.fract();
+                break;
+            }
+        }
+
+        let count = particles.get_count() as f64;
+        cache.set(ports[COUNT].get_cache_index(), count);
+    }
+}
+
+#[cfg(feature = "gui")]
+pub fn widget_factory(node: NodeId) -> Box<dyn Widget<Rc<RefCell<App>>>> {
+    standard_widget(
+        NAME,
+        node,
+        vec![
+            PortDescription::new::<Vector2<f64>>("Position", POSITION, Direction::Input),
+            PortDescription::new::<f64>("Position Spread", POSITION_SPREAD, Direction::Input).with_range(0., 400.),
+            PortDescription::new::<Vector2<f64>>("Velocity", VELOCITY, Direction::Input),
+            PortDescription::new::<f64>("Velocity Spread", VELOCITY_SPREAD, Direction::Input).with_range(0., 400.),
+            PortDescription::new::<f64>("Rate", RATE, Direction::Input).with_range(0., 200.),
+            PortDescription::new::<f64>("Max Count", MAX_COUNT, Direction::Input).with_range(0., 1000.),
+            PortDescription::new::<f64>("Lifetime", LIFETIME, Direction::Input).with_range(0., 30.),
+            PortDescription::new::<f64>("Mass", MASS, Direction::Input).with_range(0., 10.),
+            PortDescription::new::<f64>("Radius", RADIUS, Direction::Input).with_range(0., 50.),
+            PortDescription::new::<Vector2<f64>>("Force", FORCE, Direction::Input),
+            PortDescription::new::<ForceField>("Force Field", FORCE_FIELD, Direction::Input),
+            PortDescription::new::<Bounds>("Bounds", BOUNDS, Direction::Input),
+            PortDescription::new::<bool>("Collide", COLLIDE, Direction::Input),
+            PortDescription::new::<f64>("Restitution", RESTITUTION, Direction::Input).with_range(0., 1.),
+            PortDescription::new::<ParticleSystem>("Feedback", FEEDBACK, Direction::Input),
+            PortDescription::new::<f64>("Integrator", INTEGRATOR, Direction::Input)
+                .with_range(0., (Integrator::ALL.len() - 1) as f64),
+            PortDescription::new::<f64>("Seed", SEED, Direction::Input).with_range(0., 100.),
+            PortDescription::new::<ParticleSystem>("Particles", PARTICLES, Direction::Output),
+            PortDescription::new::<f64>("Count", COUNT, Direction::Output),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{App, NodeId},
+        nodes,
+    };
+
+    fn particles(app: &App, emitter: NodeId) -> &ParticleSystem {
+        let port = &app.get_node(emitter).unwrap().get_ports()[PARTICLES];
+        app.get_cache().get::<ParticleSystem>(port.get_cache_index()).unwrap()
+    }
+
+    /// An emitter at rest at the origin, stepping 0.1 seconds at a time.
+    fn emitter(rate: f64, max_count: f64, lifetime: f64) -> (App, NodeId) {
+        let mut app = nodes::new_app().with_timestep(0.1).unwrap();
+        let emitter = app.add_node(NAME);
+        app.set_value(emitter, VELOCITY_SPREAD, 0.);
+        app.set_value(emitter, RATE, rate);
+        app.set_value(emitter, MAX_COUNT, max_count);
+        app.set_value(emitter, LIFETIME, lifetime);
+        (app, emitter)
+    }
+
+    #[test]
+    fn fractional_spawns_add_up() {
+        // Half a particle is due every step.
+        let (mut app, emitter) = emitter(5., 100., 0.);
+        let mut counts = Vec::new();
+        for _ in 0..6 {
+            app.step();
+            counts.push(particles(&app, emitter).get_count());
+        }
+        assert_eq!(counts, vec![0, 1, 1, 2, 2, 3]);
+    }
+
+    #[test]
+    fn spawns_which_dont_fit_are_dropped() {
+        // One particle is due every step, but only one fits.
+        let (mut app, emitter) = emitter(10., 1., 0.);
+        for _ in 0..10 {
+            app.step();
+        }
+        assert_eq!(particles(&app, emitter).get_count(), 1);
+
+        // Making room doesn't spawn a burst of the particles which were due while it was full.
+        app.set_value(emitter, MAX_COUNT, 5.);
+        app.step();
+        assert_eq!(particles(&app, emitter).get_count(), 2);
+        let port = &app.get_node(emitter).unwrap().get_ports()[COUNT];
+        assert_eq!(app.get_cache().get::<f64>(port.get_cache_index()), Some(&2.));
+    }
+
+    #[test]
+    fn expired_particles_make_room() {
+        // Each particle lives for 3 steps, so no more than 3 are ever alive, always in the first 3 slots.
+        let (mut app, emitter) = emitter(10., 100., 0.25);
+        for _ in 0..20 {
+            app.step();
+            let particles = particles(&app, emitter);
+            assert!(particles.get_count() <= 3);
+            assert!(particles.iter().all(|(index, _)| index < 3));
+        }
+        assert_eq!(particles(&app, emitter).get_count(), 3);
+    }
+
+    #[test]
+    fn feedback_replaces_the_particles() {
+        let (mut app, source) = emitter(10., 100., 0.);
+        app.set_value(source, POSITION, Vector2::new(100., 0.));
+        let emitter = app.add_node(NAME);
+        app.set_value(emitter, RATE, 0.);
+        app.add_edge(source, PARTICLES, emitter, FEEDBACK).unwrap();
+        for _ in 0..3 {
+            app.step();
+        }
+
+        let particles = particles(&app, emitter);
+        assert_eq!(particles.get_count(), 3);
+        assert!(particles.iter().all(|(_, particle)| particle.get_position() == &Vector2::new(100., 0.)));
+    }
+}
@@ -0,0 +1,366 @@
+//! Evaluates a formula typed into the node, see [expression](super::common::expression) for the syntax. Every free
+//! variable in the formula gets an input port of its own, and the result is output as a number or a vector depending
+//! on the formula. If the formula can't be parsed, the error is shown on the node and it keeps its previous result
+//! until the formula is fixed. Its inputs still follow the variables in the formula, and the result keeps its type.
+
+use std::any::TypeId;
+#[cfg(feature = "gui")]
+use std::{cell::RefCell, rc::Rc};
+
+#[cfg(feature = "gui")]
+use druid::{
+    text::ParseFormatter,
+    widget::{Flex, Label, LineBreaking, TextBox},
+    BoxConstraints, Color, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx, Point, Size,
+    UpdateCtx, Widget, WidgetExt, WidgetPod,
+};
+use nalgebra::Vector2;
+
+use super::common::expression::{self, Expression, Value, ValueType};
+use crate::core::{Cache, CacheIndex, Direction, FrameContext, Node, NodeBehavior, Port};
+#[cfg(feature = "gui")]
+use crate::{
+    core::{App, NodeId},
+    gui::{
+        cache_lens::CacheLens,
+        standard_widget::{node_frame, port_list, PortDescription},
+    },
+};
+
+pub const NAME: &str = "Expression";
+
+// Inputs
+pub const FORMULA: usize = 0;
+// Outputs
+pub const RESULT: usize = 1;
+/// Every port from here on is the input for one of the formula's variables, in order of first appearance.
+pub const FIRST_VARIABLE: usize = 2;
+
+/// The names of the ports before [FIRST_VARIABLE]. Variables can't share them, as edges are reconnected by name.
+const RESERVED_NAMES: &[&str] = &["Formula", "Result"];
+
+pub fn node_factory(cache: &mut Cache) -> Node {
+    let ports = vec![
+        Port::new("Formula", cache.insert(String::from("0")), Direction::Input),
+        Port::new("Result", insert_default(cache, ValueType::Number), Direction::Output),
+    ];
+
+    Node::new(ports, ExpressionBehavior::new())
+}
+
+fn type_id(value_type: ValueType) -> TypeId {
+    match value_type {
+        ValueType::Number => TypeId::of::<f64>(),
+        ValueType::Vector => TypeId::of::<Vector2<f64>>(),
+    }
+}
+
+fn insert_default(cache: &mut Cache, value_type: ValueType) -> CacheIndex {
+    match value_type {
+        ValueType::Number => cache.insert(0.),
+        ValueType::Vector => cache.insert(Vector2::new(0., 0.)),
+    }
+}
+
+struct ExpressionBehavior {
+    /// The formula [expression] or [error] came from. None until the formula is first parsed.
+    parsed_formula: Option<String>,
+    expression: Option<Expression>,
+    /// Kept separately as they're still known if the formula can't be parsed.
+    variables: Vec<(String, ValueType)>,
+    error: Option<String>,
+}
+
+impl ExpressionBehavior {
+    fn new() -> ExpressionBehavior {
+        ExpressionBehavior {
+            parsed_formula: None,
+            expression: None,
+            variables: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Parses the formula if it has changed since it was last parsed.
+    fn parse(&mut self, ports: &[Port], cache: &Cache) {
+        let formula = match cache.get::<String>(ports[FORMULA].get_cache_index()) {
+            Some(formula) => formula,
+            None => return,
+        };
+        if self.parsed_formula.as_ref() == Some(formula) {
+            return;
+        }
+        match Expression::parse(formula, RESERVED_NAMES) {
+            Ok(expression) => {
+                self.variables = expression.get_variables().clone();
+                self.expression = Some(expression);
+                self.error = None;
+            }
+            Err(error) => {
+                self.variables = expression::find_variables(formula, RESERVED_NAMES);
+                self.expression = None;
+                self.error = Some(error.to_string());
+            }
+        }
+        self.parsed_formula = Some(formula.clone());
+    }
+
+    /// The type of the result port. Stays the same while the formula can't be parsed.
+    fn result_type(&self, ports: &[Port]) -> ValueType {
+        match &self.expression {
+            Some(expression) => expression.get_result_type(),
+            None if ports[RESULT].get_type_id() == &type_id(ValueType::Vector) => ValueType::Vector,
+            None => ValueType::Number,
+        }
+    }
+
+    /// Returns true if [ports] are the ones the formula needs.
+    fn matches_ports(&self, ports: &[Port]) -> bool {
+        ports[RESULT].get_type_id() == &type_id(self.result_type(ports))
+            && ports.len() == FIRST_VARIABLE + self.variables.len()
+            && ports[FIRST_VARIABLE..]
+                .iter()
+                .zip(&self.variables)
+                .all(|(port, (name, value_type))| port.get_name() == name && port.get_type_id() == &type_id(*value_type))
+    }
+}
+
+impl NodeBehavior for ExpressionBehavior {
+    /// Puts the result back to zero, keeping its type.
+    fn init(&mut self, ports: &Vec<Port>, cache: &mut Cache) {
+        let result = ports[RESULT].get_cache_index();
+        if result.is_type::<f64>() {
+            cache.set(result, 0.);
+        } else {
+            cache.set(result, Vector2::new(0., 0.));
+        }
+    }
+
+    fn compute(&mut self, ports: &Vec<Port>, cache: &mut Cache, _context: &FrameContext) {
+        // The formula is normally parsed by refresh already, unless it was changed in the Cache directly.
+        self.parse(ports, cache);
+        let expression = match &self.expression {
+            Some(expression) if self.matches_ports(ports) => expression,
+            _ => return,
+        };
+
+        let variables: Vec<Value> = self
+            .variables
+            .iter()
+            .enumerate()
+            .map(|(index, (_, value_type))| {
+                let cache_index = ports[FIRST_VARIABLE + index].get_cache_index();
+                match value_type {
+                    ValueType::Number => Value::Number(cache.get::<f64>(cache_index).copied().unwrap_or(0.)),
+                    ValueType::Vector => Value::Vector(
+                        cache
+                            .get::<Vector2<f64>>(cache_index)
+                            .copied()
+                            .unwrap_or_else(Vector2::zeros),
+                    ),
+                }
+            })
+            .collect();
+
+        match expression.evaluate(&variables) {
+            Value::Number(number) => cache.set(ports[RESULT].get_cache_index(), number),
+            Value::Vector(vector) => cache.set(ports[RESULT].get_cache_index(), vector),
+        }
+    }
+
+    fn refresh(&mut self, ports: &Vec<Port>, cache: &Cache) -> bool {
+        self.parse(ports, cache);
+        !self.matches_ports(ports)
+    }
+
+    /// Keeps the ports of variables which are still in the formula with the same type, so that their values stay.
+    fn update_ports(&mut self, ports: &mut Vec<Port>, cache: &mut Cache) {
+        let result_type = self.result_type(ports);
+        let mut old_ports: Vec<Port> = ports.drain(RESULT..).collect();
+        let result = old_ports.remove(0);
+        if result.get_type_id() == &type_id(result_type) {
+            ports.push(result);
+        } else {
+            cache.remove_any(result.get_default_cache_index());
+            ports.push(Port::new("Result", insert_default(cache, result_type), Direction::Output));
+        }
+
+        for (name, value_type) in &self.variables {
+            let old_port = old_ports
+                .iter()
+                .position(|port| port.get_name() == name && port.get_type_id() == &type_id(*value_type));
+            match old_port {
+                Some(index) => ports.push(old_ports.remove(index)),
+                None => ports.push(Port::new(name.clone(), insert_default(cache, *value_type), Direction::Input)),
+            }
+        }
+
+        for port in old_ports {
+            cache.remove_any(port.get_default_cache_index());
+        }
+    }
+
+    fn get_error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+#[cfg(feature = "gui")]
+pub fn widget_factory(node: NodeId) -> Box<dyn Widget<Rc<RefCell<App>>>> {
+    node_frame(ExpressionWidget::new(node))
+}
+
+/// Everything shown on the node which can change along with the formula.
+#[cfg(feature = "gui")]
+#[derive(PartialEq)]
+struct Layout {
+    ports: Vec<(String, TypeId)>,
+    error: Option<String>,
+}
+
+/// Rebuilds its contents whenever the node's ports or error change. The formula is only applied when enter is pressed
+/// or the text box loses focus, so that the ports don't change (and edges don't get dropped) halfway through typing.
+#[cfg(feature = "gui")]
+struct ExpressionWidget {
+    node: NodeId,
+    inner: WidgetPod<Rc<RefCell<App>>, Box<dyn Widget<Rc<RefCell<App>>>>>,
+    layout: Option<Layout>,
+}
+
+#[cfg(feature = "gui")]
+impl ExpressionWidget {
+    fn new(node: NodeId) -> ExpressionWidget {
+        ExpressionWidget {
+            node,
+            inner: WidgetPod::new(Box::new(Flex::column())),
+            layout: None,
+        }
+    }
+
+    fn current_layout(&self, app: &App) -> Option<Layout> {
+        let node = app.get_node(self.node)?;
+        Some(Layout {
+            ports: node
+                .get_ports()
+                .iter()
+                .map(|port| (port.get_name().to_string(), *port.get_type_id()))
+                .collect(),
+            error: node.get_error().map(str::to_string),
+        })
+    }
+
+    /// Returns true if the contents were rebuilt.
+    fn rebuild_if_changed(&mut self, app: &App) -> bool {
+        let layout = self.current_layout(app);
+        if layout.is_none() || layout == self.layout {
+            return false;
+        }
+        let layout = layout.unwrap();
+
+        let mut column = Flex::column()
+            .with_child(Label::new(NAME))
+            .with_spacer(5.)
+            .with_child(
+                TextBox::new()
+                    .with_formatter(ParseFormatter::<String>::new())
+                    .lens(CacheLens::<String>::new(self.node, FORMULA))
+                    .expand_width(),
+            );
+        if let Some(error) = &layout.error {
+            column.add_spacer(5.);
+            column.add_child(
+                Label::new(error.clone())
+                    .with_text_color(Color::rgb8(255, 100, 100))
+                    .with_line_break_mode(LineBreaking::WordWrap),
+            );
+        }
+        column.add_spacer(5.);
+        column.add_child(port_list(
+            self.node,
+            layout
+                .ports
+                .iter()
+                .enumerate()
+                .skip(RESULT)
+                .map(|(index, (name, type_id))| {
+                    let direction = if index == RESULT { Direction::Output } else { Direction::Input };
+                    PortDescription::with_type_id(name.clone(), index, direction, *type_id)
+                })
+                .collect(),
+        ));
+
+        self.inner = WidgetPod::new(Box::new(column));
+        self.layout = Some(layout);
+        true
+    }
+}
+
+#[cfg(feature = "gui")]
+impl Widget<Rc<RefCell<App>>> for ExpressionWidget {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Rc<RefCell<App>>, env: &Env) {
+        self.inner.event(ctx, event, data, env);
+        // The formula may have been changed by the text box, an undo or loading.
+        if self.rebuild_if_changed(&data.borrow()) {
+            ctx.children_changed();
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &Rc<RefCell<App>>, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.rebuild_if_changed(&data.borrow());
+        }
+        self.inner.lifecycle(ctx, event, data, env);
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &Rc<RefCell<App>>, data: &Rc<RefCell<App>>, env: &Env) {
+        self.inner.update(ctx, data, env);
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &Rc<RefCell<App>>, env: &Env) -> Size {
+        let size = self.inner.layout(ctx, bc, data, env);
+        self.inner.set_origin(ctx, data, env, Point::ZERO);
+        size
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &Rc<RefCell<App>>, env: &Env) {
+        self.inner.paint(ctx, data, env);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes;
+
+    fn port_names(app: &crate::core::App, node_id: crate::core::NodeId) -> Vec<&str> {
+        app.get_node(node_id).unwrap().get_ports().iter().map(|port| port.get_name()).collect()
+    }
+
+    #[test]
+    fn variables_follow_formula() {
+        let mut app = nodes::new_app();
+        let node = app.add_node(NAME);
+        app.set_value(node, FORMULA, String::from("a * B"));
+        assert_eq!(port_names(&app, node), ["Formula", "Result", "a", "B"]);
+        app.set_value(node, FIRST_VARIABLE, 2.);
+        app.set_value(node, FIRST_VARIABLE + 1, Vector2::new(1., 3.));
+        app.compute();
+
+        let result = app.get_node(node).unwrap().get_ports()[RESULT].get_cache_index();
+        assert_eq!(app.get_cache().get::<Vector2<f64>>(result), Some(&Vector2::new(2., 6.)));
+    }
+
+    #[test]
+    fn port_names_are_reserved() {
+        let mut app = nodes::new_app();
+        let node = app.add_node(NAME);
+        app.set_value(node, FORMULA, String::from("Result + x"));
+
+        // No variable port shares a name with the fixed ports, so edges can't be reconnected to the wrong one.
+        assert_eq!(port_names(&app, node), ["Formula", "Result", "x"]);
+        assert_eq!(
+            app.get_node(node).unwrap().get_error(),
+            Some("'Result' can't be used as a variable name (at character 1)")
+        );
+    }
+}
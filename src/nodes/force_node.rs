@@ -0,0 +1,204 @@
+//! Nodes which generate [ForceField]s. Fields can be added together with "Combine Forces" and are applied to
+//! particles through their "Force Field" input.
+
+/// Attractors, repellers and vortices share their inputs. [negate] flips the strength's sign.
+macro_rules! define_point_force {
+    ($module:ident, $name:literal, $variant:ident, negate: $negate:literal) => {
+        pub mod $module {
+            use nalgebra::Vector2;
+
+            use crate::nodes::common::{Force, ForceField};
+
+            define_node! {
+                name: $name,
+                inputs: {
+                    POSITION(position): Vector2<f64> = Vector2::new(0., 0.) => "Position",
+                    STRENGTH(strength): f64 = 100. => "Strength" [0., 1000.],
+                    RADIUS(radius): f64 = 10. => "Radius" [0., 200.],
+                    FALLOFF(falloff): f64 = 2. => "Falloff" [0., 4.],
+                },
+                outputs: {
+                    FORCE_FIELD(force_field): ForceField = ForceField::new() => "Force Field",
+                },
+                compute(_context) {
+                    force_field = ForceField::from_force(Force::$variant {
+                        position,
+                        strength: if $negate { -strength } else { strength },
+                        radius,
+                        falloff,
+                    });
+                }
+            }
+        }
+    };
+}
+
+pub mod gravity {
+    use nalgebra::Vector2;
+
+    use crate::nodes::common::{Force, ForceField};
+
+    define_node! {
+        name: "Gravity",
+        inputs: {
+            ACCELERATION(acceleration): Vector2<f64> = Vector2::new(0., 100.) => "Acceleration",
+        },
+        outputs: {
+            FORCE_FIELD(force_field): ForceField = ForceField::new() => "Force Field",
+        },
+        compute(_context) {
+            force_field = ForceField::from_force(Force::Gravity(acceleration));
+        }
+    }
+}
+
+pub mod drag {
+    use crate::nodes::common::{Force, ForceField};
+
+    define_node! {
+        name: "Drag",
+        inputs: {
+            LINEAR(linear): f64 = 0.5 => "Linear" [0., 5.],
+            QUADRATIC(quadratic): f64 = 0. => "Quadratic" [0., 1.],
+        },
+        outputs: {
+            FORCE_FIELD(force_field): ForceField = ForceField::new() => "Force Field",
+        },
+        compute(_context) {
+            force_field = ForceField::from_force(Force::Drag { linear, quadratic });
+        }
+    }
+}
+
+define_point_force!(attractor, "Attractor", Attractor, negate: false);
+// A repeller is just an attractor with a negative strength, but is easier to find under its own name.
+define_point_force!(repeller, "Repeller", Attractor, negate: true);
+define_point_force!(vortex, "Vortex", Vortex, negate: false);
+
+pub mod wind {
+    use nalgebra::Vector2;
+
+    use crate::nodes::common::{random, Force, ForceField};
+
+    define_node! {
+        name: "Wind",
+        // The turbulence changes over time.
+        internal_state: true,
+        inputs: {
+            DIRECTION(direction): Vector2<f64> = Vector2::new(20., 0.) => "Direction",
+            TURBULENCE(turbulence): f64 = 20. => "Turbulence" [0., 200.],
+            SCALE(scale): f64 = 0.01 => "Scale" [0., 0.1],
+            SPEED(speed): f64 = 0.5 => "Speed" [0., 5.],
+            SEED(seed): f64 = 0. => "Seed" [0., 100.],
+        },
+        outputs: {
+            FORCE_FIELD(force_field): ForceField = ForceField::new() => "Force Field",
+        },
+        compute(context) {
+            force_field = ForceField::from_force(Force::Wind {
+                direction,
+                turbulence,
+                scale,
+                time: context.time * speed,
+                seed: random::hash(&[context.seed, random::seed_from_f64(seed)]),
+            });
+        }
+    }
+}
+
+pub mod combine {
+    use crate::nodes::common::ForceField;
+
+    define_node! {
+        name: "Combine Forces",
+        inputs: {
+            A(a): ForceField = ForceField::new() => "A",
+            B(b): ForceField = ForceField::new() => "B",
+        },
+        outputs: {
+            FORCE_FIELD(force_field): ForceField = ForceField::new() => "Force Field",
+        },
+        compute(_context) {
+            force_field = a.combine(&b);
+        }
+    }
+}
+
+pub mod sample {
+    use nalgebra::Vector2;
+
+    use crate::nodes::common::ForceField;
+
+    define_node! {
+        name: "Sample Force Field",
+        inputs: {
+            FORCE_FIELD(force_field): ForceField = ForceField::new() => "Force Field",
+            POSITION(position): Vector2<f64> = Vector2::new(0., 0.) => "Position",
+            VELOCITY(velocity): Vector2<f64> = Vector2::new(0., 0.) => "Velocity",
+            MASS(mass): f64 = 1. => "Mass",
+        },
+        outputs: {
+            FORCE(force): Vector2<f64> = Vector2::new(0., 0.) => "Force",
+        },
+        compute(_context) {
+            force = force_field.force_at(&position, &velocity, mass);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+
+    use super::*;
+    use crate::{
+        core::{App, NodeId},
+        nodes,
+    };
+
+    // Attractor ports.
+    const POSITION: usize = 0;
+    const STRENGTH: usize = 1;
+    const FORCE_FIELD: usize = 4;
+    // Gravity ports.
+    const ACCELERATION: usize = 0;
+    const GRAVITY_FORCE_FIELD: usize = 1;
+    // Combine Forces ports.
+    const A: usize = 0;
+    const B: usize = 1;
+    const COMBINED_FORCE_FIELD: usize = 2;
+    // Sample Force Field ports.
+    const SAMPLED_FORCE_FIELD: usize = 0;
+    const SAMPLE_POSITION: usize = 1;
+    const MASS: usize = 3;
+    const FORCE: usize = 4;
+
+    fn sampled_force(app: &App, sample: NodeId) -> Vector2<f64> {
+        let port = &app.get_node(sample).unwrap().get_ports()[FORCE];
+        *app.get_cache().get::<Vector2<f64>>(port.get_cache_index()).unwrap()
+    }
+
+    #[test]
+    fn combined_forces_add_up() {
+        let mut app = nodes::new_app();
+        let attractor = app.add_node(attractor::NAME);
+        app.set_value(attractor, POSITION, Vector2::new(100., 0.));
+        app.set_value(attractor, STRENGTH, 50.);
+        let gravity = app.add_node(gravity::NAME);
+        app.set_value(gravity, ACCELERATION, Vector2::new(0., 10.));
+        let combine = app.add_node(combine::NAME);
+        app.add_edge(attractor, FORCE_FIELD, combine, A).unwrap();
+        app.add_edge(gravity, GRAVITY_FORCE_FIELD, combine, B).unwrap();
+        let sample = app.add_node(sample::NAME);
+        app.add_edge(combine, COMBINED_FORCE_FIELD, sample, SAMPLED_FORCE_FIELD).unwrap();
+        app.set_value(sample, MASS, 2.);
+
+        // Within the attractor's radius the full strength pulls towards it, beyond it the pull falls off.
+        app.set_value(sample, SAMPLE_POSITION, Vector2::new(95., 0.));
+        app.compute();
+        assert_eq!(sampled_force(&app, sample), Vector2::new(100., 20.));
+        app.set_value(sample, SAMPLE_POSITION, Vector2::new(120., 0.));
+        app.compute();
+        assert_eq!(sampled_force(&app, sample), Vector2::new(-25., 20.));
+    }
+}
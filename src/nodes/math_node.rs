@@ -0,0 +1,238 @@
+//! Arithmetic on [f64]s. Operations which aren't defined for some inputs (e.g. dividing by zero) output 0 instead of
+//! infinity or NaN, as those would otherwise spread through the rest of the graph.
+
+/// Defines a node with inputs `A` and `B` and a single `Result` output, in its own module.
+macro_rules! define_binary {
+    ($module:ident, $name:literal, $a_default:expr, $b_default:expr, |$a:ident, $b:ident| $operation:expr) => {
+        pub mod $module {
+            define_node! {
+                name: $name,
+                inputs: {
+                    A($a): f64 = $a_default => "A",
+                    B($b): f64 = $b_default => "B",
+                },
+                outputs: {
+                    RESULT(result): f64 = 0. => "Result",
+                },
+                compute(_context) {
+                    result = $operation;
+                }
+            }
+        }
+    };
+}
+
+/// Defines a node with a single `Value` input and a single `Result` output, in its own module.
+macro_rules! define_unary {
+    ($module:ident, $name:literal, |$value:ident| $operation:expr) => {
+        pub mod $module {
+            define_node! {
+                name: $name,
+                inputs: {
+                    VALUE($value): f64 = 0. => "Value",
+                },
+                outputs: {
+                    RESULT(result): f64 = 0. => "Result",
+                },
+                compute(_context) {
+                    result = $operation;
+                }
+            }
+        }
+    };
+}
+
+define_binary!(add, "Add", 0., 0., |a, b| a + b);
+define_binary!(subtract, "Subtract", 0., 0., |a, b| a - b);
+define_binary!(multiply, "Multiply", 1., 1., |a, b| a * b);
+define_binary!(divide, "Divide", 1., 1., |a, b| if b == 0. { 0. } else { a / b });
+define_binary!(modulo, "Modulo", 0., 1., |a, b| if b == 0. { 0. } else { a.rem_euclid(b) });
+define_binary!(power, "Power", 1., 1., |a, b| super::finite_or_zero(a.powf(b)));
+define_binary!(min, "Min", 0., 0., |a, b| a.min(b));
+define_binary!(max, "Max", 0., 0., |a, b| a.max(b));
+
+define_unary!(abs, "Abs", |value| value.abs());
+define_unary!(floor, "Floor", |value| value.floor());
+define_unary!(sin, "Sin", |value| super::finite_or_zero(value.sin()));
+define_unary!(cos, "Cos", |value| super::finite_or_zero(value.cos()));
+define_unary!(tan, "Tan", |value| super::finite_or_zero(value.tan()));
+
+pub mod clamp {
+    define_node! {
+        name: "Clamp",
+        inputs: {
+            VALUE(value): f64 = 0. => "Value",
+            MIN(min): f64 = 0. => "Min",
+            MAX(max): f64 = 1. => "Max",
+        },
+        outputs: {
+            RESULT(result): f64 = 0. => "Result",
+        },
+        compute(_context) {
+            // Unlike f64::clamp, doesn't panic if min > max.
+            result = value.max(min).min(max);
+        }
+    }
+}
+
+pub mod atan2 {
+    define_node! {
+        name: "Atan2",
+        inputs: {
+            Y(y): f64 = 0. => "Y",
+            X(x): f64 = 1. => "X",
+        },
+        outputs: {
+            ANGLE(angle): f64 = 0. => "Angle",
+        },
+        compute(_context) {
+            angle = y.atan2(x);
+        }
+    }
+}
+
+pub mod lerp {
+    define_node! {
+        name: "Lerp",
+        inputs: {
+            A(a): f64 = 0. => "A",
+            B(b): f64 = 1. => "B",
+            T(t): f64 = 0.5 => "T",
+        },
+        outputs: {
+            RESULT(result): f64 = 0. => "Result",
+        },
+        compute(_context) {
+            result = super::lerp(a, b, t);
+        }
+    }
+}
+
+/// Maps `Value` from the range `In Min` to `In Max` onto `Out Min` to `Out Max`, without clamping.
+pub mod remap {
+    define_node! {
+        name: "Remap",
+        inputs: {
+            VALUE(value): f64 = 0. => "Value",
+            IN_MIN(in_min): f64 = 0. => "In Min",
+            IN_MAX(in_max): f64 = 1. => "In Max",
+            OUT_MIN(out_min): f64 = 0. => "Out Min",
+            OUT_MAX(out_max): f64 = 1. => "Out Max",
+        },
+        outputs: {
+            RESULT(result): f64 = 0. => "Result",
+        },
+        compute(_context) {
+            result = if in_min == in_max {
+                out_min
+            } else {
+                super::lerp(out_min, out_max, (value - in_min) / (in_max - in_min))
+            };
+        }
+    }
+}
+
+pub mod smoothstep {
+    define_node! {
+        name: "Smoothstep",
+        inputs: {
+            EDGE_0(edge_0): f64 = 0. => "Edge 0",
+            EDGE_1(edge_1): f64 = 1. => "Edge 1",
+            VALUE(value): f64 = 0. => "Value",
+        },
+        outputs: {
+            RESULT(result): f64 = 0. => "Result",
+        },
+        compute(_context) {
+            result = super::smoothstep(edge_0, edge_1, value);
+        }
+    }
+}
+
+pub fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// Hermite interpolation between 0 and 1 as [value] goes from [edge_0] to [edge_1]. A step if the edges are equal.
+pub fn smoothstep(edge_0: f64, edge_1: f64, value: f64) -> f64 {
+    if edge_0 == edge_1 {
+        return if value < edge_0 { 0. } else { 1. };
+    }
+    let t = ((value - edge_0) / (edge_1 - edge_0)).clamp(0., 1.);
+    t * t * (3. - 2. * t)
+}
+
+fn finite_or_zero(value: f64) -> f64 {
+    if value.is_finite() {
+        value
+    } else {
+        0.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nodes;
+
+    /// Computes a fresh node of type [name] with [inputs] set in port order, returning its first output.
+    fn compute(name: &'static str, inputs: &[f64]) -> f64 {
+        let mut app = nodes::new_app();
+        let node = app.add_node(name);
+        for (port, value) in inputs.iter().enumerate() {
+            app.set_value(node, port, *value);
+        }
+        app.compute();
+        let port = &app.get_node(node).unwrap().get_ports()[inputs.len()];
+        *app.get_cache().get::<f64>(port.get_cache_index()).unwrap()
+    }
+
+    #[test]
+    fn division_by_zero_is_zero() {
+        assert_eq!(compute(super::divide::NAME, &[6., 3.]), 2.);
+        assert_eq!(compute(super::divide::NAME, &[1., 0.]), 0.);
+        assert_eq!(compute(super::modulo::NAME, &[-1., 3.]), 2.);
+        assert_eq!(compute(super::modulo::NAME, &[1., 0.]), 0.);
+    }
+
+    #[test]
+    fn overflow_and_nan_are_zero() {
+        assert_eq!(compute(super::power::NAME, &[2., 10.]), 1024.);
+        assert_eq!(compute(super::power::NAME, &[10., 400.]), 0.);
+        assert_eq!(compute(super::power::NAME, &[-8., 1. / 3.]), 0.);
+        assert_eq!(compute(super::power::NAME, &[0., -1.]), 0.);
+        for name in [super::sin::NAME, super::cos::NAME, super::tan::NAME] {
+            assert_eq!(compute(name, &[f64::INFINITY]), 0., "{}", name);
+            assert_eq!(compute(name, &[f64::NEG_INFINITY]), 0., "{}", name);
+        }
+        assert!(compute(super::tan::NAME, &[std::f64::consts::FRAC_PI_2]).is_finite());
+    }
+
+    #[test]
+    fn clamp_with_min_above_max_uses_max() {
+        assert_eq!(compute(super::clamp::NAME, &[5., 0., 1.]), 1.);
+        assert_eq!(compute(super::clamp::NAME, &[-5., 0., 1.]), 0.);
+        assert_eq!(compute(super::clamp::NAME, &[0.5, 2., 1.]), 1.);
+    }
+
+    #[test]
+    fn remap_with_empty_source_range_is_out_min() {
+        assert_eq!(compute(super::remap::NAME, &[5., 0., 10., 100., 200.]), 150.);
+        assert_eq!(compute(super::remap::NAME, &[20., 0., 10., 100., 200.]), 300.);
+        assert_eq!(compute(super::remap::NAME, &[5., 3., 3., 100., 200.]), 100.);
+    }
+
+    #[test]
+    fn smoothstep_edges() {
+        assert_eq!(super::smoothstep(0., 1., -1.), 0.);
+        assert_eq!(super::smoothstep(0., 1., 0.), 0.);
+        assert_eq!(super::smoothstep(0., 1., 0.5), 0.5);
+        assert_eq!(super::smoothstep(0., 1., 1.), 1.);
+        assert_eq!(super::smoothstep(0., 1., 2.), 1.);
+        // Reversed edges fall instead of rising.
+        assert_eq!(super::smoothstep(1., 0., 0.25), super::smoothstep(0., 1., 0.75));
+        // Equal edges make a step.
+        assert_eq!(super::smoothstep(2., 2., 1.9), 0.);
+        assert_eq!(super::smoothstep(2., 2., 2.), 1.);
+        assert_eq!(compute(super::smoothstep::NAME, &[0., 2., 1.]), 0.5);
+    }
+}
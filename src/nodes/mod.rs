@@ -1,29 +1,299 @@
-mod circle_node;
+#[macro_use]
+mod define_node;
+
+pub mod boids_node;
+pub mod bounds_node;
+pub mod circle_node;
 pub mod common;
-mod particle_node;
-mod value_node;
-mod vector_node;
+pub mod emitter_node;
+pub mod expression_node;
+pub mod force_node;
+pub mod math_node;
+pub mod noise_node;
+pub mod oscillator_node;
+pub mod particle_node;
+pub mod random_node;
+pub mod spring_node;
+pub mod time_node;
+pub mod value_node;
+pub mod vector_math_node;
+pub mod vector_node;
 
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::collections::HashMap;
+#[cfg(feature = "gui")]
+use std::{cell::RefCell, rc::Rc};
 
+#[cfg(feature = "gui")]
 use druid::Widget;
+use nalgebra::Vector2;
+
+use crate::core::{App, Cache, Converter, FeedbackType, Migration, Node, TypeName, ValueSerializer};
+#[cfg(feature = "gui")]
+use crate::core::NodeId;
+
+use self::common::{shapes::Circle, Bounds, Force, ForceField, Particle, ParticleSystem};
+
+/// Inserts every node type defined with [define_node] into [factories], using the function named [factory] from its
+/// module. Add new node modules here.
+macro_rules! insert_defined_nodes {
+    ($factories:ident, $factory:ident) => {
+        insert_factories!($factories, $factory;
+            vector_node,
+            circle_node,
+            time_node,
+            oscillator_node::sine,
+            oscillator_node::triangle,
+            oscillator_node::saw,
+            oscillator_node::square,
+            math_node::add,
+            math_node::subtract,
+            math_node::multiply,
+            math_node::divide,
+            math_node::modulo,
+            math_node::power,
+            math_node::min,
+            math_node::max,
+            math_node::clamp,
+            math_node::abs,
+            math_node::floor,
+            math_node::sin,
+            math_node::cos,
+            math_node::tan,
+            math_node::atan2,
+            math_node::lerp,
+            math_node::remap,
+            math_node::smoothstep,
+            vector_math_node::split,
+            vector_math_node::add,
+            vector_math_node::subtract,
+            vector_math_node::scale,
+            vector_math_node::dot,
+            vector_math_node::cross,
+            vector_math_node::length,
+            vector_math_node::normalize,
+            vector_math_node::rotate,
+            vector_math_node::from_polar,
+            vector_math_node::distance,
+            vector_math_node::lerp,
+            vector_math_node::limit_length,
+            random_node,
+            noise_node::perlin_1d,
+            noise_node::perlin_2d,
+            noise_node::perlin_3d,
+            noise_node::simplex_1d,
+            noise_node::simplex_2d,
+            noise_node::simplex_3d,
+            noise_node::value_1d,
+            noise_node::value_2d,
+            noise_node::value_3d,
+            force_node::gravity,
+            force_node::drag,
+            force_node::attractor,
+            force_node::repeller,
+            force_node::vortex,
+            force_node::wind,
+            force_node::combine,
+            force_node::sample,
+            spring_node,
+            bounds_node::rectangle,
+            bounds_node::circle,
+            boids_node,
+        );
+    };
+}
 
-use crate::core::{App, Cache, Node};
+macro_rules! insert_factories {
+    ($factories:ident, $factory:ident; $($($module:ident)::+),* $(,)?) => {
+        $($factories.insert($($module)::+::NAME, $($module)::+::$factory);)*
+    };
+}
+
+/// Creates an [App] with every node type, converter etc. registered.
+pub fn new_app() -> App {
+    App::new()
+        .with_factories(node_factories())
+        .with_feedback_types(feedback_types())
+        .with_converters(converters())
+        .with_serializers(serializers())
+        .with_type_names(type_names())
+        .with_migrations(migrations())
+}
 
-pub fn node_widget_factories() -> HashMap<&'static str, fn(index: usize) -> Box<dyn Widget<Rc<RefCell<App>>>>> {
-    let mut factories = HashMap::<&str, fn(index: usize) -> Box<dyn Widget<Rc<RefCell<App>>>>>::new();
+#[cfg(feature = "gui")]
+pub fn node_widget_factories() -> HashMap<&'static str, fn(index: NodeId) -> Box<dyn Widget<Rc<RefCell<App>>>>> {
+    let mut factories = HashMap::<&str, fn(index: NodeId) -> Box<dyn Widget<Rc<RefCell<App>>>>>::new();
     factories.insert("Value", value_node::widget_factory);
-    factories.insert("Vector2D", vector_node::widget_factory);
     factories.insert("Particle", particle_node::widget_factory);
-    factories.insert("Circle", circle_node::widget_factory);
+    factories.insert(expression_node::NAME, expression_node::widget_factory);
+    factories.insert(emitter_node::NAME, emitter_node::widget_factory);
+    insert_defined_nodes!(factories, widget_factory);
     factories
 }
 
 pub fn node_factories() -> HashMap<&'static str, fn(&mut Cache) -> Node> {
     let mut factories = HashMap::<&'static str, fn(&mut Cache) -> Node>::new();
     factories.insert("Value", value_node::node_factory);
-    factories.insert("Vector2D", vector_node::node_factory);
     factories.insert("Particle", particle_node::node_factory);
-    factories.insert("Circle", circle_node::node_factory);
+    factories.insert(expression_node::NAME, expression_node::node_factory);
+    factories.insert(emitter_node::NAME, emitter_node::node_factory);
+    insert_defined_nodes!(factories, node_factory);
     factories
 }
+
+/// How node types are grouped in the graph editor's menu. Every key of [node_factories] should be listed once.
+pub fn node_categories() -> Vec<(&'static str, Vec<&'static str>)> {
+    vec![
+        ("Values", vec!["Value", vector_node::NAME]),
+        (
+            "Time",
+            vec![
+                time_node::NAME,
+                oscillator_node::sine::NAME,
+                oscillator_node::triangle::NAME,
+                oscillator_node::saw::NAME,
+                oscillator_node::square::NAME,
+            ],
+        ),
+        (
+            "Math",
+            vec![
+                expression_node::NAME,
+                math_node::add::NAME,
+                math_node::subtract::NAME,
+                math_node::multiply::NAME,
+                math_node::divide::NAME,
+                math_node::modulo::NAME,
+                math_node::power::NAME,
+                math_node::min::NAME,
+                math_node::max::NAME,
+                math_node::clamp::NAME,
+                math_node::abs::NAME,
+                math_node::floor::NAME,
+                math_node::lerp::NAME,
+                math_node::remap::NAME,
+                math_node::smoothstep::NAME,
+            ],
+        ),
+        (
+            "Vector Math",
+            vec![
+                vector_math_node::split::NAME,
+                vector_math_node::add::NAME,
+                vector_math_node::subtract::NAME,
+                vector_math_node::scale::NAME,
+                vector_math_node::dot::NAME,
+                vector_math_node::cross::NAME,
+                vector_math_node::length::NAME,
+                vector_math_node::normalize::NAME,
+                vector_math_node::rotate::NAME,
+                vector_math_node::from_polar::NAME,
+                vector_math_node::distance::NAME,
+                vector_math_node::lerp::NAME,
+                vector_math_node::limit_length::NAME,
+            ],
+        ),
+        (
+            "Trigonometry",
+            vec![
+                math_node::sin::NAME,
+                math_node::cos::NAME,
+                math_node::tan::NAME,
+                math_node::atan2::NAME,
+            ],
+        ),
+        (
+            "Random",
+            vec![
+                random_node::NAME,
+                noise_node::perlin_1d::NAME,
+                noise_node::perlin_2d::NAME,
+                noise_node::perlin_3d::NAME,
+                noise_node::simplex_1d::NAME,
+                noise_node::simplex_2d::NAME,
+                noise_node::simplex_3d::NAME,
+                noise_node::value_1d::NAME,
+                noise_node::value_2d::NAME,
+                noise_node::value_3d::NAME,
+            ],
+        ),
+        (
+            "Physics",
+            vec![
+                "Particle",
+                emitter_node::NAME,
+                spring_node::NAME,
+                bounds_node::rectangle::NAME,
+                bounds_node::circle::NAME,
+                boids_node::NAME,
+            ],
+        ),
+        (
+            "Forces",
+            vec![
+                force_node::gravity::NAME,
+                force_node::drag::NAME,
+                force_node::attractor::NAME,
+                force_node::repeller::NAME,
+                force_node::vortex::NAME,
+                force_node::wind::NAME,
+                force_node::combine::NAME,
+                force_node::sample::NAME,
+            ],
+        ),
+        ("Shapes", vec![circle_node::NAME]),
+    ]
+}
+
+/// Every type a node can output should be listed here, otherwise its outputs can't be part of a feedback loop.
+pub fn feedback_types() -> Vec<FeedbackType> {
+    vec![
+        FeedbackType::new::<f64>(),
+        FeedbackType::new::<bool>(),
+        FeedbackType::new::<String>(),
+        FeedbackType::new::<Vector2<f64>>(),
+        FeedbackType::new::<Circle>(),
+        FeedbackType::new::<Particle>(),
+        FeedbackType::new::<ParticleSystem>(),
+        FeedbackType::new::<ForceField>(),
+        FeedbackType::new::<Bounds>(),
+    ]
+}
+
+pub fn converters() -> Vec<Converter> {
+    vec![
+        Converter::new(|value: &f64| Vector2::new(*value, *value)),
+        Converter::new(|vector: &Vector2<f64>| vector.norm()),
+        Converter::new(|value: &bool| if *value { 1. } else { 0. }),
+        Converter::new(|particle: &Particle| *particle.get_position()),
+        Converter::new(|force: &Vector2<f64>| ForceField::from_force(Force::Constant(*force))),
+    ]
+}
+
+pub fn serializers() -> Vec<ValueSerializer> {
+    vec![
+        ValueSerializer::new::<f64>(),
+        ValueSerializer::new::<bool>(),
+        ValueSerializer::new::<String>(),
+        ValueSerializer::with_conversion(|vector: &Vector2<f64>| [vector.x, vector.y], |[x, y]: [f64; 2]| Vector2::new(x, y)),
+    ]
+}
+
+/// How the types of ports are shown to the user, e.g. when an edge can't be added.
+pub fn type_names() -> Vec<TypeName> {
+    vec![
+        TypeName::new::<f64>("Number"),
+        TypeName::new::<bool>("Boolean"),
+        TypeName::new::<String>("Text"),
+        TypeName::new::<Vector2<f64>>("Vector"),
+        TypeName::new::<Circle>("Circle"),
+        TypeName::new::<Particle>("Particle"),
+        TypeName::new::<ParticleSystem>("Particles"),
+        TypeName::new::<ForceField>("Force Field"),
+        TypeName::new::<Bounds>("Bounds"),
+    ]
+}
+
+/// Add a migration here whenever a node's ports are renamed or reordered, so that older save files still load.
+pub fn migrations() -> Vec<Migration> {
+    vec![]
+}
@@ -0,0 +1,101 @@
+//! Coherent noise nodes, see [noise](super::common::noise). Each type of noise comes in 1D, 2D and 3D, where the 3D
+//! version is typically used to animate 2D noise by connecting time to `Z`. Every node outputs a value in [-1, 1],
+//! and a vector made of that value and a second, independent sample, e.g. for flow fields.
+
+/// Defines a noise node in its own module. The second argument is the inputs making up the sampled point, followed by
+/// the expressions for its coordinates.
+macro_rules! define_noise {
+    ($module:ident, $name:literal, $noise_type:ident, 1) => {
+        define_noise!(@node $module, $name, $noise_type, { X(x): f64 = 0. => "X", }, [x]);
+    };
+    ($module:ident, $name:literal, $noise_type:ident, 2) => {
+        define_noise!(
+            @node $module, $name, $noise_type,
+            { POSITION(position): Vector2<f64> = Vector2::new(0., 0.) => "Position", },
+            [position.x, position.y]
+        );
+    };
+    ($module:ident, $name:literal, $noise_type:ident, 3) => {
+        define_noise!(
+            @node $module, $name, $noise_type,
+            {
+                POSITION(position): Vector2<f64> = Vector2::new(0., 0.) => "Position",
+                Z(z): f64 = 0. => "Z",
+            },
+            [position.x, position.y, z]
+        );
+    };
+    (@node $module:ident, $name:literal, $noise_type:ident, { $($point_inputs:tt)* }, [$($coordinate:expr),*]) => {
+        pub mod $module {
+            #[allow(unused_imports)]
+            use nalgebra::Vector2;
+
+            use crate::nodes::common::{noise::{self, NoiseType}, random};
+
+            define_node! {
+                name: $name,
+                inputs: {
+                    $($point_inputs)*
+                    FREQUENCY(frequency): f64 = 1. => "Frequency" [0., 10.],
+                    OCTAVES(octaves): f64 = 1. => "Octaves" [1., 8.],
+                    LACUNARITY(lacunarity): f64 = 2. => "Lacunarity" [1., 4.],
+                    PERSISTENCE(persistence): f64 = 0.5 => "Persistence" [0., 1.],
+                    SEED(seed): f64 = 0. => "Seed" [0., 100.],
+                },
+                outputs: {
+                    VALUE(value): f64 = 0. => "Value",
+                    VECTOR(vector): Vector2<f64> = Vector2::new(0., 0.) => "Vector",
+                },
+                compute(context) {
+                    let point = [$($coordinate * frequency),*];
+                    let octaves = octaves.round().max(1.).min(16.) as u32;
+                    let seed = random::hash(&[context.seed, random::seed_from_f64(seed)]);
+                    let sample = |seed| {
+                        noise::fractal(NoiseType::$noise_type, seed, &point, octaves, lacunarity, persistence)
+                    };
+                    value = sample(seed);
+                    vector = Vector2::new(value, sample(random::hash(&[seed, 1])));
+                }
+            }
+        }
+    };
+}
+
+define_noise!(perlin_1d, "Perlin Noise 1D", Perlin, 1);
+define_noise!(perlin_2d, "Perlin Noise 2D", Perlin, 2);
+define_noise!(perlin_3d, "Perlin Noise 3D", Perlin, 3);
+define_noise!(simplex_1d, "Simplex Noise 1D", Simplex, 1);
+define_noise!(simplex_2d, "Simplex Noise 2D", Simplex, 2);
+define_noise!(simplex_3d, "Simplex Noise 3D", Simplex, 3);
+define_noise!(value_1d, "Value Noise 1D", Value, 1);
+define_noise!(value_2d, "Value Noise 2D", Value, 2);
+define_noise!(value_3d, "Value Noise 3D", Value, 3);
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+
+    use crate::nodes;
+
+    // Ports of the 2D noise nodes.
+    const POSITION: usize = 0;
+    const VALUE: usize = 6;
+
+    #[test]
+    fn graph_seed_changes_noise() {
+        for name in [super::perlin_2d::NAME, super::simplex_2d::NAME, super::value_2d::NAME] {
+            let mut app = nodes::new_app();
+            let node = app.add_node(name);
+            app.set_value(node, POSITION, Vector2::new(0.3, 0.7));
+            let mut sample = |seed| {
+                app.set_seed(seed);
+                app.compute();
+                let port = &app.get_node(node).unwrap().get_ports()[VALUE];
+                *app.get_cache().get::<f64>(port.get_cache_index()).unwrap()
+            };
+            let (first, second, first_again) = (sample(0), sample(1), sample(0));
+            assert_ne!(first, second, "{}", name);
+            assert_eq!(first, first_again, "{}", name);
+        }
+    }
+}
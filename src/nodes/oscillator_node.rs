@@ -0,0 +1,131 @@
+//! Periodic signals driven by the [App]'s clock, e.g. for animating a radius or a force. Every oscillator outputs
+//! `offset + amplitude * wave(time * frequency + phase)`, where `wave` has a period of 1 and a range of -1 to 1.
+//! Phase is measured in periods, not radians.
+
+use std::f64::consts::PI;
+
+/// Defines an oscillator node in its own module, as [define_node] needs one module per node type.
+macro_rules! define_oscillator {
+    ($module:ident, $name:literal, $wave:expr) => {
+        pub mod $module {
+            define_node! {
+                name: $name,
+                internal_state: true,
+                inputs: {
+                    FREQUENCY(frequency): f64 = 1. => "Frequency",
+                    PHASE(phase): f64 = 0. => "Phase",
+                    AMPLITUDE(amplitude): f64 = 1. => "Amplitude",
+                    OFFSET(offset): f64 = 0. => "Offset",
+                },
+                outputs: {
+                    VALUE(value): f64 = 0. => "Value",
+                },
+                compute(context) {
+                    let t = (context.time * frequency + phase).rem_euclid(1.);
+                    value = offset + amplitude * $wave(t);
+                }
+            }
+        }
+    };
+}
+
+define_oscillator!(sine, "Sine", super::sine_wave);
+define_oscillator!(triangle, "Triangle", super::triangle_wave);
+define_oscillator!(saw, "Saw", super::saw_wave);
+define_oscillator!(square, "Square", super::square_wave);
+
+// Each wave starts at 0 (or 1 for the square wave) and rises, so that they line up with each other.
+
+pub fn sine_wave(t: f64) -> f64 {
+    (2. * PI * t).sin()
+}
+
+pub fn triangle_wave(t: f64) -> f64 {
+    1. - 4. * ((t + 0.25).rem_euclid(1.) - 0.5).abs()
+}
+
+pub fn saw_wave(t: f64) -> f64 {
+    2. * (t + 0.5).rem_euclid(1.) - 1.
+}
+
+pub fn square_wave(t: f64) -> f64 {
+    if t.rem_euclid(1.) < 0.5 {
+        1.
+    } else {
+        -1.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{App, NodeId},
+        nodes,
+    };
+
+    // Ports of every oscillator node.
+    const PHASE: usize = 1;
+    const AMPLITUDE: usize = 2;
+    const OFFSET: usize = 3;
+    const VALUE: usize = 4;
+
+    type Wave = fn(f64) -> f64;
+
+    const WAVES: [(&str, Wave); 4] = [
+        ("sine", sine_wave),
+        ("triangle", triangle_wave),
+        ("saw", saw_wave),
+        ("square", square_wave),
+    ];
+
+    #[test]
+    fn waves_have_period_one_and_range_one() {
+        for (name, wave) in WAVES {
+            let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+            for step in 0..1000 {
+                let t = step as f64 / 1000. + 0.0005;
+                let value = wave(t);
+                assert!((wave(t + 1.) - value).abs() < 1e-9, "{} at {}", name, t);
+                assert!((wave(t - 3.) - value).abs() < 1e-9, "{} at {}", name, t);
+                min = min.min(value);
+                max = max.max(value);
+            }
+            assert!((-1. ..-0.99).contains(&min), "{} min {}", name, min);
+            assert!((0.99..=1.).contains(&max), "{} max {}", name, max);
+        }
+    }
+
+    #[test]
+    fn waves_start_at_zero_and_rise() {
+        // The square wave is the exception, see below.
+        for (name, wave) in &WAVES[..3] {
+            assert!(wave(0.).abs() < 1e-12, "{}", name);
+            assert!(wave(0.01) > wave(0.), "{}", name);
+        }
+        assert_eq!(square_wave(0.), 1.);
+        assert_eq!(square_wave(0.5), -1.);
+    }
+
+    fn value(app: &App, node_id: NodeId) -> f64 {
+        let port = &app.get_node(node_id).unwrap().get_ports()[VALUE];
+        *app.get_cache().get::<f64>(port.get_cache_index()).unwrap()
+    }
+
+    #[test]
+    fn phase_is_in_periods_and_follows_the_clock() {
+        let mut app = nodes::new_app().with_timestep(0.125).unwrap();
+        let node = app.add_node(sine::NAME);
+        app.set_value(node, PHASE, 0.25);
+        app.set_value(node, AMPLITUDE, 2.);
+        app.set_value(node, OFFSET, 1.);
+        app.step();
+        // A quarter period in, the sine is at its peak.
+        assert!((value(&app, node) - 3.).abs() < 1e-9);
+
+        // Another quarter period (2 steps of 1/8 s at 1 Hz) later, it's back at the offset.
+        app.step();
+        app.step();
+        assert!((value(&app, node) - 1.).abs() < 1e-9);
+    }
+}
@@ -1,13 +1,17 @@
+#[cfg(feature = "gui")]
 use std::{cell::RefCell, rc::Rc};
 
+#[cfg(feature = "gui")]
 use druid::{
-    widget::{Container, CrossAxisAlignment, Flex, Label},
+    widget::{Container, CrossAxisAlignment, Flex, Label, Slider},
     Color, Widget, WidgetExt,
 };
 use nalgebra::Vector2;
 
-use super::common::Particle;
-use crate::{core::{App, Cache, CacheIndex, Direction, Node, Port}, gui::{graph_widget::PortDirection, node_widget::NodeWidget, port_widget::PortWidget}};
+use super::common::{Bounds, ForceField, Integrator, Particle};
+use crate::core::{Cache, CacheIndex, Direction, FnBehavior, FrameContext, Node, Port};
+#[cfg(feature = "gui")]
+use crate::{core::{App, NodeId}, gui::{cache_lens::CacheLens, graph_widget::PortDirection, node_widget::NodeWidget, port_widget::PortWidget}};
 
 // Inputs
 const MASS: usize = 0;
@@ -24,6 +28,12 @@ const POSITION: usize = 8;
 const USE_ACCELERATION: usize = 9;
 const USE_VELOCITY: usize = 10;
 const USE_POSITION: usize = 11;
+// Inputs
+/// Picks the [Integrator] by its index in [Integrator::ALL].
+const INTEGRATOR: usize = 12;
+/// Evaluated at the particle's position and velocity wherever the integrator needs it, on top of [FORCE].
+const FORCE_FIELD: usize = 13;
+const BOUNDS: usize = 14;
 
 pub fn node_factory(cache: &mut Cache) -> Node {
     let mass = cache.insert(1.);
@@ -34,129 +44,164 @@ pub fn node_factory(cache: &mut Cache) -> Node {
     let use_acceleration = cache.insert(false);
     let use_velocity = cache.insert(false);
     let use_position = cache.insert(false);
+    let integrator = cache.insert(0.);
+    let force_field = cache.insert(ForceField::new());
+    let bounds = cache.insert(Bounds::unbounded());
 
     let particle = cache.insert(Particle::new());
     let acceleration = cache.insert(Vector2::new(0., 0.));
     let velocity = cache.insert(Vector2::new(0., 0.));
     let position = cache.insert(Vector2::new(0., 0.));
 
-    let mut ports = Vec::new();
-    ports.push(Port::new(mass, Direction::Input));
-    ports.push(Port::new(force, Direction::Input));
-    ports.push(Port::new(set_acceleration, Direction::Input));
-    ports.push(Port::new(set_velocity, Direction::Input));
-    ports.push(Port::new(set_position, Direction::Input));
-    ports.push(Port::new(particle, Direction::Output));
-    ports.push(Port::new(acceleration, Direction::Output));
-    ports.push(Port::new(velocity, Direction::Output));
-    ports.push(Port::new(position, Direction::Output));
-    // TODO: these 3 "inputs" could probably just be replaced by the is_connected parameter of the [Port].
-    ports.push(Port::new(use_acceleration, Direction::Input));
-    ports.push(Port::new(use_velocity, Direction::Input));
-    ports.push(Port::new(use_position, Direction::Input));
+    let ports = vec![
+        Port::new("Mass", mass, Direction::Input),
+        Port::new("Force", force, Direction::Input),
+        Port::new("Set Acceleration", set_acceleration, Direction::Input),
+        Port::new("Set Velocity", set_velocity, Direction::Input),
+        Port::new("Set Position", set_position, Direction::Input),
+        Port::new("Particle", particle, Direction::Output),
+        Port::new("Acceleration", acceleration, Direction::Output),
+        Port::new("Velocity", velocity, Direction::Output),
+        Port::new("Position", position, Direction::Output),
+        // TODO: these 3 "inputs" could probably just be replaced by the is_connected parameter of the [Port].
+        Port::new("Use Acceleration", use_acceleration, Direction::Input),
+        Port::new("Use Velocity", use_velocity, Direction::Input),
+        Port::new("Use Position", use_position, Direction::Input),
+        Port::new("Integrator", integrator, Direction::Input),
+        Port::new("Force Field", force_field, Direction::Input),
+        Port::new("Bounds", bounds, Direction::Input),
+    ];
 
+    Node::new(
+        ports,
+        FnBehavior::new(remove_all_cache)
+            .with_init(init)
+            .with_compute(compute)
+            .with_create_remove_input_cache(disconnect, connect),
+    )
+    .with_internal_state()
+}
 
-    Node::new(ports, remove_all_cache)
-        .with_compute(compute)
-        .with_create_remove_input_cache(disconnect, connect)
+/// Puts the particle back at rest at the origin.
+fn init(ports: &[Port], cache: &mut Cache) {
+    cache.set(ports[PARTICLE].get_cache_index(), Particle::new());
+    cache.set(ports[ACCELERATION].get_cache_index(), Vector2::new(0., 0.));
+    cache.set(ports[VELOCITY].get_cache_index(), Vector2::new(0., 0.));
+    cache.set(ports[POSITION].get_cache_index(), Vector2::new(0., 0.));
 }
 
-fn compute(ports: &Vec<Port>, cache: &mut Cache) {
+fn compute(ports: &[Port], cache: &mut Cache, context: &FrameContext) {
     *cache
-        .get_mut::<Particle>(&ports[PARTICLE].get_cache_index())
+        .get_mut::<Particle>(ports[PARTICLE].get_cache_index())
         .unwrap()
-        .get_mut_mass() = cache.get::<f64>(&ports[MASS].get_cache_index()).unwrap().clone();
+        .get_mut_mass() = *cache.get::<f64>(ports[MASS].get_cache_index()).unwrap();
 
-    if *cache.get::<bool>(&ports[USE_POSITION].get_cache_index()).unwrap() {
-        let set_position = cache.get::<Vector2<f64>>(&ports[SET_POSITION].get_cache_index()).unwrap();
+    if *cache.get::<bool>(ports[USE_POSITION].get_cache_index()).unwrap() {
+        let set_position = cache.get::<Vector2<f64>>(ports[SET_POSITION].get_cache_index()).unwrap();
         *cache
-            .get_mut::<Particle>(&ports[PARTICLE].get_cache_index())
+            .get_mut::<Particle>(ports[PARTICLE].get_cache_index())
             .unwrap()
-            .get_mut_position() = set_position.clone();
-    } else if *cache.get::<bool>(&ports[USE_VELOCITY].get_cache_index()).unwrap() {
-        let set_velocity = cache.get::<Vector2<f64>>(&ports[SET_VELOCITY].get_cache_index()).unwrap();
+            .get_mut_position() = *set_position;
+    } else if *cache.get::<bool>(ports[USE_VELOCITY].get_cache_index()).unwrap() {
+        let set_velocity = cache.get::<Vector2<f64>>(ports[SET_VELOCITY].get_cache_index()).unwrap();
         *cache
-            .get_mut::<Particle>(&ports[PARTICLE].get_cache_index())
+            .get_mut::<Particle>(ports[PARTICLE].get_cache_index())
             .unwrap()
-            .get_mut_velocity() = set_velocity.clone();
-    } else if *cache.get::<bool>(&ports[USE_ACCELERATION].get_cache_index()).unwrap() {
+            .get_mut_velocity() = *set_velocity;
+    } else if *cache.get::<bool>(ports[USE_ACCELERATION].get_cache_index()).unwrap() {
         let set_acceleration = cache
-            .get::<Vector2<f64>>(&ports[SET_ACCELERATION].get_cache_index())
+            .get::<Vector2<f64>>(ports[SET_ACCELERATION].get_cache_index())
             .unwrap();
         *cache
-            .get_mut::<Particle>(&ports[PARTICLE].get_cache_index())
+            .get_mut::<Particle>(ports[PARTICLE].get_cache_index())
             .unwrap()
-            .get_mut_acceleration() = set_acceleration.clone();
+            .get_mut_acceleration() = *set_acceleration;
     } else {
-        let force = cache.get::<Vector2<f64>>(&ports[FORCE].get_cache_index()).unwrap().clone();
+        let force = *cache.get::<Vector2<f64>>(ports[FORCE].get_cache_index()).unwrap();
         cache
-            .get_mut::<Particle>(&ports[PARTICLE].get_cache_index())
+            .get_mut::<Particle>(ports[PARTICLE].get_cache_index())
             .unwrap()
             .apply_force(force);
     }
 
-    cache
-        .get_mut::<Particle>(&ports[PARTICLE].get_cache_index())
-        .unwrap()
-        .update();
+    let integrator = Integrator::from_index(*cache.get::<f64>(ports[INTEGRATOR].get_cache_index()).unwrap());
+    let force_field = cache.get::<ForceField>(ports[FORCE_FIELD].get_cache_index()).unwrap().clone();
+    let bounds = cache.get::<Bounds>(ports[BOUNDS].get_cache_index()).unwrap().clone();
+    let particle = cache.get_mut::<Particle>(ports[PARTICLE].get_cache_index()).unwrap();
+    let mass = *particle.get_mass();
+    particle.integrate_with(integrator, context.dt, |position, velocity| {
+        force_field.force_at(position, velocity, mass)
+    });
+    force_field.constrain(particle, context.dt);
+    if !bounds.apply(particle) {
+        // A particle node can't die, so it starts over at rest at the origin instead.
+        *particle.get_mut_position() = Vector2::new(0., 0.);
+        *particle.get_mut_velocity() = Vector2::new(0., 0.);
+    }
 
-    let particle = cache.get::<Particle>(&ports[PARTICLE].get_cache_index()).unwrap();
+    let particle = cache.get::<Particle>(ports[PARTICLE].get_cache_index()).unwrap();
     let acceleration = *particle.get_acceleration();
     let velocity = *particle.get_velocity();
     let position = *particle.get_position();
 
     *cache
-        .get_mut::<Vector2<f64>>(&ports[ACCELERATION].get_cache_index())
+        .get_mut::<Vector2<f64>>(ports[ACCELERATION].get_cache_index())
         .unwrap() = acceleration;
-    *cache.get_mut::<Vector2<f64>>(&ports[VELOCITY].get_cache_index()).unwrap() = velocity;
-    *cache.get_mut::<Vector2<f64>>(&ports[POSITION].get_cache_index()).unwrap() = position;
+    *cache.get_mut::<Vector2<f64>>(ports[VELOCITY].get_cache_index()).unwrap() = velocity;
+    *cache.get_mut::<Vector2<f64>>(ports[POSITION].get_cache_index()).unwrap() = position;
 }
 
-fn connect(node: &Node, port: usize, cache: &mut Cache) {
+fn connect(ports: &[Port], port: usize, cache: &mut Cache) {
     match port {
-        MASS => cache.remove::<f64>(&node.get_ports()[MASS].get_cache_index()),
-        FORCE => cache.remove::<Vector2<f64>>(&node.get_ports()[FORCE].get_cache_index()),
+        MASS => cache.remove::<f64>(ports[MASS].get_cache_index()),
+        FORCE => cache.remove::<Vector2<f64>>(ports[FORCE].get_cache_index()),
+        INTEGRATOR => cache.remove::<f64>(ports[INTEGRATOR].get_cache_index()),
+        FORCE_FIELD => cache.remove::<ForceField>(ports[FORCE_FIELD].get_cache_index()),
+        BOUNDS => cache.remove::<Bounds>(ports[BOUNDS].get_cache_index()),
         SET_ACCELERATION => {
             *cache
-                .get_mut::<bool>(&node.get_ports()[USE_ACCELERATION].get_cache_index())
+                .get_mut::<bool>(ports[USE_ACCELERATION].get_cache_index())
                 .unwrap() = true;
-            cache.remove::<Vector2<f64>>(&node.get_ports()[SET_ACCELERATION].get_cache_index())
+            cache.remove::<Vector2<f64>>(ports[SET_ACCELERATION].get_cache_index())
         }
         SET_VELOCITY => {
             *cache
-                .get_mut::<bool>(&node.get_ports()[USE_VELOCITY].get_cache_index())
+                .get_mut::<bool>(ports[USE_VELOCITY].get_cache_index())
                 .unwrap() = true;
-            cache.remove::<Vector2<f64>>(&node.get_ports()[SET_VELOCITY].get_cache_index())
+            cache.remove::<Vector2<f64>>(ports[SET_VELOCITY].get_cache_index())
         }
         SET_POSITION => {
             *cache
-                .get_mut::<bool>(&node.get_ports()[USE_POSITION].get_cache_index())
+                .get_mut::<bool>(ports[USE_POSITION].get_cache_index())
                 .unwrap() = true;
-            cache.remove::<Vector2<f64>>(&node.get_ports()[SET_POSITION].get_cache_index())
+            cache.remove::<Vector2<f64>>(ports[SET_POSITION].get_cache_index())
         }
         _ => (),
     }
 }
 
-fn disconnect(node: &Node, port: usize, cache: &mut Cache) -> Option<CacheIndex> {
+fn disconnect(ports: &[Port], port: usize, cache: &mut Cache) -> Option<CacheIndex> {
     match port {
         MASS => Some(cache.insert(1.)),
         FORCE => Some(cache.insert(Vector2::new(0., 0.))),
+        INTEGRATOR => Some(cache.insert(0.)),
+        FORCE_FIELD => Some(cache.insert(ForceField::new())),
+        BOUNDS => Some(cache.insert(Bounds::unbounded())),
         SET_ACCELERATION => {
             *cache
-                .get_mut::<bool>(&node.get_ports()[USE_ACCELERATION].get_cache_index())
+                .get_mut::<bool>(ports[USE_ACCELERATION].get_cache_index())
                 .unwrap() = false;
             Some(cache.insert(Vector2::new(0., 0.)))
         }
         SET_VELOCITY => {
             *cache
-                .get_mut::<bool>(&node.get_ports()[USE_VELOCITY].get_cache_index())
+                .get_mut::<bool>(ports[USE_VELOCITY].get_cache_index())
                 .unwrap() = false;
             Some(cache.insert(Vector2::new(0., 0.)))
         }
         SET_POSITION => {
             *cache
-                .get_mut::<bool>(&node.get_ports()[USE_POSITION].get_cache_index())
+                .get_mut::<bool>(ports[USE_POSITION].get_cache_index())
                 .unwrap() = false;
             Some(cache.insert(Vector2::new(0., 0.)))
         }
@@ -164,22 +209,26 @@ fn disconnect(node: &Node, port: usize, cache: &mut Cache) -> Option<CacheIndex>
     }
 }
 
-fn remove_all_cache(ports: &Vec<Port>, cache: &mut Cache) {
-    cache.remove::<f64>(&ports[MASS].get_cache_index());
-    cache.remove::<Vector2<f64>>(&ports[FORCE].get_cache_index());
-    cache.remove::<Vector2<f64>>(&ports[SET_ACCELERATION].get_cache_index());
-    cache.remove::<Vector2<f64>>(&ports[SET_VELOCITY].get_cache_index());
-    cache.remove::<Vector2<f64>>(&ports[SET_POSITION].get_cache_index());
-    cache.remove::<bool>(&ports[USE_ACCELERATION].get_cache_index());
-    cache.remove::<bool>(&ports[USE_POSITION].get_cache_index());
-    cache.remove::<bool>(&ports[USE_VELOCITY].get_cache_index());
-    cache.remove::<Particle>(&ports[PARTICLE].get_cache_index());
-    cache.remove::<Vector2<f64>>(&ports[ACCELERATION].get_cache_index());
-    cache.remove::<Vector2<f64>>(&ports[VELOCITY].get_cache_index());
-    cache.remove::<Vector2<f64>>(&ports[POSITION].get_cache_index());
+fn remove_all_cache(ports: &[Port], cache: &mut Cache) {
+    cache.remove::<f64>(ports[MASS].get_cache_index());
+    cache.remove::<Vector2<f64>>(ports[FORCE].get_cache_index());
+    cache.remove::<Vector2<f64>>(ports[SET_ACCELERATION].get_cache_index());
+    cache.remove::<Vector2<f64>>(ports[SET_VELOCITY].get_cache_index());
+    cache.remove::<Vector2<f64>>(ports[SET_POSITION].get_cache_index());
+    cache.remove::<bool>(ports[USE_ACCELERATION].get_cache_index());
+    cache.remove::<bool>(ports[USE_POSITION].get_cache_index());
+    cache.remove::<bool>(ports[USE_VELOCITY].get_cache_index());
+    cache.remove::<f64>(ports[INTEGRATOR].get_cache_index());
+    cache.remove::<ForceField>(ports[FORCE_FIELD].get_cache_index());
+    cache.remove::<Bounds>(ports[BOUNDS].get_cache_index());
+    cache.remove::<Particle>(ports[PARTICLE].get_cache_index());
+    cache.remove::<Vector2<f64>>(ports[ACCELERATION].get_cache_index());
+    cache.remove::<Vector2<f64>>(ports[VELOCITY].get_cache_index());
+    cache.remove::<Vector2<f64>>(ports[POSITION].get_cache_index());
 }
 
-pub fn widget_factory(index: usize) -> Box<dyn Widget<Rc<RefCell<App>>>> {
+#[cfg(feature = "gui")]
+pub fn widget_factory(index: NodeId) -> Box<dyn Widget<Rc<RefCell<App>>>> {
     Box::new(NodeWidget::new(
         Container::new(
             Flex::column()
@@ -215,6 +264,30 @@ pub fn widget_factory(index: usize) -> Box<dyn Widget<Rc<RefCell<App>>>> {
                                 .with_child(Label::new("Force")),
                         )
                         .with_spacer(5.)
+                        .with_child(
+                            Flex::row()
+                                .with_child(PortWidget::new(
+                                    index,
+                                    FORCE_FIELD,
+                                    PortDirection::Input,
+                                    PortWidget::FORCE_FIELD,
+                                ))
+                                .with_spacer(5.)
+                                .with_child(Label::new("Force Field")),
+                        )
+                        .with_spacer(5.)
+                        .with_child(
+                            Flex::row()
+                                .with_child(PortWidget::new(
+                                    index,
+                                    BOUNDS,
+                                    PortDirection::Input,
+                                    PortWidget::BOUNDS,
+                                ))
+                                .with_spacer(5.)
+                                .with_child(Label::new("Bounds")),
+                        )
+                        .with_spacer(5.)
                         .with_child(
                             Flex::row()
                                 .with_child(PortWidget::new(
@@ -253,6 +326,23 @@ pub fn widget_factory(index: usize) -> Box<dyn Widget<Rc<RefCell<App>>>> {
                                 // .with_child(TextBox::new().lens(StringInputLens("string"))),
                                 .with_child(Label::new("Set Acceleration")),
                         )
+                        .with_spacer(5.)
+                        .with_child(
+                            Flex::row()
+                                .with_child(PortWidget::new(
+                                    index,
+                                    INTEGRATOR,
+                                    PortDirection::Input,
+                                    PortWidget::F64,
+                                ))
+                                .with_spacer(5.)
+                                .with_child(Label::new("Integrator"))
+                                .with_child(
+                                    Slider::new()
+                                        .with_range(0., (Integrator::ALL.len() - 1) as f64)
+                                        .lens(CacheLens::<f64>::new(index, INTEGRATOR)),
+                                ),
+                        )
                         .expand_width(),
                 )
                 .with_spacer(5.)
@@ -0,0 +1,116 @@
+use nalgebra::Vector2;
+
+use super::common::random::{self, Rng};
+
+define_node! {
+    name: "Random",
+    internal_state_if: PER_FRAME,
+    inputs: {
+        SEED(seed): f64 = 0. => "Seed" [0., 100.],
+        MIN(min): f64 = 0. => "Min",
+        MAX(max): f64 = 1. => "Max",
+        NORMAL(normal): bool = false => "Normal",
+        PER_FRAME(per_frame): bool = false => "Per Frame",
+    },
+    outputs: {
+        VALUE(value): f64 = 0. => "Value",
+        VECTOR(vector): Vector2<f64> = Vector2::new(0., 0.) => "Vector",
+    },
+    compute(context) {
+        // The salt keeps nodes with the same seed independent of each other.
+        let frame = if per_frame { context.frame + 1 } else { 0 };
+        let mut rng = Rng::new(random::hash(&[context.seed, context.salt, random::seed_from_f64(seed), frame]));
+        // Normally distributed values are centred in the range, with 99.7% of them (3 standard deviations) inside it.
+        let mut sample = || {
+            if normal {
+                rng.normal((min + max) / 2., (max - min).abs() / 6.)
+            } else {
+                rng.range(min, max)
+            }
+        };
+        value = sample();
+        vector = Vector2::new(sample(), sample());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{App, Edit, NodeId},
+        nodes,
+    };
+
+    fn value(app: &App, node_id: NodeId) -> f64 {
+        let port = &app.get_node(node_id).unwrap().get_ports()[VALUE];
+        *app.get_cache().get::<f64>(port.get_cache_index()).unwrap()
+    }
+
+    #[test]
+    fn nodes_with_same_seed_differ() {
+        let mut app = nodes::new_app();
+        let a = app.add_node(NAME);
+        let b = app.add_node(NAME);
+        app.compute();
+        assert_ne!(value(&app, a), value(&app, b));
+    }
+
+    #[test]
+    fn values_survive_save_and_undo() {
+        let mut app = nodes::new_app();
+        let removed = app.add_node(NAME);
+        let a = app.add_node(NAME);
+        let b = app.add_node(NAME);
+        app.remove_node(removed);
+        app.compute();
+        let (a_value, b_value) = (value(&app, a), value(&app, b));
+
+        let mut loaded_app = nodes::new_app();
+        let loaded = loaded_app.load(&app.save().to_json()).unwrap();
+        // A new node mustn't share its salt with any loaded one.
+        let c = loaded_app.add_node(NAME);
+        loaded_app.compute();
+        assert_eq!(value(&loaded_app, loaded.nodes[0].node_id), a_value);
+        assert_eq!(value(&loaded_app, loaded.nodes[1].node_id), b_value);
+        assert_ne!(value(&loaded_app, c), a_value);
+        assert_ne!(value(&loaded_app, c), b_value);
+
+        app.edit(Edit::RemoveNode(a)).unwrap();
+        assert!(app.undo());
+        app.compute();
+        assert_eq!(value(&app, a), a_value);
+    }
+
+    #[test]
+    fn constant_values_are_only_computed_once() {
+        let mut app = nodes::new_app();
+        let node = app.add_node(NAME);
+        app.compute();
+        let revision = |app: &App| {
+            let port = &app.get_node(node).unwrap().get_ports()[VALUE];
+            app.get_cache().revision(port.get_cache_index()).unwrap()
+        };
+        let first = revision(&app);
+        app.compute();
+        assert_eq!(revision(&app), first);
+
+        // Changing the graph seed computes them again.
+        let old_value = value(&app, node);
+        app.set_seed(1);
+        app.compute();
+        assert!(revision(&app) > first);
+        assert_ne!(value(&app, node), old_value);
+    }
+
+    #[test]
+    fn per_frame_values_change() {
+        let mut app = nodes::new_app();
+        let node = app.add_node(NAME);
+        app.set_value(node, PER_FRAME, true);
+        app.compute();
+        let first = value(&app, node);
+        app.compute();
+        assert_ne!(value(&app, node), first);
+        assert!((0. ..1.).contains(&first));
+    }
+}
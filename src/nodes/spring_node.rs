@@ -0,0 +1,112 @@
+//! Ties two particles together. Connect each particle's output to an input here, and each force field back to the
+//! particle's "Force Field" input; the edges closing the loop become feedback edges, so the forces are one frame
+//! behind. Chains of springs make ropes, grids of them cloth and soft bodies. Raising the rigidity also moves the
+//! particles back towards the rest length after they've moved, which holds stiff ropes together where a strong spring
+//! would overshoot.
+
+use super::common::{Constraint, Force, ForceField, Particle};
+
+define_node! {
+    name: "Spring",
+    inputs: {
+        PARTICLE_A(particle_a): Particle = Particle::new() => "Particle A",
+        PARTICLE_B(particle_b): Particle = Particle::new() => "Particle B",
+        REST_LENGTH(rest_length): f64 = 50. => "Rest Length" [0., 500.],
+        STIFFNESS(stiffness): f64 = 50. => "Stiffness" [0., 1000.],
+        DAMPING(damping): f64 = 1. => "Damping" [0., 50.],
+        RIGIDITY(rigidity): f64 = 0. => "Rigidity" [0., 1.],
+    },
+    outputs: {
+        FORCE_FIELD_A(force_field_a): ForceField = ForceField::new() => "Force Field A",
+        FORCE_FIELD_B(force_field_b): ForceField = ForceField::new() => "Force Field B",
+        LENGTH(length): f64 = 0. => "Length",
+    },
+    compute(_context) {
+        let rest_length = rest_length.max(0.);
+        // Each particle is pulled towards the other, so the forces are equal and opposite.
+        let spring = |anchor: &Particle| Force::Spring {
+            anchor: *anchor.get_position(),
+            anchor_velocity: *anchor.get_velocity(),
+            rest_length,
+            stiffness,
+            damping,
+        };
+        force_field_a = ForceField::from_force(spring(&particle_b));
+        force_field_b = ForceField::from_force(spring(&particle_a));
+
+        let rigidity = rigidity.clamp(0., 1.);
+        if rigidity > 0. {
+            // The correction is shared so that the lighter particle moves further, like it would for a force.
+            let total_mass = particle_a.get_mass() + particle_b.get_mass();
+            let share_a = if total_mass > 0. { particle_b.get_mass() / total_mass } else { 0.5 };
+            let distance = |anchor: &Particle, strength: f64| Constraint::Distance {
+                anchor: *anchor.get_position(),
+                rest_length,
+                strength,
+            };
+            let constraint_a = distance(&particle_b, rigidity * share_a);
+            let constraint_b = distance(&particle_a, rigidity * (1. - share_a));
+            force_field_a = force_field_a.combine(&ForceField::from_constraint(constraint_a));
+            force_field_b = force_field_b.combine(&ForceField::from_constraint(constraint_b));
+        }
+        length = (particle_a.get_position() - particle_b.get_position()).norm();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+
+    use super::*;
+    use crate::{
+        core::{App, NodeId},
+        nodes,
+    };
+
+    fn port(app: &App, node_id: NodeId, name: &str) -> usize {
+        let ports = app.get_node(node_id).unwrap().get_ports();
+        ports.iter().position(|port| port.get_name() == name).unwrap()
+    }
+
+    /// Two particles [distance] apart, tied together by a spring without any stiffness.
+    fn rope(distance: f64, rigidity: f64) -> (App, NodeId) {
+        let mut app = nodes::new_app();
+        let spring = app.add_node(NAME);
+        let ends = [(0., PARTICLE_A, FORCE_FIELD_A), (distance, PARTICLE_B, FORCE_FIELD_B)];
+        for (x, particle_port, force_field_port) in ends {
+            let particle = app.add_node("Particle");
+            let mut start = Particle::new();
+            *start.get_mut_position() = Vector2::new(x, 0.);
+            app.set_value(particle, port(&app, particle, "Particle"), start);
+            app.add_edge(particle, port(&app, particle, "Particle"), spring, particle_port).unwrap();
+            app.add_edge(spring, force_field_port, particle, port(&app, particle, "Force Field")).unwrap();
+        }
+        app.set_value(spring, STIFFNESS, 0.);
+        app.set_value(spring, DAMPING, 0.);
+        app.set_value(spring, RIGIDITY, rigidity);
+        (app, spring)
+    }
+
+    fn length(app: &App, spring: NodeId) -> f64 {
+        let port = &app.get_node(spring).unwrap().get_ports()[LENGTH];
+        *app.get_cache().get::<f64>(port.get_cache_index()).unwrap()
+    }
+
+    #[test]
+    fn rigidity_pulls_particles_to_rest_length() {
+        let (mut app, spring) = rope(100., 1.);
+        for _ in 0..60 {
+            app.compute();
+        }
+        assert!((length(&app, spring) - 50.).abs() < 1e-3, "{}", length(&app, spring));
+    }
+
+    #[test]
+    fn without_rigidity_or_stiffness_nothing_moves() {
+        let (mut app, spring) = rope(100., 0.);
+        for _ in 0..10 {
+            app.compute();
+        }
+        assert_eq!(length(&app, spring), 100.);
+    }
+}
@@ -0,0 +1,39 @@
+define_node! {
+    name: "Time",
+    internal_state: true,
+    inputs: {},
+    outputs: {
+        FRAME(frame): f64 = 0. => "Frame",
+        SECONDS(seconds): f64 = 0. => "Seconds",
+        DT(dt): f64 = 0. => "Dt",
+    },
+    compute(context) {
+        frame = context.frame as f64;
+        seconds = context.time;
+        dt = context.dt;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes;
+
+    #[test]
+    fn outputs_follow_the_clock() {
+        let mut app = nodes::new_app().with_timestep(0.5).unwrap();
+        let node = app.add_node(NAME);
+        for _ in 0..3 {
+            app.step();
+        }
+        let output = |port: usize| {
+            let port = &app.get_node(node).unwrap().get_ports()[port];
+            *app.get_cache().get::<f64>(port.get_cache_index()).unwrap()
+        };
+        // The outputs describe the frame which was just computed, the clock has already moved on to the next one.
+        assert_eq!(app.get_clock().get_frame(), 3);
+        assert_eq!(output(FRAME), 2.);
+        assert_eq!(output(SECONDS), 1.);
+        assert_eq!(output(DT), 0.5);
+    }
+}
@@ -1,12 +1,16 @@
+#[cfg(feature = "gui")]
 use std::{cell::RefCell, rc::Rc};
 
+#[cfg(feature = "gui")]
 use druid::{
     widget::{Container, CrossAxisAlignment, Flex, Label, Slider, Stepper},
     Color, Widget, WidgetExt,
 };
 
+use crate::core::{Cache, Direction, FnBehavior, Node, Port};
+#[cfg(feature = "gui")]
 use crate::{
-    core::{App, Cache, Direction, Node, Port},
+    core::{App, NodeId},
     gui::{
         cache_lens::CacheLens, graph_widget::PortDirection, node_widget::NodeWidget,
         port_widget::PortWidget,
@@ -19,17 +23,17 @@ const VALUE: usize = 0;
 pub fn node_factory(cache: &mut Cache) -> Node {
     let value = cache.insert(0.);
 
-    let mut ports = Vec::new();
-    ports.push(Port::new(value, Direction::Output));
+    let ports = vec![Port::new("Value", value, Direction::Output)];
 
-    Node::new(ports, remove_all_cache)
+    Node::new(ports, FnBehavior::new(remove_all_cache))
 }
 
-fn remove_all_cache(ports: &Vec<Port>, cache: &mut Cache) {
+fn remove_all_cache(ports: &[Port], cache: &mut Cache) {
     cache.remove::<f64>(ports[VALUE].get_cache_index());
 }
 
-pub fn widget_factory(index: usize) -> Box<dyn Widget<Rc<RefCell<App>>>> {
+#[cfg(feature = "gui")]
+pub fn widget_factory(index: NodeId) -> Box<dyn Widget<Rc<RefCell<App>>>> {
     Box::new(NodeWidget::new(
         Container::new(
             Flex::column()
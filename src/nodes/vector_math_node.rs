@@ -0,0 +1,274 @@
+//! Arithmetic on [Vector2](nalgebra::Vector2)s. As with [math_node](super::math_node), operations which aren't
+//! defined for some inputs (e.g. normalising a zero vector) output 0 instead of NaN.
+
+/// Defines a node with vector inputs `A` and `B` and a single output, in its own module.
+macro_rules! define_binary {
+    ($module:ident, $name:literal, $output_ty:ty, $output_default:expr, |$a:ident, $b:ident| $operation:expr) => {
+        pub mod $module {
+            use nalgebra::Vector2;
+
+            define_node! {
+                name: $name,
+                inputs: {
+                    A($a): Vector2<f64> = Vector2::new(0., 0.) => "A",
+                    B($b): Vector2<f64> = Vector2::new(0., 0.) => "B",
+                },
+                outputs: {
+                    RESULT(result): $output_ty = $output_default => "Result",
+                },
+                compute(_context) {
+                    result = $operation;
+                }
+            }
+        }
+    };
+}
+
+define_binary!(add, "Vector Add", Vector2<f64>, Vector2::new(0., 0.), |a, b| a + b);
+define_binary!(subtract, "Vector Subtract", Vector2<f64>, Vector2::new(0., 0.), |a, b| a - b);
+define_binary!(dot, "Dot", f64, 0., |a, b| a.dot(&b));
+// The z component of the cross product of the two vectors extended to 3D.
+define_binary!(cross, "Cross", f64, 0., |a, b| a.perp(&b));
+define_binary!(distance, "Distance", f64, 0., |a, b| (b - a).norm());
+
+pub mod split {
+    use nalgebra::Vector2;
+
+    define_node! {
+        name: "Split Vector",
+        inputs: {
+            VECTOR(vector): Vector2<f64> = Vector2::new(0., 0.) => "Vector",
+        },
+        outputs: {
+            X(x): f64 = 0. => "X",
+            Y(y): f64 = 0. => "Y",
+        },
+        compute(_context) {
+            x = vector.x;
+            y = vector.y;
+        }
+    }
+}
+
+pub mod scale {
+    use nalgebra::Vector2;
+
+    define_node! {
+        name: "Vector Scale",
+        inputs: {
+            VECTOR(vector): Vector2<f64> = Vector2::new(0., 0.) => "Vector",
+            FACTOR(factor): f64 = 1. => "Factor",
+        },
+        outputs: {
+            RESULT(result): Vector2<f64> = Vector2::new(0., 0.) => "Result",
+        },
+        compute(_context) {
+            result = vector * factor;
+        }
+    }
+}
+
+pub mod length {
+    use nalgebra::Vector2;
+
+    define_node! {
+        name: "Length",
+        inputs: {
+            VECTOR(vector): Vector2<f64> = Vector2::new(0., 0.) => "Vector",
+        },
+        outputs: {
+            LENGTH(length): f64 = 0. => "Length",
+        },
+        compute(_context) {
+            length = vector.norm();
+        }
+    }
+}
+
+pub mod normalize {
+    use nalgebra::Vector2;
+
+    define_node! {
+        name: "Normalize",
+        inputs: {
+            VECTOR(vector): Vector2<f64> = Vector2::new(0., 0.) => "Vector",
+        },
+        outputs: {
+            RESULT(result): Vector2<f64> = Vector2::new(0., 0.) => "Result",
+        },
+        compute(_context) {
+            result = vector.try_normalize(0.).unwrap_or_else(Vector2::zeros);
+        }
+    }
+}
+
+/// Rotates counter-clockwise by an angle in radians.
+pub mod rotate {
+    use nalgebra::Vector2;
+
+    define_node! {
+        name: "Rotate",
+        inputs: {
+            VECTOR(vector): Vector2<f64> = Vector2::new(0., 0.) => "Vector",
+            ANGLE(angle): f64 = 0. => "Angle",
+        },
+        outputs: {
+            RESULT(result): Vector2<f64> = Vector2::new(0., 0.) => "Result",
+        },
+        compute(_context) {
+            let (sin, cos) = angle.sin_cos();
+            result = Vector2::new(vector.x * cos - vector.y * sin, vector.x * sin + vector.y * cos);
+        }
+    }
+}
+
+pub mod from_polar {
+    use nalgebra::Vector2;
+
+    define_node! {
+        name: "From Polar",
+        inputs: {
+            ANGLE(angle): f64 = 0. => "Angle",
+            MAGNITUDE(magnitude): f64 = 1. => "Magnitude",
+        },
+        outputs: {
+            VECTOR(vector): Vector2<f64> = Vector2::new(1., 0.) => "Vector",
+        },
+        compute(_context) {
+            vector = Vector2::new(angle.cos(), angle.sin()) * magnitude;
+        }
+    }
+}
+
+pub mod lerp {
+    use nalgebra::Vector2;
+
+    define_node! {
+        name: "Vector Lerp",
+        inputs: {
+            A(a): Vector2<f64> = Vector2::new(0., 0.) => "A",
+            B(b): Vector2<f64> = Vector2::new(0., 0.) => "B",
+            T(t): f64 = 0.5 => "T",
+        },
+        outputs: {
+            RESULT(result): Vector2<f64> = Vector2::new(0., 0.) => "Result",
+        },
+        compute(_context) {
+            result = a + (b - a) * t;
+        }
+    }
+}
+
+/// Scales the vector down to `Max Length` if it is longer, e.g. to cap a particle's speed.
+pub mod limit_length {
+    use nalgebra::Vector2;
+
+    use crate::nodes::common::limit_length;
+
+    define_node! {
+        name: "Limit Length",
+        inputs: {
+            VECTOR(vector): Vector2<f64> = Vector2::new(0., 0.) => "Vector",
+            MAX_LENGTH(max_length): f64 = 1. => "Max Length",
+        },
+        outputs: {
+            RESULT(result): Vector2<f64> = Vector2::new(0., 0.) => "Result",
+        },
+        compute(_context) {
+            result = limit_length(vector, max_length);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    use nalgebra::Vector2;
+
+    use crate::{
+        core::{App, NodeId},
+        nodes,
+    };
+
+    /// Computes a fresh node of type [name] after [set_inputs], returning the value of its output at [port].
+    fn compute<T: Clone + 'static>(name: &'static str, set_inputs: impl FnOnce(&mut App, NodeId), port: usize) -> T {
+        let mut app = nodes::new_app();
+        let node = app.add_node(name);
+        set_inputs(&mut app, node);
+        app.compute();
+        let port = &app.get_node(node).unwrap().get_ports()[port];
+        app.get_cache().get::<T>(port.get_cache_index()).unwrap().clone()
+    }
+
+    fn assert_near(actual: Vector2<f64>, expected: Vector2<f64>) {
+        assert!((actual - expected).norm() < 1e-9, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn rotate_is_counter_clockwise() {
+        let rotate = |vector: Vector2<f64>, angle: f64| {
+            compute::<Vector2<f64>>(
+                super::rotate::NAME,
+                |app, node| {
+                    app.set_value(node, 0, vector);
+                    app.set_value(node, 1, angle);
+                },
+                2,
+            )
+        };
+        assert_near(rotate(Vector2::new(1., 0.), FRAC_PI_2), Vector2::new(0., 1.));
+        assert_near(rotate(Vector2::new(0., 1.), FRAC_PI_2), Vector2::new(-1., 0.));
+        assert_near(rotate(Vector2::new(2., 3.), -PI), Vector2::new(-2., -3.));
+    }
+
+    #[test]
+    fn from_polar_scales_the_unit_vector() {
+        let from_polar = |angle: f64, magnitude: f64| {
+            compute::<Vector2<f64>>(
+                super::from_polar::NAME,
+                |app, node| {
+                    app.set_value(node, 0, angle);
+                    app.set_value(node, 1, magnitude);
+                },
+                2,
+            )
+        };
+        assert_near(from_polar(0., 1.), Vector2::new(1., 0.));
+        assert_near(from_polar(FRAC_PI_2, 3.), Vector2::new(0., 3.));
+        assert_near(from_polar(PI, 2.), Vector2::new(-2., 0.));
+    }
+
+    #[test]
+    fn normalizing_zero_gives_zero() {
+        let normalize = |vector: Vector2<f64>| {
+            compute::<Vector2<f64>>(super::normalize::NAME, |app, node| app.set_value(node, 0, vector), 1)
+        };
+        assert_eq!(normalize(Vector2::new(0., 0.)), Vector2::new(0., 0.));
+        assert_near(normalize(Vector2::new(3., 4.)), Vector2::new(0.6, 0.8));
+    }
+
+    #[test]
+    fn cross_is_positive_counter_clockwise() {
+        let cross = |a: Vector2<f64>, b: Vector2<f64>| {
+            compute::<f64>(
+                super::cross::NAME,
+                |app, node| {
+                    app.set_value(node, 0, a);
+                    app.set_value(node, 1, b);
+                },
+                2,
+            )
+        };
+        assert_eq!(cross(Vector2::new(1., 0.), Vector2::new(0., 1.)), 1.);
+        assert_eq!(cross(Vector2::new(0., 1.), Vector2::new(1., 0.)), -1.);
+        assert_eq!(cross(Vector2::new(2., 2.), Vector2::new(1., 1.)), 0.);
+    }
+
+    #[test]
+    fn split_outputs_components() {
+        let set_inputs = |app: &mut App, node| app.set_value(node, 0, Vector2::new(3., -4.));
+        assert_eq!(compute::<f64>(super::split::NAME, set_inputs, 1), 3.);
+        assert_eq!(compute::<f64>(super::split::NAME, set_inputs, 2), -4.);
+    }
+}